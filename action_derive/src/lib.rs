@@ -0,0 +1,489 @@
+//! Derive the `action6` instances for a new scaffold.
+//!
+//! A scaffold only has to say what type it completes, how to `pure` and `bind`
+//! values of that type, and how to run a loop:
+//!
+//! ```ignore
+//! #[derive(Scaffold)]
+//! #[scaffold(
+//!     complete = "Counted<A>",
+//!     pure = "counted",
+//!     bind = "bind_counted",
+//!     tail_rec = "tail_rec_counted"
+//! )]
+//! struct BuildCounted;
+//!
+//! fn counted<A>(value: A) -> Counted<A> { ... }
+//! fn bind_counted<A, B, F>(value: Counted<A>, func: F) -> Counted<B>
+//! where
+//!     F: Fn(A) -> Counted<B>,
+//! { ... }
+//! fn tail_rec_counted<A, B, F>(init: A, func: F) -> Counted<B>
+//! where
+//!     F: Fn(A) -> Counted<Step<A, B>>,
+//! { ... }
+//! ```
+//!
+//! This generates `Ap`, `Eager`, `Bind`, `Zip` and `TailRec` for the scaffold,
+//! and the run instances for `Map`, `AndThen`, `First`, `Second` and `Combine`. The
+//! actions which have one run instance for every scaffold, such as `Pure`,
+//! `Literal`, `Sequence` and the loops, then run with it too.
+//!
+//! The attribute takes:
+//!
+//! * `complete`: the completed type, where `A` is the type of the value.
+//! * `pure`: a function from `A` to the completed type.
+//! * `bind`: a function from a completed `A` and a function `Fn(A) -> R<B>`
+//!   to a completed `B`.
+//! * `tail_rec`: a function like `TailRec::tail_rec`, which runs a loop in
+//!   constant stack. Binding each iteration to the next would use stack for
+//!   every iteration, so there is no default.
+//! * `writer` and `log` (optional): a function from `A` and a log of type
+//!   `log` to the completed type, which generates `BaseWriter`, so that
+//!   `writer` and `tell` run with the scaffold. They must be given together.
+//! * `bound` (optional): bounds every value type must meet, such as `Clone`.
+//! * `path` (optional): the path to `action6`, `crate::action6` by default.
+//!
+//! The scaffold may have generic parameters, but they must not be called `A`,
+//! `B`, `C`, `F`, `ActA` or `ActB`, and `where` clauses are not supported.
+
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+#[proc_macro_derive(Scaffold, attributes(scaffold))]
+pub fn derive_scaffold(input: TokenStream) -> TokenStream {
+    let result = parse(input).map(|scaffold| scaffold.generate());
+    match result {
+        Ok(code) => code,
+        Err(message) => format!("compile_error!({:?});", message),
+    }
+    .parse()
+    .expect("generated code should be valid tokens")
+}
+
+struct Scaffold {
+    name: String,
+    /// The generic parameters as they are declared, with any bounds.
+    params: Vec<String>,
+    /// The generic parameters as they are used in the scaffold type.
+    args: Vec<String>,
+    complete: String,
+    pure: String,
+    bind: String,
+    tail_rec: String,
+    /// The `writer` function and the type of its log.
+    writer: Option<(String, String)>,
+    bound: Option<String>,
+    path: String,
+}
+
+fn parse(input: TokenStream) -> Result<Scaffold, String> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let mut options = Vec::new();
+    let mut index = 0;
+
+    // Attributes and visibility come before the name.
+    let name = loop {
+        match tokens.get(index) {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '#' => {
+                if let Some(TokenTree::Group(group)) = tokens.get(index + 1) {
+                    options.extend(parse_attribute(group.stream())?);
+                }
+                index += 2;
+            }
+            Some(TokenTree::Ident(ident)) => {
+                let keyword = ident.to_string();
+                index += 1;
+                if keyword == "struct" || keyword == "enum" {
+                    match tokens.get(index) {
+                        Some(TokenTree::Ident(name)) => break name.to_string(),
+                        _ => return Err("expected the name of the scaffold".to_string()),
+                    }
+                }
+            }
+            Some(_) => index += 1,
+            None => return Err("`Scaffold` can only be derived for a struct or enum".to_string()),
+        }
+    };
+    index += 1;
+
+    let (params, args) = match tokens.get(index) {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            parse_generics(&tokens[index + 1..])
+        }
+        _ => (Vec::new(), Vec::new()),
+    };
+
+    let mut complete = None;
+    let mut pure = None;
+    let mut bind = None;
+    let mut tail_rec = None;
+    let mut writer = None;
+    let mut log = None;
+    let mut bound = None;
+    let mut path = "crate::action6".to_string();
+    for (key, value) in options {
+        match key.as_str() {
+            "complete" => complete = Some(value),
+            "pure" => pure = Some(value),
+            "bind" => bind = Some(value),
+            "tail_rec" => tail_rec = Some(value),
+            "writer" => writer = Some(value),
+            "log" => log = Some(value),
+            "bound" => bound = Some(value),
+            "path" => path = value,
+            _ => return Err(format!("unknown scaffold option `{}`", key)),
+        }
+    }
+    let required = |option: Option<String>, key: &str| {
+        option.ok_or_else(|| format!("missing `#[scaffold({} = \"...\")]`", key))
+    };
+    let writer = match (writer, log) {
+        (Some(writer), log) => Some((writer, required(log, "log")?)),
+        (None, Some(_)) => return Err("`log` is only used with `writer`".to_string()),
+        (None, None) => None,
+    };
+
+    Ok(Scaffold {
+        name,
+        params,
+        args,
+        complete: required(complete, "complete")?,
+        pure: required(pure, "pure")?,
+        bind: required(bind, "bind")?,
+        tail_rec: required(tail_rec, "tail_rec")?,
+        writer,
+        bound,
+        path,
+    })
+}
+
+/// Read the `key = "value"` pairs from a `#[scaffold(...)]` attribute. Other
+/// attributes produce no options.
+fn parse_attribute(attribute: TokenStream) -> Result<Vec<(String, String)>, String> {
+    let mut tokens = attribute.into_iter();
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "scaffold" => {}
+        _ => return Ok(Vec::new()),
+    }
+    let arguments = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            group.stream()
+        }
+        _ => return Err("expected `#[scaffold(key = \"value\", ...)]`".to_string()),
+    };
+
+    let mut options = Vec::new();
+    let mut tokens = arguments.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let key = match token {
+            TokenTree::Ident(ident) => ident.to_string(),
+            _ => return Err("expected the name of a scaffold option".to_string()),
+        };
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => {}
+            _ => return Err(format!("expected `=` after `{}`", key)),
+        }
+        let value = match tokens.next() {
+            Some(TokenTree::Literal(literal)) => {
+                let literal = literal.to_string();
+                if literal.len() >= 2 && literal.starts_with('"') && literal.ends_with('"') {
+                    literal[1..literal.len() - 1].to_string()
+                } else {
+                    return Err(format!("expected a string for `{}`", key));
+                }
+            }
+            _ => return Err(format!("expected a string for `{}`", key)),
+        };
+        options.push((key, value));
+        match tokens.next() {
+            None => {}
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => {}
+            _ => return Err("expected `,` between scaffold options".to_string()),
+        }
+    }
+    Ok(options)
+}
+
+/// Split the generic parameters, starting just after the opening `<`, into
+/// their declarations and their names.
+fn parse_generics(tokens: &[TokenTree]) -> (Vec<String>, Vec<String>) {
+    let mut params = Vec::new();
+    let mut args = Vec::new();
+    let mut current: Vec<&TokenTree> = Vec::new();
+    let mut depth = 0;
+    for token in tokens {
+        if let TokenTree::Punct(punct) = token {
+            match punct.as_char() {
+                '>' if follows_minus(&current) => {}
+                '<' => depth += 1,
+                '>' if depth == 0 => break,
+                '>' => depth -= 1,
+                ',' if depth == 0 => {
+                    push_param(&current, &mut params, &mut args);
+                    current.clear();
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        current.push(token);
+    }
+    push_param(&current, &mut params, &mut args);
+    (params, args)
+}
+
+/// Whether the tokens end with a `-` joined to the next token, which makes a
+/// following `>` part of a `->` rather than a closing bracket.
+fn follows_minus(tokens: &[&TokenTree]) -> bool {
+    match tokens.last() {
+        Some(TokenTree::Punct(punct)) => {
+            punct.as_char() == '-' && punct.spacing() == Spacing::Joint
+        }
+        _ => false,
+    }
+}
+
+fn push_param(tokens: &[&TokenTree], params: &mut Vec<String>, args: &mut Vec<String>) {
+    if tokens.is_empty() {
+        return;
+    }
+    // Defaults are only allowed where the type is declared. An `=` inside
+    // angle brackets, as in `Iterator<Item = A>`, is part of a bound instead.
+    let mut depth = 0;
+    let mut end = tokens.len();
+    for (index, token) in tokens.iter().enumerate() {
+        if let TokenTree::Punct(punct) = token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                '>' if !follows_minus(&tokens[..index]) => depth -= 1,
+                '=' if depth == 0 => {
+                    end = index;
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+    // Joint punctuation, such as `->`, `::` or the `'` of a lifetime, must stay
+    // joined to the token after it.
+    let mut declaration = String::new();
+    for token in &tokens[..end] {
+        declaration.push_str(&token.to_string());
+        match token {
+            TokenTree::Punct(punct) if punct.spacing() == Spacing::Joint => {}
+            _ => declaration.push(' '),
+        }
+    }
+    let declaration = declaration.trim_end().to_string();
+    let name = match tokens {
+        [TokenTree::Punct(quote), TokenTree::Ident(name), ..] if quote.as_char() == '\'' => {
+            format!("'{}", name)
+        }
+        [TokenTree::Ident(keyword), TokenTree::Ident(name), ..]
+            if keyword.to_string() == "const" =>
+        {
+            name.to_string()
+        }
+        [first, ..] => first.to_string(),
+        [] => unreachable!(),
+    };
+    params.push(declaration);
+    args.push(name);
+}
+
+impl Scaffold {
+    fn generate(&self) -> String {
+        let path = &self.path;
+        let scaffold = if self.args.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}<{}>", self.name, self.args.join(", "))
+        };
+        // Lifetimes have to be declared before the type parameters.
+        let (lifetimes, params): (Vec<_>, Vec<_>) = self
+            .params
+            .iter()
+            .partition(|param| param.starts_with('\''));
+        let generics = |values: &str| {
+            let mut all: Vec<String> = lifetimes.iter().map(|param| param.to_string()).collect();
+            all.push(values.to_string());
+            all.extend(params.iter().map(|param| param.to_string()));
+            all.join(", ")
+        };
+        let bounds = |types: &[&str]| match &self.bound {
+            Some(bound) => types
+                .iter()
+                .map(|ty| format!("{}: {},", ty, bound))
+                .collect::<String>(),
+            None => String::new(),
+        };
+        let Scaffold {
+            complete,
+            pure,
+            bind,
+            tail_rec,
+            ..
+        } = self;
+        let base_writer = match &self.writer {
+            Some((writer, log)) => format!(
+                "
+                impl<{ap_generics}> {path}::BaseWriter<A, {log}> for {scaffold}
+                where
+                    {bound_a}
+                {{
+                    fn writer(value: A, log: {log}) -> Self::R {{
+                        {writer}(value, log)
+                    }}
+                }}
+                ",
+                path = path,
+                scaffold = scaffold,
+                writer = writer,
+                log = log,
+                ap_generics = generics("A"),
+                bound_a = bounds(&["A"]),
+            ),
+            None => String::new(),
+        };
+
+        format!(
+            "
+            impl<{eager_generics}> {path}::Eager for {scaffold} {{}}
+
+            impl<{ap_generics}> {path}::Ap<A> for {scaffold}
+            where
+                {bound_a}
+            {{
+                type R = {complete};
+                fn build(value: A) -> Self::R {{
+                    {pure}(value)
+                }}
+            }}
+
+            impl<{bind_generics}> {path}::Bind<A, B> for {scaffold}
+            where
+                {bound_ab}
+            {{
+                fn bind<F>(
+                    value: <Self as {path}::Ap<A>>::R,
+                    func: F,
+                ) -> <Self as {path}::Ap<B>>::R
+                where
+                    F: Fn(A) -> <Self as {path}::Ap<B>>::R,
+                {{
+                    {bind}(value, func)
+                }}
+            }}
+
+            impl<{bind_generics}> {path}::Zip<A, B> for {scaffold}
+            where
+                A: Clone,
+                <Self as {path}::Ap<B>>::R: Clone,
+                {bound_zip}
+            {{
+                fn zip(
+                    value_a: <Self as {path}::Ap<A>>::R,
+                    value_b: <Self as {path}::Ap<B>>::R,
+                ) -> <Self as {path}::Ap<(A, B)>>::R {{
+                    <Self as {path}::Bind<A, (A, B)>>::bind(value_a, |result_a| {{
+                        <Self as {path}::Bind<B, (A, B)>>::bind(value_b.clone(), |result_b| {{
+                            <Self as {path}::Ap<(A, B)>>::build((result_a.clone(), result_b))
+                        }})
+                    }})
+                }}
+            }}
+
+            impl<{bind_generics}> {path}::TailRec<A, B> for {scaffold}
+            where
+                {bound_step}
+            {{
+                fn tail_rec<F>(init: A, func: F) -> <Self as {path}::Ap<B>>::R
+                where
+                    F: Fn(A) -> <Self as {path}::Ap<{path}::Step<A, B>>>::R + 'static,
+                {{
+                    {tail_rec}(init, func)
+                }}
+            }}
+
+            impl<{map_generics}> {path}::Run<{scaffold}, B> for {path}::Map<A, ActA, F>
+            where
+                {scaffold}: {path}::Bind<A, B>,
+                ActA: {path}::Run<{scaffold}, A>,
+                F: {path}::MapFn<A, B>,
+            {{
+                fn run(&self) -> <{scaffold} as {path}::Ap<B>>::R {{
+                    self.run_bind::<{scaffold}, B>()
+                }}
+            }}
+
+            impl<{and_then_generics}> {path}::Run<{scaffold}, B>
+                for {path}::AndThen<A, ActA, ActB, F>
+            where
+                {scaffold}: {path}::Bind<A, B>,
+                ActA: {path}::Run<{scaffold}, A>,
+                ActB: {path}::Run<{scaffold}, B>,
+                F: Fn(&A) -> ActB,
+            {{
+                fn run(&self) -> <{scaffold} as {path}::Ap<B>>::R {{
+                    self.run_bind::<{scaffold}, B>()
+                }}
+            }}
+
+            impl<{first_generics}> {path}::Run<{scaffold}, A> for {path}::First<B, ActA, ActB>
+            where
+                {scaffold}: {path}::Zip<A, B> + {path}::Bind<(A, B), A>,
+                ActA: {path}::Run<{scaffold}, A>,
+                ActB: {path}::Run<{scaffold}, B>,
+            {{
+                fn run(&self) -> <{scaffold} as {path}::Ap<A>>::R {{
+                    self.run_bind::<{scaffold}, A>()
+                }}
+            }}
+
+            impl<{first_generics}> {path}::Run<{scaffold}, B> for {path}::Second<A, ActA, ActB>
+            where
+                {scaffold}: {path}::Zip<A, B> + {path}::Bind<(A, B), B>,
+                ActA: {path}::Run<{scaffold}, A>,
+                ActB: {path}::Run<{scaffold}, B>,
+            {{
+                fn run(&self) -> <{scaffold} as {path}::Ap<B>>::R {{
+                    self.run_bind::<{scaffold}, B>()
+                }}
+            }}
+
+            impl<{combine_generics}> {path}::Run<{scaffold}, C>
+                for {path}::Combine<A, B, ActA, ActB, F>
+            where
+                {scaffold}: {path}::Zip<A, B> + {path}::Bind<(A, B), C>,
+                ActA: {path}::Run<{scaffold}, A>,
+                ActB: {path}::Run<{scaffold}, B>,
+                F: Fn(&A, &B) -> C,
+            {{
+                fn run(&self) -> <{scaffold} as {path}::Ap<C>>::R {{
+                    self.run_bind::<{scaffold}, C>()
+                }}
+            }}
+
+            {base_writer}
+            ",
+            path = path,
+            scaffold = scaffold,
+            complete = complete,
+            pure = pure,
+            bind = bind,
+            tail_rec = tail_rec,
+            base_writer = base_writer,
+            eager_generics = self.params.join(", "),
+            ap_generics = generics("A"),
+            bind_generics = generics("A, B"),
+            map_generics = generics("A, B, ActA, F"),
+            and_then_generics = generics("A, B, ActA, ActB, F"),
+            first_generics = generics("A, B, ActA, ActB"),
+            combine_generics = generics("A, B, C, ActA, ActB, F"),
+            bound_a = bounds(&["A"]),
+            bound_ab = bounds(&["A", "B"]),
+            bound_zip = bounds(&["A", "B", "(A, B)"]),
+            bound_step = bounds(&["A", "B", &format!("{}::Step<A, B>", path)]),
+        )
+    }
+}
@@ -1,51 +1,106 @@
-
-// mod error;
+// Some constructors spell out their field names.
+#![allow(clippy::redundant_field_names)]
+
+mod boxed;
+mod cont;
+mod describe;
+mod either;
+mod error;
+mod future;
+pub mod laws;
 // mod identity;
-// mod writer;
+mod list;
+mod loops;
+mod memo;
+pub mod monoid;
+mod notation;
+mod option;
+mod par;
+mod reader;
+#[cfg(any(test, feature = "testing"))]
+mod record;
+mod simplify;
+mod state;
+mod trace;
+mod trampoline;
+mod writer;
 
 use std::marker::PhantomData;
 
-// struct First<'b, B, ActA, ActB> {
-//     act_a: ActA,
-//     act_b: ActB,
-//     _act_b_type: PhantomData<&'b B>,
-// }
-// struct Second<'a, A, ActA, ActB> {
-//     act_a: ActA,
-//     act_b: ActB,
-//     _act_a_type: PhantomData<&'a A>,
-// }
-// struct Sequence<ActA, ActB>(ActA, ActB);
-// struct Combine<'a, 'b, A, B, ActA, ActB, F> {
-//     act_a: ActA,
-//     act_b: ActB,
-//     func: F,
-//     _act_a_type: PhantomData<&'a A>,
-//     _act_b_type: PhantomData<&'b B>,
-// }
-
-pub trait Monoid: Default {
-    fn op(&self, other: &Self) -> Self;
+pub use action_derive::Scaffold;
+
+pub use self::boxed::{BoxAction, Boxed, RcAction};
+pub use self::cont::{call_cc, BuildCont, CallCc, Cont, Escape, Jump};
+pub use self::describe::{Describe, Description};
+pub use self::either::{if_then_else, EitherAction};
+pub use self::error::{
+    catch_error, throw_error, BaseCatchError, BaseError, CatchError, ThrowError,
+};
+pub use self::future::{
+    block_on, from_future, BoxFuture, BuildFuture, FromFuture, LocalExecutor, Task,
+};
+pub use self::list::{choose, guard, BaseList, BuildList, Choose, Guard};
+pub use self::loops::{
+    fold_m, for_each, replicate_m, sequence_vec, traverse, while_m, Collected, FoldM, ForEach,
+    ReplicateM, SequenceVec, Traverse, WhileM,
+};
+pub use self::memo::{CacheStats, CacheStore, HashMapStore, LruStore, Memo, MemoCache};
+pub use self::monoid::{Monoid, Semigroup};
+pub use self::option::{
+    from_option, none, or_else, BaseOption, BuildOption, FromOption, Nothing, OrElse,
+};
+pub use self::par::RunPar;
+pub use self::reader::{ask, asks, local, Ask, Asks, BuildReader, Local, Reader, RunWithEnv};
+#[cfg(any(test, feature = "testing"))]
+pub use self::record::{BuildRecord, Event, Recorded, Recorder, Recording};
+pub use self::simplify::{Compose, FuseAndThen, FuseMap, Simplify};
+pub use self::state::{get, gets, modify, put, BuildState, Get, Gets, Modify, Put, State};
+pub use self::trace::{ChromeTraceFile, RunTraced, Span, TraceCollector, TraceSink, Tracer};
+pub use self::trampoline::{tail_rec_m, Program, Step, TailRec, TailRecM, Trampolined};
+pub use self::writer::{censor, listen, pass, tell, BaseListen, Censor, Listen, Pass, Tell};
+
+pub struct First<B, ActA, ActB> {
+    act_a: ActA,
+    act_b: ActB,
+    _act_b_type: PhantomData<fn() -> B>,
 }
-
-impl Monoid for u32 {
-    fn op(&self, other: &Self) -> Self {
-        self + other
-    }
+pub struct Second<A, ActA, ActB> {
+    act_a: ActA,
+    act_b: ActB,
+    _act_a_type: PhantomData<fn() -> A>,
+}
+pub struct Sequence<ActA, ActB>(ActA, ActB);
+pub struct Combine<A, B, ActA, ActB, F> {
+    act_a: ActA,
+    act_b: ActB,
+    func: F,
+    _act_a_type: PhantomData<fn() -> A>,
+    _act_b_type: PhantomData<fn() -> B>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Identity<A>(A);
+pub struct Identity<A>(pub A);
 
+/// Scaffolds may be nested. The outer scaffold completes its type, then hands
+/// the result to the inner scaffold `S` to complete. For example
+/// `BuildWriter<W, BuildError<E>>` produces `Result<(A, W), E>`, whereas
+/// `BuildError<E, BuildWriter<W>>` produces `(Result<A, E>, W)`. The inner
+/// scaffold defaults to `()`, so a lone `BuildWriter<W>` produces `(A, W)`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuildId<S = ()>(pub S);
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct BuildId();
+pub struct BuildWriter<W, S = ()>(pub PhantomData<fn() -> W>, pub S);
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct BuildWriter<W>(pub PhantomData<*const W>);
+pub struct BuildError<E, S = ()>(pub PhantomData<fn() -> E>, pub S);
 
+/// `BuildValidation` produces the same `Result<A, E>` as `BuildError`, and
+/// `and_then` still stops at the first error. Actions which are combined
+/// independently of each other are all run however, and their errors are
+/// collected using `Semigroup::op`.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct BuildError<E>(pub PhantomData<*const E>);
+pub struct BuildValidation<E, S = ()>(pub PhantomData<fn() -> E>, pub S);
 
 /// The typebuilder trait is implemented for type "scaffolds". Type scaffolds
 /// are zero size markers which represent an incomplete type. When we run the
@@ -60,40 +115,215 @@ pub trait Ap<A> {
     fn build(value: A) -> Self::R;
 }
 
+/// A scaffold whose completed values are built by running the action, rather
+/// than waiting to be run themselves. Every scaffold which can `Bind` is eager.
+/// The lazy scaffolds, such as `BuildState`, are not, which lets `RunTraced`
+/// give them impls of their own.
+pub trait Eager {}
+
+/// `Bind` is to `and_then` what `Ap` is to `pure`. It sequences two completed
+/// types, so that an outer scaffold can thread its results through whichever
+/// scaffold it has been nested in.
+pub trait Bind<A, B>: Ap<A> + Ap<B> + Eager {
+    fn bind<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<B>>::R;
+}
+
+/// `Zip` is the applicative counterpart to `Bind`. It pairs two completed types
+/// which were produced independently of each other, so unlike `bind` it may
+/// look at both before deciding on the result.
+pub trait Zip<A, B>: Ap<A> + Ap<B> + Ap<(A, B)> {
+    fn zip(value_a: <Self as Ap<A>>::R, value_b: <Self as Ap<B>>::R) -> <Self as Ap<(A, B)>>::R;
+}
+
 /// The identity monad.
+impl Eager for () {}
 impl<A> Ap<A> for () {
     type R = A;
     fn build(value: A) -> Self::R {
         value
     }
 }
+impl<A, B> Bind<A, B> for () {
+    fn bind<F>(value: A, func: F) -> B
+    where
+        F: Fn(A) -> B,
+    {
+        func(value)
+    }
+}
+impl<A, B> Zip<A, B> for () {
+    fn zip(value_a: A, value_b: B) -> (A, B) {
+        (value_a, value_b)
+    }
+}
 
-impl<A> Ap<A> for BuildId {
-    type R = Identity<A>;
+impl<S> Eager for BuildId<S> {}
+impl<A, S> Ap<A> for BuildId<S>
+where
+    S: Ap<Identity<A>>,
+{
+    type R = S::R;
     fn build(value: A) -> Self::R {
-        Identity(value)
+        S::build(Identity(value))
+    }
+}
+impl<A, B, S> Bind<A, B> for BuildId<S>
+where
+    S: Bind<Identity<A>, Identity<B>>,
+{
+    fn bind<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<B>>::R,
+    {
+        S::bind(value, |Identity(result_a)| func(result_a))
+    }
+}
+impl<A, B, S> Zip<A, B> for BuildId<S>
+where
+    S: Zip<Identity<A>, Identity<B>> + Bind<(Identity<A>, Identity<B>), Identity<(A, B)>>,
+{
+    fn zip(value_a: <Self as Ap<A>>::R, value_b: <Self as Ap<B>>::R) -> <Self as Ap<(A, B)>>::R {
+        S::bind(
+            S::zip(value_a, value_b),
+            |(Identity(result_a), Identity(result_b))| {
+                <S as Ap<Identity<(A, B)>>>::build(Identity((result_a, result_b)))
+            },
+        )
     }
 }
 
-impl<A, W> Ap<A> for BuildWriter<W>
+impl<S, W> Eager for BuildWriter<W, S> {}
+impl<A, S, W> Ap<A> for BuildWriter<W, S>
 where
+    S: Ap<(A, W)>,
     W: Default,
 {
-    type R = (A, W);
+    type R = S::R;
     fn build(value: A) -> Self::R {
-        (value, W::default())
+        S::build((value, W::default()))
+    }
+}
+impl<A, B, S, W> Bind<A, B> for BuildWriter<W, S>
+where
+    S: Bind<(A, W), (B, W)> + Bind<(B, W), (B, W)>,
+    W: Monoid,
+{
+    fn bind<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<B>>::R,
+    {
+        <S as Bind<(A, W), (B, W)>>::bind(value, |(result_a, log_a)| {
+            <S as Bind<(B, W), (B, W)>>::bind(func(result_a), |(result_b, log_b)| {
+                <S as Ap<(B, W)>>::build((result_b, log_a.op(&log_b)))
+            })
+        })
+    }
+}
+impl<A, B, S, W> Zip<A, B> for BuildWriter<W, S>
+where
+    S: Zip<(A, W), (B, W)> + Bind<((A, W), (B, W)), ((A, B), W)>,
+    W: Monoid,
+{
+    fn zip(value_a: <Self as Ap<A>>::R, value_b: <Self as Ap<B>>::R) -> <Self as Ap<(A, B)>>::R {
+        S::bind(
+            S::zip(value_a, value_b),
+            |((result_a, log_a), (result_b, log_b))| {
+                <S as Ap<((A, B), W)>>::build(((result_a, result_b), log_a.op(&log_b)))
+            },
+        )
     }
 }
 
-impl<A, E> Ap<A> for BuildError<E> {
-    type R = Result<A, E>;
+impl<S, E> Eager for BuildError<E, S> {}
+impl<A, S, E> Ap<A> for BuildError<E, S>
+where
+    S: Ap<Result<A, E>>,
+{
+    type R = S::R;
     fn build(value: A) -> Self::R {
-        Ok(value)
+        S::build(Ok(value))
+    }
+}
+impl<A, B, S, E> Bind<A, B> for BuildError<E, S>
+where
+    S: Bind<Result<A, E>, Result<B, E>>,
+{
+    fn bind<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<B>>::R,
+    {
+        S::bind(value, |result_a| match result_a {
+            Ok(result_a) => func(result_a),
+            Err(err_a) => <S as Ap<Result<B, E>>>::build(Err(err_a)),
+        })
+    }
+}
+impl<A, B, S, E> Zip<A, B> for BuildError<E, S>
+where
+    S: Zip<Result<A, E>, Result<B, E>> + Bind<(Result<A, E>, Result<B, E>), Result<(A, B), E>>,
+{
+    fn zip(value_a: <Self as Ap<A>>::R, value_b: <Self as Ap<B>>::R) -> <Self as Ap<(A, B)>>::R {
+        S::bind(S::zip(value_a, value_b), |results| {
+            <S as Ap<Result<(A, B), E>>>::build(match results {
+                (Ok(result_a), Ok(result_b)) => Ok((result_a, result_b)),
+                (Err(err_a), _) => Err(err_a),
+                (_, Err(err_b)) => Err(err_b),
+            })
+        })
     }
 }
 
+impl<S, E> Eager for BuildValidation<E, S> {}
+impl<A, S, E> Ap<A> for BuildValidation<E, S>
+where
+    S: Ap<Result<A, E>>,
+{
+    type R = S::R;
+    fn build(value: A) -> Self::R {
+        S::build(Ok(value))
+    }
+}
+impl<A, B, S, E> Bind<A, B> for BuildValidation<E, S>
+where
+    S: Bind<Result<A, E>, Result<B, E>>,
+{
+    fn bind<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<B>>::R,
+    {
+        <BuildError<E, S> as Bind<A, B>>::bind(value, func)
+    }
+}
+impl<A, B, S, E> Zip<A, B> for BuildValidation<E, S>
+where
+    E: Monoid,
+    S: Zip<Result<A, E>, Result<B, E>> + Bind<(Result<A, E>, Result<B, E>), Result<(A, B), E>>,
+{
+    fn zip(value_a: <Self as Ap<A>>::R, value_b: <Self as Ap<B>>::R) -> <Self as Ap<(A, B)>>::R {
+        S::bind(S::zip(value_a, value_b), |results| {
+            <S as Ap<Result<(A, B), E>>>::build(match results {
+                (Ok(result_a), Ok(result_b)) => Ok((result_a, result_b)),
+                (Err(err_a), Err(err_b)) => Err(err_a.op(&err_b)),
+                (Err(err_a), _) => Err(err_a),
+                (_, Err(err_b)) => Err(err_b),
+            })
+        })
+    }
+}
+impl<E, S> From<BuildError<E, S>> for BuildValidation<E, S> {
+    fn from(scaffold: BuildError<E, S>) -> Self {
+        BuildValidation(PhantomData, scaffold.1)
+    }
+}
+impl<E, S> From<BuildValidation<E, S>> for BuildError<E, S> {
+    fn from(scaffold: BuildValidation<E, S>) -> Self {
+        BuildError(PhantomData, scaffold.1)
+    }
+}
 
-trait Mappable<A>
+pub trait Mappable<A>
 where
     Self: Sized,
 {
@@ -104,7 +334,7 @@ where
     /// should perform the action, then modify the result using the function.
     fn map<B, F>(self, func: F) -> Map<A, Self, F>
     where
-        F: FnMut(&A) -> B,
+        F: Fn(&A) -> B,
         Map<A, Self, F>: Mappable<B>,
     {
         Map {
@@ -115,60 +345,63 @@ where
     }
 }
 
-// trait Sequential<A>: Mappable<A> {
-//     fn first<'b, B, ActB>(self, other: ActB) -> First<'b, B, Self, ActB>
-//     where
-//         ActB: Sequential<B>,
-//         First<'b, B, Self, ActB>: Sequential<A>,
-//     {
-//         First {
-//             act_a: self,
-//             act_b: other,
-//             _act_b_type: PhantomData,
-//         }
-//     }
-
-//     fn second<'a, B, ActB>(self, other: ActB) -> Second<'a, A, Self, ActB>
-//     where
-//         ActB: Sequential<B>,
-//         Second<'a, A, Self, ActB>: Sequential<B>,
-//     {
-//         Second {
-//             act_a: self,
-//             act_b: other,
-//             _act_a_type: PhantomData,
-//         }
-//     }
-
-//     fn sequence<B, ActB>(self, other: ActB) -> Sequence<Self, ActB>
-//     where
-//         ActB: Sequential<B>,
-//         Sequence<Self, ActB>: Sequential<(A, B)>,
-//     {
-//         Sequence(self, other)
-//     }
-
-//     fn combine<'a, 'b, B, ActB, F, R>(
-//         self,
-//         other: ActB,
-//         func: F,
-//     ) -> Combine<'a, 'b, A, B, Self, ActB, F>
-//     where
-//         ActB: Sequential<B>,
-//         F: FnMut(&A, &B) -> R,
-//         Combine<'a, 'b, A, B, Self, ActB, F>: Sequential<R>,
-//     {
-//         Combine {
-//             act_a: self,
-//             act_b: other,
-//             func: func,
-//             _act_a_type: PhantomData,
-//             _act_b_type: PhantomData,
-//         }
-//     }
-// }
-
-trait Action<A>
+/// `Sequential` actions can be run one after the other when neither depends on
+/// the result of the other. Since there is no dependency, scaffolds are free to
+/// look at both results before combining them.
+pub trait Sequential<A>: Mappable<A> {
+    /// Run both actions, keeping the result of the first.
+    fn first<B, ActB>(self, other: ActB) -> First<B, Self, ActB>
+    where
+        ActB: Sequential<B>,
+        First<B, Self, ActB>: Sequential<A>,
+    {
+        First {
+            act_a: self,
+            act_b: other,
+            _act_b_type: PhantomData,
+        }
+    }
+
+    /// Run both actions, keeping the result of the second.
+    fn second<B, ActB>(self, other: ActB) -> Second<A, Self, ActB>
+    where
+        ActB: Sequential<B>,
+        Second<A, Self, ActB>: Sequential<B>,
+    {
+        Second {
+            act_a: self,
+            act_b: other,
+            _act_a_type: PhantomData,
+        }
+    }
+
+    /// Run both actions, keeping both results.
+    fn sequence<B, ActB>(self, other: ActB) -> Sequence<Self, ActB>
+    where
+        ActB: Sequential<B>,
+        Sequence<Self, ActB>: Sequential<(A, B)>,
+    {
+        Sequence(self, other)
+    }
+
+    /// Run both actions, and combine the results using a function.
+    fn combine<B, ActB, F, R>(self, other: ActB, func: F) -> Combine<A, B, Self, ActB, F>
+    where
+        ActB: Sequential<B>,
+        F: Fn(&A, &B) -> R,
+        Combine<A, B, Self, ActB, F>: Sequential<R>,
+    {
+        Combine {
+            act_a: self,
+            act_b: other,
+            func: func,
+            _act_a_type: PhantomData,
+            _act_b_type: PhantomData,
+        }
+    }
+}
+
+pub trait Action<A>
 where
     Self: Sized,
 {
@@ -188,7 +421,7 @@ where
     }
 }
 
-trait Run<S, A>: Action<A>
+pub trait Run<S, A>: Action<A>
 where
     S: Ap<A>,
 {
@@ -198,7 +431,6 @@ where
     }
 }
 
-
 /// `Pure` is an action which does nothing except return a value.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Pure<A> {
@@ -208,6 +440,7 @@ pub fn pure<A>(value: A) -> Pure<A> {
     Pure { value: value }
 }
 impl<A> Mappable<A> for Pure<A> {}
+impl<A> Sequential<A> for Pure<A> {}
 impl<A> Action<A> for Pure<A> {}
 /// Pure is an element of every type of action, so we can implement a run
 /// instance for any type scaffold.
@@ -221,7 +454,6 @@ where
     }
 }
 
-
 pub struct Literal<S, A>
 where
     S: Ap<A>,
@@ -235,6 +467,7 @@ where
     Literal { value: value }
 }
 impl<S, A> Mappable<A> for Literal<S, A> where S: Ap<A> {}
+impl<S, A> Sequential<A> for Literal<S, A> where S: Ap<A> {}
 impl<S, A> Action<A> for Literal<S, A> where S: Ap<A> {}
 impl<S, A> Run<S, A> for Literal<S, A>
 where
@@ -246,59 +479,130 @@ where
     }
 }
 
+/// The function in a `Map`, which is any `Fn(&A) -> B`. Functions which are
+/// composed by `simplify` can't be closures, since they need a type which can
+/// be named, so they implement `MapFn` instead.
+pub trait MapFn<A, B> {
+    fn call(&self, value: &A) -> B;
+}
+impl<A, B, F> MapFn<A, B> for F
+where
+    F: Fn(&A) -> B,
+{
+    fn call(&self, value: &A) -> B {
+        self(value)
+    }
+}
 
-struct Map<A, ActA, F> {
+pub struct Map<A, ActA, F> {
     act_a: ActA,
     func: F,
-    _act_a_type: PhantomData<*const A>,
+    _act_a_type: PhantomData<fn() -> A>,
+}
+impl<A, ActA, F> Map<A, ActA, F> {
+    /// Run with any scaffold which can `Bind`. The run instances generated by
+    /// `#[derive(Scaffold)]` use this, since they can't see the fields.
+    pub fn run_bind<S, B>(&self) -> <S as Ap<B>>::R
+    where
+        S: Bind<A, B>,
+        ActA: Run<S, A>,
+        F: MapFn<A, B>,
+    {
+        S::bind(self.act_a.run(), |result_a| {
+            <S as Ap<B>>::build(self.func.call(&result_a))
+        })
+    }
 }
 impl<A, B, ActA, F> Mappable<B> for Map<A, ActA, F>
 where
     ActA: Mappable<A>,
-    F: Fn(&A) -> B,
+    F: MapFn<A, B>,
+{
+}
+impl<A, B, ActA, F> Sequential<B> for Map<A, ActA, F>
+where
+    ActA: Sequential<A>,
+    F: MapFn<A, B>,
 {
 }
 impl<A, B, ActA, F> Action<B> for Map<A, ActA, F>
 where
     ActA: Action<A>,
-    F: Fn(&A) -> B,
+    F: MapFn<A, B>,
 {
 }
 impl<A, B, ActA, F> Run<(), B> for Map<A, ActA, F>
 where
     ActA: Run<(), A>,
-    F: Fn(&A) -> B,
+    F: MapFn<A, B>,
 {
     fn run(&self) -> <() as Ap<B>>::R {
-        (self.func)(&self.act_a.run())
+        self.func.call(&self.act_a.run())
     }
 }
-impl<A, B, ActA, F, W> Run<BuildWriter<W>, B> for Map<A, ActA, F>
+impl<A, B, ActA, F, S> Run<BuildId<S>, B> for Map<A, ActA, F>
+where
+    S: Bind<Identity<A>, Identity<B>>,
+    ActA: Run<BuildId<S>, A>,
+    F: MapFn<A, B>,
+{
+    fn run(&self) -> <BuildId<S> as Ap<B>>::R {
+        S::bind(self.act_a.run(), |Identity(result_a)| {
+            <S as Ap<Identity<B>>>::build(Identity(self.func.call(&result_a)))
+        })
+    }
+}
+impl<A, B, ActA, F, S, W> Run<BuildWriter<W, S>, B> for Map<A, ActA, F>
 where
     W: Default,
-    ActA: Run<BuildWriter<W>, A>,
-    F: Fn(&A) -> B,
+    S: Bind<(A, W), (B, W)>,
+    ActA: Run<BuildWriter<W, S>, A>,
+    F: MapFn<A, B>,
 {
-    fn run(&self) -> <BuildWriter<W> as Ap<B>>::R {
-        let (result_a, log) = self.act_a.run();
-        ((self.func)(&result_a), log)
+    fn run(&self) -> <BuildWriter<W, S> as Ap<B>>::R {
+        S::bind(self.act_a.run(), |(result_a, log)| {
+            <S as Ap<(B, W)>>::build((self.func.call(&result_a), log))
+        })
     }
 }
 
-
 /// Bind two actions together using the result from the first action to modify
 /// the second.
+///
+/// `run` nests a call for every `and_then`, so a deep chain, such as an action
+/// which binds to itself recursively, should be run with
+/// `Trampolined::run_trampolined` instead.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-struct AndThen<A, ActA, ActB, F> {
+pub struct AndThen<A, ActA, ActB, F> {
     act_a: ActA,
     func: F,
-    _act_a_type: PhantomData<*const A>,
-    _act_b: PhantomData<*const ActB>,
+    _act_a_type: PhantomData<fn() -> A>,
+    _act_b: PhantomData<fn() -> ActB>,
+}
+impl<A, ActA, ActB, F> AndThen<A, ActA, ActB, F> {
+    /// Run with any scaffold which can `Bind`. The run instances generated by
+    /// `#[derive(Scaffold)]` use this, since they can't see the fields.
+    pub fn run_bind<S, B>(&self) -> <S as Ap<B>>::R
+    where
+        S: Bind<A, B>,
+        ActA: Run<S, A>,
+        ActB: Run<S, B>,
+        F: Fn(&A) -> ActB,
+    {
+        S::bind(self.act_a.run(), |result_a| (self.func)(&result_a).run())
+    }
 }
 impl<A, B, ActA, ActB, F> Mappable<B> for AndThen<A, ActA, ActB, F>
 where
-    ActA: Action<A>,
-    ActB: Action<B>,
+    ActA: Mappable<A>,
+    ActB: Mappable<B>,
+    F: Fn(&A) -> ActB,
+{
+}
+impl<A, B, ActA, ActB, F> Sequential<B> for AndThen<A, ActA, ActB, F>
+where
+    ActA: Sequential<A>,
+    ActB: Sequential<B>,
     F: Fn(&A) -> ActB,
 {
 }
@@ -319,20 +623,30 @@ where
         (self.func)(&self.act_a.run()).run()
     }
 }
-impl<A, B, ActA, ActB, W, F> Run<BuildWriter<W>, B> for AndThen<A, ActA, ActB, F>
+impl<A, B, ActA, ActB, F, S> Run<BuildId<S>, B> for AndThen<A, ActA, ActB, F>
 where
-    W: Monoid,
-    ActA: Run<BuildWriter<W>, A>,
-    ActB: Run<BuildWriter<W>, B>,
+    BuildId<S>: Bind<A, B>,
+    ActA: Run<BuildId<S>, A>,
+    ActB: Run<BuildId<S>, B>,
     F: Fn(&A) -> ActB,
 {
-    fn run(&self) -> <BuildWriter<W> as Ap<B>>::R {
-        let (result_a, log_a) = self.act_a.run();
-        let (result_b, log_b) = (self.func)(&result_a).run();
-        (result_b, log_a.op(&log_b))
+    fn run(&self) -> <BuildId<S> as Ap<B>>::R {
+        <BuildId<S> as Bind<A, B>>::bind(self.act_a.run(), |result_a| (self.func)(&result_a).run())
+    }
+}
+impl<A, B, ActA, ActB, W, F, S> Run<BuildWriter<W, S>, B> for AndThen<A, ActA, ActB, F>
+where
+    BuildWriter<W, S>: Bind<A, B>,
+    ActA: Run<BuildWriter<W, S>, A>,
+    ActB: Run<BuildWriter<W, S>, B>,
+    F: Fn(&A) -> ActB,
+{
+    fn run(&self) -> <BuildWriter<W, S> as Ap<B>>::R {
+        <BuildWriter<W, S> as Bind<A, B>>::bind(self.act_a.run(), |result_a| {
+            (self.func)(&result_a).run()
+        })
     }
 }
-
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Writer<A, W> {
@@ -346,122 +660,269 @@ pub fn writer<A, W>(value: A, log: W) -> Writer<A, W> {
     }
 }
 impl<A, W> Mappable<A> for Writer<A, W> {}
+impl<A, W> Sequential<A> for Writer<A, W> {}
 impl<A, W> Action<A> for Writer<A, W> {}
-impl<W, A> Run<BuildWriter<W>, A> for Writer<A, W>
+/// A writer can be run with any scaffold which contains a `BuildWriter`.
+impl<A, S, W> Run<S, A> for Writer<A, W>
 where
+    S: BaseWriter<A, W>,
     A: Clone,
-    W: Default + Clone,
+    W: Clone,
 {
-    fn run(&self) -> <BuildWriter<W> as Ap<A>>::R {
-        (self.value.clone(), self.log.clone())
+    fn run(&self) -> S::R {
+        S::writer(self.value.clone(), self.log.clone())
     }
 }
 
+/// Scaffolds which can complete a value together with a log. An outer scaffold
+/// which is not a writer passes the log through to the scaffold it wraps.
+pub trait BaseWriter<A, W>: Ap<A> {
+    fn writer(value: A, log: W) -> Self::R;
+}
+impl<A, S, W> BaseWriter<A, W> for BuildWriter<W, S>
+where
+    S: Ap<(A, W)>,
+    W: Default,
+{
+    fn writer(value: A, log: W) -> Self::R {
+        S::build((value, log))
+    }
+}
+impl<A, S, W> BaseWriter<A, W> for BuildId<S>
+where
+    S: BaseWriter<Identity<A>, W>,
+{
+    fn writer(value: A, log: W) -> Self::R {
+        S::writer(Identity(value), log)
+    }
+}
 
-// impl<'a, A, B, ActA, F> Sequential<B> for Map<'a, A, ActA, F>
-// where
-//     ActA: Sequential<A>,
-//     F: Fn(&A) -> B,
-// {
-// }
-
-// // impl<A> Mappable<A> for Pure<A> {}
-// // impl<A> Sequential<A> for Pure<A> {}
-// // impl<A> Action<A> for Pure<A> {}
-
-// impl<'b, A, B, ActA, ActB> Mappable<A> for First<'b, B, ActA, ActB>
-// where
-//     ActA: Mappable<A>,
-//     ActB: Mappable<B>,
-// {
-// }
-// impl<'b, A, B, ActA, ActB> Sequential<A> for First<'b, B, ActA, ActB>
-// where
-//     ActA: Sequential<A>,
-//     ActB: Sequential<B>,
-// {
-// }
-// impl<'b, A, B, ActA, ActB> Action<A> for First<'b, B, ActA, ActB>
-// where
-//     ActA: Action<A>,
-//     ActB: Action<B>,
-// {
-// }
-
-// impl<'a, A, B, ActA, ActB> Mappable<B> for Second<'a, A, ActA, ActB>
-// where
-//     ActA: Mappable<A>,
-//     ActB: Mappable<B>,
-// {
-// }
-// impl<'a, A, B, ActA, ActB> Sequential<B> for Second<'a, A, ActA, ActB>
-// where
-//     ActA: Sequential<A>,
-//     ActB: Sequential<B>,
-// {
-// }
-// impl<'a, A, B, ActA, ActB> Action<B> for Second<'a, A, ActA, ActB>
-// where
-//     ActA: Action<A>,
-//     ActB: Action<B>,
-// {
-// }
+impl<A, B, ActA, ActB> Mappable<A> for First<B, ActA, ActB>
+where
+    ActA: Mappable<A>,
+    ActB: Mappable<B>,
+{
+}
+impl<A, B, ActA, ActB> Sequential<A> for First<B, ActA, ActB>
+where
+    ActA: Sequential<A>,
+    ActB: Sequential<B>,
+{
+}
+impl<A, B, ActA, ActB> Action<A> for First<B, ActA, ActB>
+where
+    ActA: Action<A>,
+    ActB: Action<B>,
+{
+}
+impl<B, ActA, ActB> First<B, ActA, ActB> {
+    /// Run with any scaffold which can `Zip` two results, and `Bind` to pick
+    /// the one to keep. The run instances for the scaffolds which complete
+    /// their types straight away use this, as do those generated by
+    /// `#[derive(Scaffold)]`.
+    pub fn run_bind<S, A>(&self) -> <S as Ap<A>>::R
+    where
+        S: Zip<A, B> + Bind<(A, B), A>,
+        ActA: Run<S, A>,
+        ActB: Run<S, B>,
+    {
+        S::bind(
+            S::zip(self.act_a.run(), self.act_b.run()),
+            |(result_a, _)| <S as Ap<A>>::build(result_a),
+        )
+    }
+}
+impl<A, B, ActA, ActB> Run<(), A> for First<B, ActA, ActB>
+where
+    ActA: Run<(), A>,
+    ActB: Run<(), B>,
+{
+    fn run(&self) -> A {
+        self.run_bind::<(), A>()
+    }
+}
+impl<A, B, ActA, ActB, S> Run<BuildId<S>, A> for First<B, ActA, ActB>
+where
+    BuildId<S>: Zip<A, B> + Bind<(A, B), A>,
+    ActA: Run<BuildId<S>, A>,
+    ActB: Run<BuildId<S>, B>,
+{
+    fn run(&self) -> <BuildId<S> as Ap<A>>::R {
+        self.run_bind::<BuildId<S>, A>()
+    }
+}
+impl<A, B, ActA, ActB, S, W> Run<BuildWriter<W, S>, A> for First<B, ActA, ActB>
+where
+    BuildWriter<W, S>: Zip<A, B> + Bind<(A, B), A>,
+    ActA: Run<BuildWriter<W, S>, A>,
+    ActB: Run<BuildWriter<W, S>, B>,
+{
+    fn run(&self) -> <BuildWriter<W, S> as Ap<A>>::R {
+        self.run_bind::<BuildWriter<W, S>, A>()
+    }
+}
 
-// impl<A, B, ActA, ActB> Mappable<(A, B)> for Sequence<ActA, ActB>
-// where
-//     ActA: Mappable<A>,
-//     ActB: Mappable<B>,
-// {
-// }
-// impl<A, B, ActA, ActB> Sequential<(A, B)> for Sequence<ActA, ActB>
-// where
-//     ActA: Sequential<A>,
-//     ActB: Sequential<B>,
-// {
-// }
-// impl<A, B, ActA, ActB> Action<(A, B)> for Sequence<ActA, ActB>
-// where
-//     ActA: Action<A>,
-//     ActB: Action<B>,
-// {
-// }
+impl<A, B, ActA, ActB> Mappable<B> for Second<A, ActA, ActB>
+where
+    ActA: Mappable<A>,
+    ActB: Mappable<B>,
+{
+}
+impl<A, B, ActA, ActB> Sequential<B> for Second<A, ActA, ActB>
+where
+    ActA: Sequential<A>,
+    ActB: Sequential<B>,
+{
+}
+impl<A, B, ActA, ActB> Action<B> for Second<A, ActA, ActB>
+where
+    ActA: Action<A>,
+    ActB: Action<B>,
+{
+}
+impl<A, ActA, ActB> Second<A, ActA, ActB> {
+    /// Run with any scaffold which can `Zip` two results, and `Bind` to pick
+    /// the one to keep.
+    pub fn run_bind<S, B>(&self) -> <S as Ap<B>>::R
+    where
+        S: Zip<A, B> + Bind<(A, B), B>,
+        ActA: Run<S, A>,
+        ActB: Run<S, B>,
+    {
+        S::bind(
+            S::zip(self.act_a.run(), self.act_b.run()),
+            |(_, result_b)| <S as Ap<B>>::build(result_b),
+        )
+    }
+}
+impl<A, B, ActA, ActB> Run<(), B> for Second<A, ActA, ActB>
+where
+    ActA: Run<(), A>,
+    ActB: Run<(), B>,
+{
+    fn run(&self) -> B {
+        self.run_bind::<(), B>()
+    }
+}
+impl<A, B, ActA, ActB, S> Run<BuildId<S>, B> for Second<A, ActA, ActB>
+where
+    BuildId<S>: Zip<A, B> + Bind<(A, B), B>,
+    ActA: Run<BuildId<S>, A>,
+    ActB: Run<BuildId<S>, B>,
+{
+    fn run(&self) -> <BuildId<S> as Ap<B>>::R {
+        self.run_bind::<BuildId<S>, B>()
+    }
+}
+impl<A, B, ActA, ActB, S, W> Run<BuildWriter<W, S>, B> for Second<A, ActA, ActB>
+where
+    BuildWriter<W, S>: Zip<A, B> + Bind<(A, B), B>,
+    ActA: Run<BuildWriter<W, S>, A>,
+    ActB: Run<BuildWriter<W, S>, B>,
+{
+    fn run(&self) -> <BuildWriter<W, S> as Ap<B>>::R {
+        self.run_bind::<BuildWriter<W, S>, B>()
+    }
+}
 
-// impl<'a, 'b, A, B, C, ActA, ActB, F> Mappable<C> for Combine<'a, 'b, A, B, ActA, ActB, F>
-// where
-//     ActA: Mappable<A>,
-//     ActB: Mappable<B>,
-//     F: Fn(&A, &B) -> C,
-// {
-// }
-// impl<'a, 'b, A, B, C, ActA, ActB, F> Sequential<C> for Combine<'a, 'b, A, B, ActA, ActB, F>
-// where
-//     ActA: Sequential<A>,
-//     ActB: Sequential<B>,
-//     F: Fn(&A, &B) -> C,
-// {
-// }
-// impl<'a, 'b, A, B, C, ActA, ActB, F> Action<C> for Combine<'a, 'b, A, B, ActA, ActB, F>
-// where
-//     ActA: Action<A>,
-//     ActB: Action<B>,
-//     F: Fn(&A, &B) -> C,
-// {
-// }
+impl<A, B, ActA, ActB> Mappable<(A, B)> for Sequence<ActA, ActB>
+where
+    ActA: Mappable<A>,
+    ActB: Mappable<B>,
+{
+}
+impl<A, B, ActA, ActB> Sequential<(A, B)> for Sequence<ActA, ActB>
+where
+    ActA: Sequential<A>,
+    ActB: Sequential<B>,
+{
+}
+impl<A, B, ActA, ActB> Action<(A, B)> for Sequence<ActA, ActB>
+where
+    ActA: Action<A>,
+    ActB: Action<B>,
+{
+}
+impl<S, A, B, ActA, ActB> Run<S, (A, B)> for Sequence<ActA, ActB>
+where
+    S: Zip<A, B>,
+    ActA: Run<S, A>,
+    ActB: Run<S, B>,
+{
+    fn run(&self) -> <S as Ap<(A, B)>>::R {
+        S::zip(self.0.run(), self.1.run())
+    }
+}
 
-// impl<'a, 'ab, A, B, ActA, ActB, F> Mappable<B> for AndThen<'a, 'ab, A, ActA, ActB, F>
-// where
-//     ActA: Mappable<A>,
-//     ActB: Mappable<B>,
-//     F: Fn(&A) -> ActB,
-// {
-// }
-// impl<'a, 'ab, A, B, ActA, ActB, F> Sequential<B> for AndThen<'a, 'ab, A, ActA, ActB, F>
-// where
-//     ActA: Sequential<A>,
-//     ActB: Sequential<B>,
-//     F: Fn(&A) -> ActB,
-// {
-// }
+impl<A, B, C, ActA, ActB, F> Mappable<C> for Combine<A, B, ActA, ActB, F>
+where
+    ActA: Mappable<A>,
+    ActB: Mappable<B>,
+    F: Fn(&A, &B) -> C,
+{
+}
+impl<A, B, C, ActA, ActB, F> Sequential<C> for Combine<A, B, ActA, ActB, F>
+where
+    ActA: Sequential<A>,
+    ActB: Sequential<B>,
+    F: Fn(&A, &B) -> C,
+{
+}
+impl<A, B, C, ActA, ActB, F> Action<C> for Combine<A, B, ActA, ActB, F>
+where
+    ActA: Action<A>,
+    ActB: Action<B>,
+    F: Fn(&A, &B) -> C,
+{
+}
+impl<A, B, ActA, ActB, F> Combine<A, B, ActA, ActB, F> {
+    /// Run with any scaffold which can `Zip` two results, and `Bind` to
+    /// combine them.
+    pub fn run_bind<S, C>(&self) -> <S as Ap<C>>::R
+    where
+        S: Zip<A, B> + Bind<(A, B), C>,
+        ActA: Run<S, A>,
+        ActB: Run<S, B>,
+        F: Fn(&A, &B) -> C,
+    {
+        S::bind(
+            S::zip(self.act_a.run(), self.act_b.run()),
+            |(result_a, result_b)| <S as Ap<C>>::build((self.func)(&result_a, &result_b)),
+        )
+    }
+}
+impl<A, B, C, ActA, ActB, F> Run<(), C> for Combine<A, B, ActA, ActB, F>
+where
+    ActA: Run<(), A>,
+    ActB: Run<(), B>,
+    F: Fn(&A, &B) -> C,
+{
+    fn run(&self) -> C {
+        self.run_bind::<(), C>()
+    }
+}
+impl<A, B, C, ActA, ActB, F, S> Run<BuildId<S>, C> for Combine<A, B, ActA, ActB, F>
+where
+    BuildId<S>: Zip<A, B> + Bind<(A, B), C>,
+    ActA: Run<BuildId<S>, A>,
+    ActB: Run<BuildId<S>, B>,
+    F: Fn(&A, &B) -> C,
+{
+    fn run(&self) -> <BuildId<S> as Ap<C>>::R {
+        self.run_bind::<BuildId<S>, C>()
+    }
+}
+impl<A, B, C, ActA, ActB, F, S, W> Run<BuildWriter<W, S>, C> for Combine<A, B, ActA, ActB, F>
+where
+    BuildWriter<W, S>: Zip<A, B> + Bind<(A, B), C>,
+    ActA: Run<BuildWriter<W, S>, A>,
+    ActB: Run<BuildWriter<W, S>, B>,
+    F: Fn(&A, &B) -> C,
+{
+    fn run(&self) -> <BuildWriter<W, S> as Ap<C>>::R {
+        self.run_bind::<BuildWriter<W, S>, C>()
+    }
+}
 
 // impl<'ab, A, B, ActA, ActB, Ctx> Mappable<B> for AndThenCtx<'ab, A, ActA, ActB, Ctx>
 // where
@@ -477,6 +938,7 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::collections::BTreeSet;
     use std::marker::PhantomData;
 
     fn build_two<A, B, S>(
@@ -492,8 +954,8 @@ mod test {
 
     #[test]
     fn same_struct_different_value() {
-        let writer_type: PhantomData<*const ()> = PhantomData;
-        let scaffold = BuildWriter(writer_type);
+        let writer_type: PhantomData<fn() -> ()> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
 
         let (result_a, result_b) = build_two(scaffold, 32, "Hello");
 
@@ -503,8 +965,8 @@ mod test {
 
     #[test]
     fn same_struct_different_value_2() {
-        let err_type: PhantomData<*const ()> = PhantomData;
-        let scaffold = BuildError(err_type);
+        let err_type: PhantomData<fn() -> ()> = PhantomData;
+        let scaffold = BuildError(err_type, ());
 
         let (result_a, result_b) = build_two(scaffold, 32, "Hello");
 
@@ -512,6 +974,30 @@ mod test {
         assert_eq!(result_b, Ok("Hello"));
     }
 
+    #[test]
+    fn nested_struct_different_value() {
+        let err_type: PhantomData<fn() -> ()> = PhantomData;
+        let writer_type: PhantomData<fn() -> ()> = PhantomData;
+        let scaffold = BuildWriter(writer_type, BuildError(err_type, BuildId(())));
+
+        let (result_a, result_b) = build_two(scaffold, 32, "Hello");
+
+        assert_eq!(result_a, Identity(Ok((32, ()))));
+        assert_eq!(result_b, Identity(Ok(("Hello", ()))));
+    }
+
+    #[test]
+    fn nested_struct_different_value_2() {
+        let err_type: PhantomData<fn() -> ()> = PhantomData;
+        let writer_type: PhantomData<fn() -> ()> = PhantomData;
+        let scaffold = BuildId(BuildError(err_type, BuildWriter(writer_type, ())));
+
+        let (result_a, result_b) = build_two(scaffold, 32, "Hello");
+
+        assert_eq!(result_a, (Ok(Identity(32)), ()));
+        assert_eq!(result_b, (Ok(Identity("Hello")), ()));
+    }
+
     #[test]
     fn run_and_then_identity() {
         let action = pure(3).and_then(|x| pure(x + 2)).and_then(|y| pure(y + 3));
@@ -527,8 +1013,8 @@ mod test {
 
     #[test]
     fn pure_is_polymorphic() {
-        let writer_type: PhantomData<*const ()> = PhantomData;
-        let scaffold = BuildWriter(writer_type);
+        let writer_type: PhantomData<fn() -> ()> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
 
         assert_eq!(pure(5).run_qualified(&scaffold), (5, ()));
         assert_eq!(pure(5).run_qualified(&()), 5);
@@ -536,8 +1022,8 @@ mod test {
 
     #[test]
     fn and_then_is_polymorphic() {
-        let writer_type: PhantomData<*const u32> = PhantomData;
-        let scaffold = BuildWriter(writer_type);
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
 
         let action = pure(3).and_then(|x| pure(x + 2));
 
@@ -547,8 +1033,8 @@ mod test {
 
     #[test]
     fn run_and_then_writer() {
-        let writer_type: PhantomData<*const u32> = PhantomData;
-        let scaffold = BuildWriter(writer_type);
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
 
         let action = pure(3).and_then(|x| pure(x + 2)).and_then(|y| pure(y + 3));
         let action2 = pure(5).and_then(|x| pure(x + 7)).and_then(|y| pure(y + 9));
@@ -563,8 +1049,8 @@ mod test {
 
     #[test]
     fn basic_writer() {
-        let writer_type: PhantomData<*const u32> = PhantomData;
-        let scaffold = BuildWriter(writer_type);
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
 
         let action = pure(true)
             .and_then(|x| writer(!x, 3))
@@ -581,4 +1067,446 @@ mod test {
 
         assert_eq!(action3.run_qualified(&scaffold), (true, 36));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn nested_writer() {
+        let err_type: PhantomData<fn() -> ()> = PhantomData;
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let writer_error = BuildWriter(writer_type, BuildError(err_type, ()));
+        let error_writer = BuildError(err_type, BuildWriter(writer_type, ()));
+        let id_writer = BuildId(BuildWriter(writer_type, ()));
+
+        let action = writer(3, 10).map(|x| x + 2).and_then(|y| writer(y + 3, 5));
+
+        assert_eq!(action.run_qualified(&writer_error), Ok((8, 15)));
+        assert_eq!(action.run_qualified(&error_writer), (Ok(8), 15));
+        assert_eq!(action.run_qualified(&id_writer), (Identity(8), 15));
+    }
+
+    #[test]
+    fn nested_stacks_differ() {
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let writer_error = BuildWriter(writer_type, BuildError(err_type, ()));
+        let error_writer = BuildError(err_type, BuildWriter(writer_type, ()));
+
+        let action = writer(3, 10)
+            .and_then(|_| throw_error::<i32, _>("failed"))
+            .and_then(|y| writer(y + 3, 5));
+
+        assert_eq!(action.run_qualified(&writer_error), Err("failed"));
+        assert_eq!(action.run_qualified(&error_writer), (Err("failed"), 10));
+    }
+
+    #[test]
+    fn sequential_identity() {
+        let action = pure(3).sequence(pure("three"));
+        let action2 = pure(3).combine(pure(4), |x, y| x * y);
+
+        assert_eq!(action.run_qualified(&()), (3, "three"));
+        assert_eq!(action2.run_qualified(&()), 12);
+        assert_eq!(pure(3).first(pure(4)).run_qualified(&()), 3);
+        assert_eq!(pure(3).second(pure(4)).run_qualified(&()), 4);
+        assert_eq!(
+            pure(3).second(pure(4)).run_qualified(&BuildId(())),
+            Identity(4)
+        );
+    }
+
+    #[test]
+    fn sequential_writer() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = writer(3, 10).sequence(writer(4, 5));
+        let action2 = writer(3, 10).combine(writer(4, 5), |x, y| x + y);
+
+        assert_eq!(action.run_qualified(&scaffold), ((3, 4), 15));
+        assert_eq!(action2.run_qualified(&scaffold), (7, 15));
+        assert_eq!(
+            writer(3, 10).first(writer(4, 5)).run_qualified(&scaffold),
+            (3, 15)
+        );
+        assert_eq!(
+            writer(3, 10).second(writer(4, 5)).run_qualified(&scaffold),
+            (4, 15)
+        );
+    }
+
+    #[test]
+    fn sequential_error() {
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, ());
+
+        let action = pure(3).sequence(pure(4));
+        let action2 =
+            throw_error::<i32, _>("first").combine(throw_error::<i32, _>("second"), |x, y| x + y);
+
+        assert_eq!(action.run_qualified(&scaffold), Ok((3, 4)));
+        assert_eq!(action2.run_qualified(&scaffold), Err("first"));
+        assert_eq!(
+            pure(3)
+                .first(throw_error::<i32, _>("second"))
+                .run_qualified(&scaffold),
+            Err("second")
+        );
+    }
+
+    #[test]
+    fn sequential_does_not_depend() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, BuildWriter(writer_type, ()));
+
+        let action = writer(3, 10)
+            .sequence(throw_error::<i32, _>("failed"))
+            .and_then(|_| writer(0, 1));
+
+        assert_eq!(action.run_qualified(&scaffold), (Err("failed"), 10));
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Counted<A> {
+        value: A,
+        binds: usize,
+    }
+
+    fn counted<A>(value: A) -> Counted<A> {
+        Counted { value, binds: 0 }
+    }
+
+    fn bind_counted<A, B, F>(counted: Counted<A>, func: F) -> Counted<B>
+    where
+        F: Fn(A) -> Counted<B>,
+    {
+        let result = func(counted.value);
+        Counted {
+            value: result.value,
+            binds: counted.binds + result.binds + 1,
+        }
+    }
+
+    fn tail_rec_counted<A, B, F>(init: A, func: F) -> Counted<B>
+    where
+        F: Fn(A) -> Counted<Step<A, B>>,
+    {
+        let mut state = init;
+        let mut binds = 0;
+        loop {
+            let step = func(state);
+            binds += step.binds + 1;
+            match step.value {
+                Step::Loop(next) => state = next,
+                Step::Done(value) => return Counted { value, binds },
+            }
+        }
+    }
+
+    #[derive(Scaffold)]
+    #[scaffold(
+        complete = "Counted<A>",
+        pure = "counted",
+        bind = "bind_counted",
+        tail_rec = "tail_rec_counted"
+    )]
+    struct BuildCounted;
+
+    #[test]
+    fn derived_scaffold() {
+        let action = pure(3).and_then(|x| pure(x + 1)).map(|x| x * 2);
+        let action2 = pure(3).sequence(pure("three"));
+        let action3 = pure(3).combine(pure(4), |x, y| x * y);
+
+        assert_eq!(
+            action.run_qualified(&BuildCounted),
+            Counted { value: 8, binds: 2 }
+        );
+        assert_eq!(
+            action2.run_qualified(&BuildCounted),
+            Counted {
+                value: (3, "three"),
+                binds: 2
+            }
+        );
+        assert_eq!(
+            action3.run_qualified(&BuildCounted),
+            Counted {
+                value: 12,
+                binds: 3
+            }
+        );
+    }
+
+    #[derive(Scaffold)]
+    #[scaffold(
+        complete = "Counted<A>",
+        pure = "counted",
+        bind = "bind_counted",
+        tail_rec = "tail_rec_counted"
+    )]
+    struct BuildCountedBy<N: Fn(usize) -> usize = fn(usize) -> usize, D: std::fmt::Debug = ()>(
+        PhantomData<(N, D)>,
+    );
+
+    #[test]
+    fn derived_scaffold_with_fn_bound() {
+        let scaffold: BuildCountedBy = BuildCountedBy(PhantomData);
+
+        let action = pure(3).and_then(|x| pure(x + 1));
+
+        assert_eq!(
+            action.run_qualified(&scaffold),
+            Counted { value: 4, binds: 1 }
+        );
+    }
+
+    #[test]
+    fn derived_scaffold_loops() {
+        let action = tail_rec_m(0, |&n| {
+            pure(if n < 3 {
+                Step::Loop(n + 1)
+            } else {
+                Step::Done(n)
+            })
+        });
+
+        let action2 = traverse(vec![1, 2], |&x| pure(x * 10));
+
+        assert_eq!(
+            action.run_qualified(&BuildCounted),
+            Counted { value: 3, binds: 4 }
+        );
+        assert_eq!(
+            action2.run_qualified(&BuildCounted),
+            Counted {
+                value: vec![10, 20],
+                binds: 5
+            }
+        );
+    }
+
+    fn unlogged<A, W>(value: A) -> (A, W)
+    where
+        W: Monoid,
+    {
+        (value, W::default())
+    }
+
+    fn bind_logged<A, B, W, F>((value, log_a): (A, W), func: F) -> (B, W)
+    where
+        W: Monoid,
+        F: Fn(A) -> (B, W),
+    {
+        let (result, log_b) = func(value);
+        (result, log_a.op(&log_b))
+    }
+
+    fn logged<A, W>(value: A, log: W) -> (A, W) {
+        (value, log)
+    }
+
+    fn tail_rec_logged<A, B, W, F>(init: A, func: F) -> (B, W)
+    where
+        W: Monoid,
+        F: Fn(A) -> (Step<A, B>, W),
+    {
+        let mut state = init;
+        let mut log = W::default();
+        loop {
+            let (step, log_b) = func(state);
+            log = log.op(&log_b);
+            match step {
+                Step::Loop(next) => state = next,
+                Step::Done(result) => return (result, log),
+            }
+        }
+    }
+
+    #[derive(Scaffold)]
+    #[scaffold(
+        complete = "(A, W)",
+        pure = "unlogged",
+        bind = "bind_logged",
+        tail_rec = "tail_rec_logged",
+        writer = "logged",
+        log = "W"
+    )]
+    struct BuildLogged<W: Monoid>(PhantomData<fn() -> W>);
+
+    #[test]
+    fn derived_generic_scaffold() {
+        let writer_type: PhantomData<fn() -> Vec<String>> = PhantomData;
+        let scaffold = BuildLogged(writer_type);
+
+        let action = writer(3, vec!["three".to_string()])
+            .and_then(|&x| tell(vec![format!("{}", x + 1)]).map(move |_| x * 2));
+
+        assert_eq!(
+            action.run_qualified(&scaffold),
+            action.run_qualified(&BuildWriter(writer_type, ()))
+        );
+        assert_eq!(
+            action.run_qualified(&scaffold),
+            (6, vec!["three".to_string(), "4".to_string()])
+        );
+    }
+
+    #[test]
+    fn derived_tail_rec_runs_in_constant_stack() {
+        let writer_type: PhantomData<fn() -> monoid::Sum<u64>> = PhantomData;
+
+        let action = tail_rec_m(0, |&n| {
+            writer(
+                if n < 1_000_000 {
+                    Step::Loop(n + 1)
+                } else {
+                    Step::Done(n)
+                },
+                monoid::Sum(1),
+            )
+        });
+
+        assert_eq!(
+            action.run_qualified(&BuildLogged(writer_type)),
+            (1_000_000, monoid::Sum(1_000_001))
+        );
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Labelled<'a, A> {
+        value: A,
+        labels: Vec<&'a str>,
+    }
+
+    fn unlabelled<'a, A>(value: A) -> Labelled<'a, A> {
+        Labelled {
+            value,
+            labels: Vec::new(),
+        }
+    }
+
+    fn bind_labelled<'a, A, B, F>(labelled: Labelled<'a, A>, func: F) -> Labelled<'a, B>
+    where
+        F: Fn(A) -> Labelled<'a, B>,
+    {
+        let result = func(labelled.value);
+        let mut labels = labelled.labels;
+        labels.extend(result.labels);
+        Labelled {
+            value: result.value,
+            labels,
+        }
+    }
+
+    fn tail_rec_labelled<'a, A, B, F>(init: A, func: F) -> Labelled<'a, B>
+    where
+        F: Fn(A) -> Labelled<'a, Step<A, B>>,
+    {
+        let mut state = init;
+        let mut labels = Vec::new();
+        loop {
+            let step = func(state);
+            labels.extend(step.labels);
+            match step.value {
+                Step::Loop(next) => state = next,
+                Step::Done(value) => return Labelled { value, labels },
+            }
+        }
+    }
+
+    fn label<'a, A>(value: A, label: &'a str) -> Labelled<'a, A> {
+        Labelled {
+            value,
+            labels: vec![label],
+        }
+    }
+
+    #[derive(Scaffold)]
+    #[scaffold(
+        complete = "Labelled<'a, A>",
+        pure = "unlabelled",
+        bind = "bind_labelled",
+        tail_rec = "tail_rec_labelled",
+        writer = "label",
+        log = "&'a str"
+    )]
+    struct BuildLabelled<'a>(PhantomData<&'a str>);
+
+    #[test]
+    fn derived_scaffold_with_lifetime() {
+        let names = ["first".to_string(), "second".to_string()];
+
+        let action =
+            writer(3, names[0].as_str()).and_then(|&x| tell(names[1].as_str()).map(move |_| x * 2));
+
+        assert_eq!(
+            action.run_qualified(&BuildLabelled(PhantomData)),
+            Labelled {
+                value: 6,
+                labels: vec!["first", "second"]
+            }
+        );
+    }
+
+    fn singleton<A>(value: A) -> BTreeSet<A>
+    where
+        A: Ord,
+    {
+        Some(value).into_iter().collect()
+    }
+
+    fn bind_set<A, B, F>(set: BTreeSet<A>, func: F) -> BTreeSet<B>
+    where
+        B: Ord,
+        F: Fn(A) -> BTreeSet<B>,
+    {
+        set.into_iter().flat_map(func).collect()
+    }
+
+    /// Every state still to be run is kept in `pending`, rather than on the
+    /// stack.
+    fn tail_rec_set<A, B, F>(init: A, func: F) -> BTreeSet<B>
+    where
+        B: Ord,
+        F: Fn(A) -> BTreeSet<Step<A, B>>,
+    {
+        let mut pending = vec![init];
+        let mut results = BTreeSet::new();
+        while let Some(state) = pending.pop() {
+            for step in func(state) {
+                match step {
+                    Step::Loop(next) => pending.push(next),
+                    Step::Done(result) => {
+                        results.insert(result);
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    #[derive(Scaffold)]
+    #[scaffold(
+        complete = "BTreeSet<A>",
+        pure = "singleton",
+        bind = "bind_set",
+        tail_rec = "tail_rec_set",
+        bound = "Ord",
+        path = "super"
+    )]
+    struct BuildSet;
+
+    #[test]
+    fn derived_scaffold_with_bound_and_path() {
+        let numbers: BTreeSet<u32> = (1..=4).collect();
+
+        let action = literal::<BuildSet, _>(numbers.clone()).map(|x| x % 2);
+        let action2 = literal::<BuildSet, _>(numbers).sequence(pure("number"));
+
+        assert_eq!(action.run_qualified(&BuildSet), (0..2).collect());
+        assert_eq!(
+            action2.run_qualified(&BuildSet),
+            (1..=4).map(|x| (x, "number")).collect()
+        );
+    }
+}
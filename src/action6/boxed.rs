@@ -0,0 +1,208 @@
+use std::rc::Rc;
+
+use super::{Action, Ap, Mappable, Program, Run, Sequential, Trampolined};
+
+/// `Run` can't be made into a trait object, since `Mappable` and `Action`
+/// require `Sized`, so boxed actions are stored behind this trait instead.
+pub(super) trait DynRun<S, A>
+where
+    S: Ap<A>,
+{
+    fn dyn_run(&self) -> S::R;
+    /// Fails, giving the action back, if it can't be taken apart.
+    fn dyn_program(self: Box<Self>) -> Result<Program<S, A>, Box<dyn DynRun<S, A>>>;
+    /// Fails, giving the action back, if it can't be taken apart or is still
+    /// shared.
+    fn dyn_program_unique(self: Rc<Self>) -> Result<Program<S, A>, Rc<dyn DynRun<S, A>>>;
+}
+
+/// An action boxed by `Boxed`, which `run_trampolined` runs as a single
+/// effect.
+struct Whole<Act>(Act);
+impl<S, A, Act> DynRun<S, A> for Whole<Act>
+where
+    S: Ap<A>,
+    Act: Run<S, A> + 'static,
+{
+    fn dyn_run(&self) -> S::R {
+        self.0.run()
+    }
+    fn dyn_program(self: Box<Self>) -> Result<Program<S, A>, Box<dyn DynRun<S, A>>> {
+        Err(self)
+    }
+    fn dyn_program_unique(self: Rc<Self>) -> Result<Program<S, A>, Rc<dyn DynRun<S, A>>> {
+        Err(self)
+    }
+}
+
+/// An action boxed by `Trampolined::boxed_trampolined`, which
+/// `run_trampolined` can take apart.
+pub(super) struct Parts<Act>(pub(super) Act);
+impl<S, A, Act> DynRun<S, A> for Parts<Act>
+where
+    S: Ap<A>,
+    Act: Run<S, A> + Trampolined<S, A> + 'static,
+{
+    fn dyn_run(&self) -> S::R {
+        self.0.run()
+    }
+    fn dyn_program(self: Box<Self>) -> Result<Program<S, A>, Box<dyn DynRun<S, A>>> {
+        Ok(self.0.program())
+    }
+    fn dyn_program_unique(self: Rc<Self>) -> Result<Program<S, A>, Rc<dyn DynRun<S, A>>> {
+        match Rc::try_unwrap(self) {
+            Ok(parts) => Ok(parts.0.program()),
+            Err(parts) => Err(parts),
+        }
+    }
+}
+
+/// An action with its type erased, which can only be run with the scaffold
+/// `S`. Boxing lets actions of different types be stored together, or returned
+/// from the branches of a recursive function. Running such a function with
+/// `run` uses stack for every level of recursion, so deep recursion should be
+/// boxed with `Trampolined::boxed_trampolined`, and run with
+/// `Trampolined::run_trampolined`.
+pub struct BoxAction<S, A>
+where
+    S: Ap<A>,
+{
+    pub(super) action: Box<dyn DynRun<S, A>>,
+}
+impl<S, A> Mappable<A> for BoxAction<S, A> where S: Ap<A> {}
+impl<S, A> Sequential<A> for BoxAction<S, A> where S: Ap<A> {}
+impl<S, A> Action<A> for BoxAction<S, A> where S: Ap<A> {}
+impl<S, A> Run<S, A> for BoxAction<S, A>
+where
+    S: Ap<A>,
+{
+    fn run(&self) -> S::R {
+        self.action.dyn_run()
+    }
+}
+
+/// A type erased action which can be shared, so unlike `BoxAction` it can be
+/// cloned cheaply.
+pub struct RcAction<S, A>
+where
+    S: Ap<A>,
+{
+    pub(super) action: Rc<dyn DynRun<S, A>>,
+}
+impl<S, A> Clone for RcAction<S, A>
+where
+    S: Ap<A>,
+{
+    fn clone(&self) -> Self {
+        RcAction {
+            action: self.action.clone(),
+        }
+    }
+}
+impl<S, A> From<BoxAction<S, A>> for RcAction<S, A>
+where
+    S: Ap<A>,
+{
+    fn from(action: BoxAction<S, A>) -> Self {
+        RcAction {
+            action: Rc::from(action.action),
+        }
+    }
+}
+impl<S, A> Mappable<A> for RcAction<S, A> where S: Ap<A> {}
+impl<S, A> Sequential<A> for RcAction<S, A> where S: Ap<A> {}
+impl<S, A> Action<A> for RcAction<S, A> where S: Ap<A> {}
+impl<S, A> Run<S, A> for RcAction<S, A>
+where
+    S: Ap<A>,
+{
+    fn run(&self) -> S::R {
+        self.action.dyn_run()
+    }
+}
+
+/// Erase the type of an action which can be run with the scaffold `S`.
+///
+/// `Trampolined::run_trampolined` runs an action boxed this way as a single
+/// effect. Use `Trampolined::boxed_trampolined` instead to box an action which
+/// recurses deeply through its own boxed type.
+pub trait Boxed<S, A>: Run<S, A> + 'static
+where
+    S: Ap<A>,
+{
+    fn boxed(self) -> BoxAction<S, A> {
+        BoxAction {
+            action: Box::new(Whole(self)),
+        }
+    }
+    fn shared(self) -> RcAction<S, A> {
+        RcAction {
+            action: Rc::new(Whole(self)),
+        }
+    }
+}
+impl<S, A, Act> Boxed<S, A> for Act
+where
+    S: Ap<A>,
+    Act: Run<S, A> + 'static,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{pure, throw_error, writer, BuildError, BuildWriter};
+    use super::*;
+    use std::marker::PhantomData;
+
+    type Logged<A> = BoxAction<BuildWriter<u32>, A>;
+
+    fn countdown(n: u32) -> Logged<u32> {
+        if n == 0 {
+            pure(0).boxed()
+        } else {
+            writer(n, 1).and_then(|&n| countdown(n - 1)).boxed()
+        }
+    }
+
+    #[test]
+    fn recursive_actions() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        assert_eq!(countdown(100).run_qualified(&scaffold), (0, 100));
+    }
+
+    #[test]
+    fn different_actions_together() {
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, ());
+
+        let actions: Vec<BoxAction<BuildError<&str>, i32>> = vec![
+            pure(3).boxed(),
+            pure(4).map(|x| x * 2).boxed(),
+            throw_error("failed").boxed(),
+        ];
+
+        assert_eq!(
+            actions
+                .iter()
+                .map(|action| action.run_qualified(&scaffold))
+                .collect::<Vec<_>>(),
+            vec![Ok(3), Ok(8), Err("failed")]
+        );
+    }
+
+    #[test]
+    fn shared_actions() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let step: RcAction<BuildWriter<u32>, u32> = writer(2, 5).shared();
+        let step2 = step.clone();
+        let action = step.and_then(move |&x| step2.clone().map(move |y| x + y));
+        let action2: RcAction<_, _> = countdown(3).into();
+
+        assert_eq!(action.run_qualified(&scaffold), (4, 10));
+        assert_eq!(action2.run_qualified(&scaffold), (0, 3));
+    }
+}
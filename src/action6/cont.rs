@@ -0,0 +1,447 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use super::{
+    Action, AndThen, Ap, Literal, Map, MapFn, Mappable, Pure, Run, RunTraced, Sequential, Step,
+    TailRec, Tracer,
+};
+
+/// The scaffold for actions in continuation passing style, where `Res` is the
+/// type of the final answer.
+///
+/// The completed type waits for a final continuation before doing anything, and
+/// runs its loops through thunks which outlive the action that built them, so
+/// everything it captures must be `'static`, and `BuildCont` can not be nested
+/// inside another scaffold.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuildCont<Res>(pub PhantomData<fn() -> Res>);
+
+/// The final answer, or the rest of the work needed to find it. A loop's
+/// continuation returns the next iteration as a thunk instead of running it,
+/// so that `force` runs the iterations one after another rather than inside
+/// each other.
+enum Thunk<Res> {
+    Done(Res),
+    Later(Box<dyn FnOnce() -> Thunk<Res>>),
+}
+impl<Res> Thunk<Res> {
+    fn force(self) -> Res {
+        let mut thunk = self;
+        loop {
+            match thunk {
+                Thunk::Done(answer) => return answer,
+                Thunk::Later(next) => thunk = next(),
+            }
+        }
+    }
+}
+
+/// The rest of the program, waiting for a value of type `A`.
+type Continuation<Res, A> = Rc<dyn Fn(A) -> Thunk<Res>>;
+
+/// A suspended computation waiting for the rest of the program, completed by
+/// `BuildCont`.
+pub struct Cont<Res, A>(Rc<dyn Fn(Continuation<Res, A>) -> Thunk<Res>>);
+impl<Res, A> Cont<Res, A> {
+    fn new<F>(func: F) -> Self
+    where
+        F: Fn(Continuation<Res, A>) -> Thunk<Res> + 'static,
+    {
+        Cont(Rc::new(func))
+    }
+    fn run_cont_rc(&self, cont: Continuation<Res, A>) -> Thunk<Res> {
+        (self.0)(cont)
+    }
+    /// Run the computation, passing its result to the final continuation.
+    pub fn run_cont<F>(&self, cont: F) -> Res
+    where
+        F: Fn(A) -> Res + 'static,
+        Res: 'static,
+    {
+        self.run_cont_rc(Rc::new(move |result| Thunk::Done(cont(result))))
+            .force()
+    }
+}
+impl<A> Cont<A, A>
+where
+    A: 'static,
+{
+    /// Run the computation, using its result as the final answer.
+    pub fn eval_cont(&self) -> A {
+        self.run_cont(|result| result)
+    }
+}
+impl<Res, A> Clone for Cont<Res, A> {
+    fn clone(&self) -> Self {
+        Cont(self.0.clone())
+    }
+}
+
+impl<A, Res> Ap<A> for BuildCont<Res>
+where
+    A: Clone + 'static,
+    Res: 'static,
+{
+    type R = Cont<Res, A>;
+    fn build(value: A) -> Self::R {
+        Cont::new(move |cont| cont(value.clone()))
+    }
+}
+
+/// The continuation captured by `call_cc`. Jumping to it abandons the rest of
+/// the current computation, and resumes from where `call_cc` was run.
+pub struct Escape<Res, A> {
+    cont: Continuation<Res, A>,
+}
+impl<Res, A> Escape<Res, A> {
+    pub fn escape<B>(&self, value: A) -> Jump<Res, A, B> {
+        Jump {
+            cont: self.cont.clone(),
+            value,
+            _result_type: PhantomData,
+        }
+    }
+}
+impl<Res, A> Clone for Escape<Res, A> {
+    fn clone(&self) -> Self {
+        Escape {
+            cont: self.cont.clone(),
+        }
+    }
+}
+
+/// `Jump` is an action which passes a value to an escape continuation. Since
+/// it never returns, it can stand in for an action of any type.
+pub struct Jump<Res, A, B> {
+    cont: Continuation<Res, A>,
+    value: A,
+    _result_type: PhantomData<fn() -> B>,
+}
+impl<Res, A, B> Mappable<B> for Jump<Res, A, B> {}
+impl<Res, A, B> Sequential<B> for Jump<Res, A, B> {}
+impl<Res, A, B> Action<B> for Jump<Res, A, B> {}
+impl<Res, A, B> Run<BuildCont<Res>, B> for Jump<Res, A, B>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    Res: 'static,
+{
+    fn run(&self) -> Cont<Res, B> {
+        let cont = self.cont.clone();
+        let value = self.value.clone();
+        Cont::new(move |_| cont(value.clone()))
+    }
+}
+
+/// Call with current continuation. The function is given an `Escape` which can
+/// be used anywhere inside the action it returns to finish the whole `call_cc`
+/// early.
+pub struct CallCc<Res, A, ActA, F> {
+    func: F,
+    _escape_type: PhantomData<fn() -> (Res, A)>,
+    _act_a: PhantomData<fn() -> ActA>,
+}
+pub fn call_cc<Res, A, ActA, F>(func: F) -> CallCc<Res, A, ActA, F>
+where
+    ActA: Action<A>,
+    F: Fn(Escape<Res, A>) -> ActA,
+{
+    CallCc {
+        func,
+        _escape_type: PhantomData,
+        _act_a: PhantomData,
+    }
+}
+impl<Res, A, ActA, F> Mappable<A> for CallCc<Res, A, ActA, F>
+where
+    ActA: Mappable<A>,
+    F: Fn(Escape<Res, A>) -> ActA,
+{
+}
+impl<Res, A, ActA, F> Sequential<A> for CallCc<Res, A, ActA, F>
+where
+    ActA: Sequential<A>,
+    F: Fn(Escape<Res, A>) -> ActA,
+{
+}
+impl<Res, A, ActA, F> Action<A> for CallCc<Res, A, ActA, F>
+where
+    ActA: Action<A>,
+    F: Fn(Escape<Res, A>) -> ActA,
+{
+}
+impl<Res, A, ActA, F> Run<BuildCont<Res>, A> for CallCc<Res, A, ActA, F>
+where
+    A: Clone + 'static,
+    Res: 'static,
+    ActA: Run<BuildCont<Res>, A>,
+    F: Fn(Escape<Res, A>) -> ActA + Clone + 'static,
+{
+    fn run(&self) -> Cont<Res, A> {
+        let func = self.func.clone();
+        Cont::new(move |cont| {
+            let escape = Escape { cont: cont.clone() };
+            func(escape).run().run_cont_rc(cont)
+        })
+    }
+}
+
+impl<A, B, ActA, F, Res> Run<BuildCont<Res>, B> for Map<A, ActA, F>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    Res: 'static,
+    ActA: Run<BuildCont<Res>, A>,
+    F: MapFn<A, B> + Clone + 'static,
+{
+    fn run(&self) -> Cont<Res, B> {
+        let cont_a = self.act_a.run();
+        let func = self.func.clone();
+        Cont::new(move |cont| {
+            let func = func.clone();
+            cont_a.run_cont_rc(Rc::new(move |result_a| cont(func.call(&result_a))))
+        })
+    }
+}
+
+impl<A, B, ActA, ActB, F, Res> Run<BuildCont<Res>, B> for AndThen<A, ActA, ActB, F>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    Res: 'static,
+    ActA: Run<BuildCont<Res>, A>,
+    ActB: Run<BuildCont<Res>, B>,
+    F: Fn(&A) -> ActB + Clone + 'static,
+{
+    fn run(&self) -> Cont<Res, B> {
+        let cont_a = self.act_a.run();
+        let func = self.func.clone();
+        Cont::new(move |cont| {
+            let func = func.clone();
+            cont_a.run_cont_rc(Rc::new(move |result_a| {
+                func(&result_a).run().run_cont_rc(cont.clone())
+            }))
+        })
+    }
+}
+
+/// Each iteration's continuation returns the next iteration as a thunk, so the
+/// loop only needs constant stack however many times it repeats. An action
+/// which uses the answer its continuation returns still waits for the rest of
+/// the loop inside it, so only the iterations which call their continuation
+/// last are run one after another.
+impl<A, B, Res> TailRec<A, B> for BuildCont<Res>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    Res: 'static,
+{
+    fn tail_rec<F>(init: A, func: F) -> Cont<Res, B>
+    where
+        F: Fn(A) -> Cont<Res, Step<A, B>> + 'static,
+    {
+        let func = Rc::new(func);
+        Cont::new(move |cont| run_loop(&func, init.clone(), cont))
+    }
+}
+
+fn run_loop<Res, A, B, F>(func: &Rc<F>, value: A, cont: Continuation<Res, B>) -> Thunk<Res>
+where
+    A: 'static,
+    B: 'static,
+    Res: 'static,
+    F: Fn(A) -> Cont<Res, Step<A, B>> + 'static,
+{
+    let next = func.clone();
+    func(value).run_cont_rc(Rc::new(move |step| match step {
+        Step::Loop(value) => {
+            let next = next.clone();
+            let cont = cont.clone();
+            Thunk::Later(Box::new(move || run_loop(&next, value, cont)))
+        }
+        Step::Done(result) => cont(result),
+    }))
+}
+
+impl<Res, A> Cont<Res, A>
+where
+    Res: 'static,
+    A: 'static,
+{
+    /// Run inside a span for the step `Act`, from when the computation is
+    /// called until it passes its result to the continuation. A computation
+    /// which is resumed more than once only has a span the first time.
+    fn traced<Act>(self, tracer: &Rc<Tracer<'static>>, name: &'static str) -> Self {
+        let tracer = tracer.clone();
+        Cont::new(move |cont| {
+            let span = Rc::new(Cell::new(Some(tracer.start::<Act>(name))));
+            let tracer = tracer.clone();
+            self.run_cont_rc(Rc::new(move |result| {
+                if let Some(span) = span.take() {
+                    tracer.finish(span);
+                }
+                cont(result)
+            }))
+        })
+    }
+}
+
+impl<A, Res> RunTraced<'static, BuildCont<Res>, A> for Pure<A>
+where
+    A: Clone + 'static,
+    Res: 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> Cont<Res, A> {
+        <BuildCont<Res> as Ap<A>>::build(self.value.clone()).traced::<Self>(tracer, "Pure")
+    }
+}
+
+impl<A, Res> RunTraced<'static, BuildCont<Res>, A> for Literal<BuildCont<Res>, A>
+where
+    A: Clone + 'static,
+    Res: 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> Cont<Res, A> {
+        self.value.clone().traced::<Self>(tracer, "Literal")
+    }
+}
+
+impl<A, B, ActA, F, Res> RunTraced<'static, BuildCont<Res>, B> for Map<A, ActA, F>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    Res: 'static,
+    ActA: RunTraced<'static, BuildCont<Res>, A>,
+    F: MapFn<A, B> + Clone + 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> Cont<Res, B> {
+        let cont_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        Cont::new(move |cont| {
+            let func = func.clone();
+            cont_a.run_cont_rc(Rc::new(move |result_a| cont(func.call(&result_a))))
+        })
+        .traced::<Self>(tracer, "Map")
+    }
+}
+
+impl<A, B, ActA, ActB, F, Res> RunTraced<'static, BuildCont<Res>, B> for AndThen<A, ActA, ActB, F>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    Res: 'static,
+    ActA: RunTraced<'static, BuildCont<Res>, A>,
+    ActB: RunTraced<'static, BuildCont<Res>, B>,
+    F: Fn(&A) -> ActB + Clone + 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> Cont<Res, B> {
+        let cont_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        let tracer_b = tracer.clone();
+        Cont::new(move |cont| {
+            let func = func.clone();
+            let tracer_b = tracer_b.clone();
+            cont_a.run_cont_rc(Rc::new(move |result_a| {
+                func(&result_a).trace(&tracer_b).run_cont_rc(cont.clone())
+            }))
+        })
+        .traced::<Self>(tracer, "AndThen")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{literal, pure, tail_rec_m};
+    use super::*;
+
+    #[test]
+    fn final_continuation() {
+        let scaffold: BuildCont<String> = BuildCont(PhantomData);
+
+        let action = pure(3).and_then(|x| pure(x + 2)).map(|y| y * 2);
+
+        assert_eq!(
+            action
+                .run_qualified(&scaffold)
+                .run_cont(|x| format!("{}", x)),
+            "10"
+        );
+    }
+
+    #[test]
+    fn call_cc_exits_early() {
+        let scaffold: BuildCont<i32> = BuildCont(PhantomData);
+
+        let action = call_cc(|exit: Escape<i32, i32>| {
+            pure(1)
+                .and_then(move |&x| exit.escape::<i32>(x + 10))
+                .map(|y| y * 100)
+        })
+        .map(|z| z + 1);
+
+        assert_eq!(action.run_qualified(&scaffold).eval_cont(), 12);
+    }
+
+    #[test]
+    fn call_cc_without_escape() {
+        let scaffold: BuildCont<i32> = BuildCont(PhantomData);
+
+        let action = call_cc(|_: Escape<i32, i32>| pure(1).map(|y| y * 100));
+
+        assert_eq!(action.run_qualified(&scaffold).eval_cont(), 100);
+    }
+
+    #[test]
+    fn loops_wrap_continuations() {
+        let scaffold: BuildCont<String> = BuildCont(PhantomData);
+
+        let action = tail_rec_m(0, |&n: &u32| {
+            let step = if n < 1_000 {
+                Step::Loop(n + 1)
+            } else {
+                Step::Done(n)
+            };
+            pure(step)
+        });
+        let action2 = tail_rec_m(0, |&n: &u32| {
+            literal::<BuildCont<String>, _>(Cont::new(move |cont| {
+                let step = if n < 3 {
+                    Step::Loop(n + 1)
+                } else {
+                    Step::Done(n)
+                };
+                Thunk::Done(format!("<{}>", cont(step).force()))
+            }))
+        });
+
+        assert_eq!(
+            action
+                .run_qualified(&scaffold)
+                .run_cont(|x| format!("{}", x)),
+            "1000"
+        );
+        assert_eq!(
+            action2
+                .run_qualified(&scaffold)
+                .run_cont(|x| format!("{}", x)),
+            "<<<<3>>>>"
+        );
+    }
+
+    #[test]
+    fn loop_runs_in_constant_stack() {
+        let scaffold: BuildCont<u64> = BuildCont(PhantomData);
+
+        let action = tail_rec_m((0u64, 0u64), |&(n, total)| {
+            pure(if n < 1_000_000 {
+                Step::Loop((n + 1, total + n))
+            } else {
+                Step::Done(total)
+            })
+        })
+        .map(|total| total + 1);
+
+        assert_eq!(action.run_qualified(&scaffold).eval_cont(), 499_999_500_001);
+    }
+}
@@ -0,0 +1,357 @@
+use std::fmt;
+
+use super::{
+    AndThen, Ap, Ask, Asks, BoxAction, CallCc, CatchError, Censor, Choose, Combine, EitherAction,
+    First, FoldM, ForEach, FromFuture, FromOption, Get, Gets, Guard, Jump, Listen, Literal, Local,
+    Map, Memo, Modify, Nothing, OrElse, Pass, Pure, Put, RcAction, ReplicateM, Second, Sequence,
+    SequenceVec, TailRecM, Tell, ThrowError, Traverse, WhileM, Writer,
+};
+
+/// The structure of an action: the kind of step, and the steps inside it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Description {
+    pub name: &'static str,
+    /// The steps inside this one, each with the role it plays, such as
+    /// `"then"` for the action returned by the function in an `AndThen`.
+    pub children: Vec<(&'static str, Description)>,
+}
+impl Description {
+    pub fn new(name: &'static str) -> Self {
+        Description {
+            name,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn child(mut self, role: &'static str, child: Description) -> Self {
+        self.children.push((role, child));
+        self
+    }
+
+    /// Visit every step in order, with a number for the step and the number
+    /// of the step it's inside.
+    fn walk<F>(&self, visit: &mut F)
+    where
+        F: FnMut(usize, Option<(usize, &'static str)>, &Description),
+    {
+        fn go<F>(
+            node: &Description,
+            parent: Option<(usize, &'static str)>,
+            next: &mut usize,
+            visit: &mut F,
+        ) where
+            F: FnMut(usize, Option<(usize, &'static str)>, &Description),
+        {
+            let id = *next;
+            *next += 1;
+            visit(id, parent, node);
+            for (role, child) in &node.children {
+                go(child, Some((id, role)), next, visit);
+            }
+        }
+        go(self, None, &mut 0, visit)
+    }
+
+    /// Draw the action as a Graphviz graph.
+    pub fn to_dot(&self) -> String {
+        let mut nodes = String::new();
+        let mut edges = String::new();
+        self.walk(&mut |id, parent, node| {
+            let label = node.name.replace('\\', "\\\\").replace('"', "\\\"");
+            nodes.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+            if let Some((parent, role)) = parent {
+                edges.push_str(&format!(
+                    "    n{} -> n{} [label=\"{}\"];\n",
+                    parent, id, role
+                ));
+            }
+        });
+        format!("digraph action {{\n{}{}}}\n", nodes, edges)
+    }
+
+    /// Draw the action as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        let mut nodes = String::new();
+        let mut edges = String::new();
+        self.walk(&mut |id, parent, node| {
+            let label = node.name.replace('"', "#quot;");
+            nodes.push_str(&format!("    n{}[\"{}\"]\n", id, label));
+            if let Some((parent, role)) = parent {
+                edges.push_str(&format!("    n{} -->|{}| n{}\n", parent, role, id));
+            }
+        });
+        format!("flowchart TD\n{}{}", nodes, edges)
+    }
+}
+
+/// An indented tree, one step per line.
+impl fmt::Display for Description {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn go(
+            node: &Description,
+            role: Option<&str>,
+            indent: usize,
+            f: &mut fmt::Formatter,
+        ) -> fmt::Result {
+            write!(f, "{:width$}", "", width = indent * 2)?;
+            if let Some(role) = role {
+                write!(f, "{}: ", role)?;
+            }
+            writeln!(f, "{}", node.name)?;
+            for (role, child) in &node.children {
+                go(child, Some(role), indent + 1, f)?;
+            }
+            Ok(())
+        }
+        go(self, None, 0, f)
+    }
+}
+
+/// Describe an action without running it.
+///
+/// The action returned by a function, such as in `AndThen`, doesn't exist
+/// until the action is run, so it's described from its type alone. A type
+/// can't say which branch of an `EitherAction` will be taken, so it describes
+/// both, whereas an `EitherAction` value only describes the branch it holds.
+pub trait Describe {
+    fn describe_type() -> Description
+    where
+        Self: Sized;
+    fn describe(&self) -> Description
+    where
+        Self: Sized,
+    {
+        Self::describe_type()
+    }
+}
+
+impl<A, ActA, F> Describe for Map<A, ActA, F>
+where
+    ActA: Describe,
+{
+    fn describe_type() -> Description {
+        Description::new("Map").child("act_a", ActA::describe_type())
+    }
+    fn describe(&self) -> Description {
+        Description::new("Map").child("act_a", self.act_a.describe())
+    }
+}
+
+impl<A, ActA, ActB, F> Describe for AndThen<A, ActA, ActB, F>
+where
+    ActA: Describe,
+    ActB: Describe,
+{
+    fn describe_type() -> Description {
+        Description::new("AndThen")
+            .child("act_a", ActA::describe_type())
+            .child("then", ActB::describe_type())
+    }
+    fn describe(&self) -> Description {
+        Description::new("AndThen")
+            .child("act_a", self.act_a.describe())
+            .child("then", ActB::describe_type())
+    }
+}
+
+/// The actions which run two actions independently of each other.
+macro_rules! describe_pair {
+    ($(impl<$($param:ident),*> for $action:ty => $name:expr, $act_a:tt, $act_b:tt;)*) => {
+        $(
+            impl<$($param),*> Describe for $action
+            where
+                ActA: Describe,
+                ActB: Describe,
+            {
+                fn describe_type() -> Description {
+                    Description::new($name)
+                        .child("act_a", ActA::describe_type())
+                        .child("act_b", ActB::describe_type())
+                }
+                fn describe(&self) -> Description {
+                    Description::new($name)
+                        .child("act_a", self.$act_a.describe())
+                        .child("act_b", self.$act_b.describe())
+                }
+            }
+        )*
+    };
+}
+
+describe_pair! {
+    impl<ActA, ActB> for Sequence<ActA, ActB> => "Sequence", 0, 1;
+    impl<A, B, ActA, ActB, F> for Combine<A, B, ActA, ActB, F> => "Combine", act_a, act_b;
+    impl<B, ActA, ActB> for First<B, ActA, ActB> => "First", act_a, act_b;
+    impl<A, ActA, ActB> for Second<A, ActA, ActB> => "Second", act_a, act_b;
+}
+
+impl<L, R> Describe for EitherAction<L, R>
+where
+    L: Describe,
+    R: Describe,
+{
+    fn describe_type() -> Description {
+        Description::new("Either")
+            .child("left", L::describe_type())
+            .child("right", R::describe_type())
+    }
+    fn describe(&self) -> Description {
+        match self {
+            EitherAction::Left(act_l) => Description::new("Either").child("left", act_l.describe()),
+            EitherAction::Right(act_r) => {
+                Description::new("Either").child("right", act_r.describe())
+            }
+        }
+    }
+}
+
+impl<S, A> Describe for Literal<S, A>
+where
+    S: Ap<A>,
+{
+    fn describe_type() -> Description {
+        Description::new("Literal")
+    }
+}
+
+/// A boxed action could be any action, so it can't be looked inside.
+impl<S, A> Describe for BoxAction<S, A>
+where
+    S: Ap<A>,
+{
+    fn describe_type() -> Description {
+        Description::new("BoxAction")
+    }
+}
+impl<S, A> Describe for RcAction<S, A>
+where
+    S: Ap<A>,
+{
+    fn describe_type() -> Description {
+        Description::new("RcAction")
+    }
+}
+
+/// The other actions are described from their types, with a child for each
+/// action type they contain.
+macro_rules! describe_type {
+    ($(impl<$($param:ident),*> for $action:ty => $name:expr $(, $role:expr => $child:ident)*;)*) => {
+        $(
+            impl<$($param),*> Describe for $action
+            where
+                $($child: Describe,)*
+            {
+                fn describe_type() -> Description {
+                    Description::new($name)$(.child($role, $child::describe_type()))*
+                }
+            }
+        )*
+    };
+}
+
+describe_type! {
+    impl<A> for Pure<A> => "Pure";
+    impl<A, W> for Writer<A, W> => "Writer";
+    impl<W> for Tell<W> => "Tell";
+    impl<A, W, ActA> for Listen<A, W, ActA> => "Listen", "act_a" => ActA;
+    impl<F, ActA> for Pass<F, ActA> => "Pass", "act_a" => ActA;
+    impl<ActA, F> for Censor<ActA, F> => "Censor", "act_a" => ActA;
+    impl<A, E> for ThrowError<A, E> => "ThrowError";
+    impl<E, ActA, ActB, F> for CatchError<E, ActA, ActB, F> => "CatchError",
+        "act_a" => ActA, "handler" => ActB;
+    impl<A> for Nothing<A> => "Nothing";
+    impl<A> for FromOption<A> => "FromOption";
+    impl<ActA, ActB, F> for OrElse<ActA, ActB, F> => "OrElse", "act_a" => ActA, "or_else" => ActB;
+    impl<A> for Choose<A> => "Choose";
+    impl<> for Guard => "Guard";
+    impl<St> for Get<St> => "Get";
+    impl<St> for Put<St> => "Put";
+    impl<St, F> for Modify<St, F> => "Modify";
+    impl<St, F> for Gets<St, F> => "Gets";
+    impl<Env> for Ask<Env> => "Ask";
+    impl<Env, F> for Asks<Env, F> => "Asks";
+    impl<Env, ActA, F> for Local<Env, ActA, F> => "Local", "act_a" => ActA;
+    impl<Res, A, B> for Jump<Res, A, B> => "Jump";
+    impl<Res, A, ActA, F> for CallCc<Res, A, ActA, F> => "CallCc", "act_a" => ActA;
+    impl<A, ActStep, F> for TailRecM<A, ActStep, F> => "TailRecM", "step" => ActStep;
+    impl<T, ActB, F> for Traverse<T, ActB, F> => "Traverse", "each" => ActB;
+    impl<ActA> for SequenceVec<ActA> => "SequenceVec", "each" => ActA;
+    impl<ActA> for ReplicateM<ActA> => "ReplicateM", "each" => ActA;
+    impl<T, B, ActB, F> for ForEach<T, B, ActB, F> => "ForEach", "each" => ActB;
+    impl<T, Acc, ActAcc, F> for FoldM<T, Acc, ActAcc, F> => "FoldM", "step" => ActAcc;
+    impl<ActCond, ActA> for WhileM<ActCond, ActA> => "WhileM", "cond" => ActCond, "body" => ActA;
+    impl<F> for FromFuture<F> => "FromFuture";
+    impl<K, V, ActA, C> for Memo<K, V, ActA, C> => "Memo", "act_a" => ActA;
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{pure, tell, writer, Action, Mappable, Sequential};
+    use super::*;
+
+    #[test]
+    fn describes_structure() {
+        let action = pure(3)
+            .map(|x| x + 1)
+            .sequence(tell(1))
+            .and_then(|&(x, ())| writer(x, 2));
+
+        assert_eq!(
+            action.describe(),
+            Description::new("AndThen")
+                .child(
+                    "act_a",
+                    Description::new("Sequence")
+                        .child(
+                            "act_a",
+                            Description::new("Map").child("act_a", Description::new("Pure"))
+                        )
+                        .child("act_b", Description::new("Tell"))
+                )
+                .child("then", Description::new("Writer"))
+        );
+        assert_eq!(
+            action.describe().to_string(),
+            "AndThen\n  act_a: Sequence\n    act_a: Map\n      act_a: Pure\n    act_b: Tell\n  \
+             then: Writer\n"
+        );
+    }
+
+    #[test]
+    fn either_values_and_types() {
+        let action = |x: u32| {
+            if x > 5 {
+                EitherAction::Left(pure(x))
+            } else {
+                EitherAction::Right(writer(x, 1))
+            }
+        };
+        let chained = pure(3).and_then(move |&x| action(x));
+
+        assert_eq!(
+            action(7).describe(),
+            Description::new("Either").child("left", Description::new("Pure"))
+        );
+        assert_eq!(
+            chained.describe().to_string(),
+            "AndThen\n  act_a: Pure\n  then: Either\n    left: Pure\n    right: Writer\n"
+        );
+    }
+
+    #[test]
+    fn exports() {
+        let action = pure(3).map(|x| x + 1).and_then(|&x| pure(x));
+
+        assert_eq!(
+            action.describe().to_dot(),
+            "digraph action {\n    n0 [label=\"AndThen\"];\n    n1 [label=\"Map\"];\n    \
+             n2 [label=\"Pure\"];\n    n3 [label=\"Pure\"];\n    n0 -> n1 [label=\"act_a\"];\n    \
+             n1 -> n2 [label=\"act_a\"];\n    n0 -> n3 [label=\"then\"];\n}\n"
+        );
+        assert_eq!(
+            action.describe().to_mermaid(),
+            "flowchart TD\n    n0[\"AndThen\"]\n    n1[\"Map\"]\n    n2[\"Pure\"]\n    \
+             n3[\"Pure\"]\n    n0 -->|act_a| n1\n    n1 -->|act_a| n2\n    n0 -->|then| n3\n"
+        );
+    }
+}
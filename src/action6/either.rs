@@ -0,0 +1,166 @@
+use super::{Action, Ap, Mappable, Run, Sequential};
+
+/// `EitherAction` holds one of two actions with the same result type, so that
+/// a function can return different actions from each branch without boxing.
+/// It runs whichever action it holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EitherAction<L, R> {
+    Left(L),
+    Right(R),
+}
+impl<A, L, R> Mappable<A> for EitherAction<L, R>
+where
+    L: Mappable<A>,
+    R: Mappable<A>,
+{
+}
+impl<A, L, R> Sequential<A> for EitherAction<L, R>
+where
+    L: Sequential<A>,
+    R: Sequential<A>,
+{
+}
+impl<A, L, R> Action<A> for EitherAction<L, R>
+where
+    L: Action<A>,
+    R: Action<A>,
+{
+}
+impl<S, A, L, R> Run<S, A> for EitherAction<L, R>
+where
+    S: Ap<A>,
+    L: Run<S, A>,
+    R: Run<S, A>,
+{
+    fn run(&self) -> S::R {
+        match self {
+            EitherAction::Left(act_l) => act_l.run(),
+            EitherAction::Right(act_r) => act_r.run(),
+        }
+    }
+}
+
+/// Choose between two actions using a condition.
+pub fn if_then_else<A, ActA, ActB>(
+    condition: bool,
+    act_a: ActA,
+    act_b: ActB,
+) -> EitherAction<ActA, ActB>
+where
+    ActA: Action<A>,
+    ActB: Action<A>,
+{
+    if condition {
+        EitherAction::Left(act_a)
+    } else {
+        EitherAction::Right(act_b)
+    }
+}
+
+/// Choose between any number of actions with a `match`.
+///
+/// Each arm may produce a different type of action. The arms are wrapped in
+/// nested `EitherAction`s, so the first arm is `Left(act)`, the second is
+/// `Right(Left(act))`, and so on, with the last arm only wrapped in `Right`s.
+///
+/// ```ignore
+/// pure(n).and_then(|&n| select!(n % 3;
+///     0 => pure(n),
+///     1 => writer(n, 1),
+///     _ => tell(2).map(move |_| n),
+/// ))
+/// ```
+#[macro_export]
+macro_rules! select {
+    ($value:expr; $($($pattern:pat)|+ $(if $guard:expr)? => $act:expr),+ $(,)?) => {
+        $crate::select!(
+            @arms ($value) [] [] $({ ($($pattern)|+ $(if $guard)?) ($act) })+
+        )
+    };
+    (@arms ($value:expr) [$($arms:tt)*] [$($wrap:path),*] { ($($head:tt)*) ($act:expr) }) => {
+        match $value {
+            $($arms)*
+            $($head)* => $crate::select!(@wrap [$($wrap),*] $act),
+        }
+    };
+    (@arms ($value:expr) [$($arms:tt)*] [$($wrap:path),*] { ($($head:tt)*) ($act:expr) } $($rest:tt)+) => {
+        $crate::select!(
+            @arms ($value)
+            [
+                $($arms)*
+                $($head)* => $crate::select!(
+                    @wrap [$($wrap,)* $crate::action6::EitherAction::Left] $act
+                ),
+            ]
+            [$($wrap,)* $crate::action6::EitherAction::Right]
+            $($rest)+
+        )
+    };
+    (@wrap [] $act:expr) => {
+        $act
+    };
+    (@wrap [$first:path $(, $wrap:path)*] $act:expr) => {
+        $first($crate::select!(@wrap [$($wrap),*] $act))
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{pure, tell, throw_error, writer, BuildError, BuildWriter};
+    use super::*;
+    use std::marker::PhantomData;
+
+    #[test]
+    fn branches_differ() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = |x: u32| {
+            pure(x).and_then(|&x| if_then_else(x > 5, writer(x, 10), pure(x).map(|x| x * 2)))
+        };
+
+        assert_eq!(action(7).run_qualified(&scaffold), (7, 10));
+        assert_eq!(action(3).run_qualified(&scaffold), (6, 0));
+    }
+
+    #[test]
+    fn select_many_arms() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = |x: u32| {
+            pure(x).and_then(|&x| {
+                select!(x % 4;
+                    0 => pure(x),
+                    1 | 2 => writer(x, 1),
+                    n if n == x => tell(5).map(move |_| x * 10),
+                    _ => pure(0),
+                )
+            })
+        };
+
+        assert_eq!(action(8).run_qualified(&scaffold), (8, 0));
+        assert_eq!(action(5).run_qualified(&scaffold), (5, 1));
+        assert_eq!(action(2).run_qualified(&scaffold), (2, 1));
+        assert_eq!(action(3).run_qualified(&scaffold), (30, 5));
+        assert_eq!(action(7).run_qualified(&scaffold), (0, 0));
+    }
+
+    #[test]
+    fn select_error() {
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, ());
+
+        let parse = |input: &'static str| {
+            select!(input.parse::<i32>();
+                Ok(x) if x < 0 => throw_error("negative"),
+                Ok(x) => pure(x),
+                Err(_) => throw_error("not a number"),
+            )
+        };
+
+        assert_eq!(parse("12").run_qualified(&scaffold), Ok(12));
+        assert_eq!(parse("-1").run_qualified(&scaffold), Err("negative"));
+        assert_eq!(parse("x").run_qualified(&scaffold), Err("not a number"));
+    }
+}
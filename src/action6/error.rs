@@ -1,250 +1,567 @@
 use std::marker::PhantomData;
 
 use super::{
-    Action, AndThen, AndThenCtx, Run,
+    Action, AndThen, Ap, BaseListen, BaseWriter, Bind, BuildError, BuildId, BuildValidation,
+    BuildWriter, Combine, First, Identity, Map, MapFn, Mappable, Run, Second, Sequential, Zip,
 };
 
-pub struct ThrowError<E> {
+/// `ThrowError` is an action which fails with an error. When run with an error
+/// scaffold, every action following it in an `and_then` chain is skipped.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ThrowError<A, E> {
     error: E,
+    _result_type: PhantomData<fn() -> A>,
 }
-impl <A, E> Action<A> for ThrowError<E> {}
-pub fn throw_error<E>(err: E) -> ThrowError<E> {
-    ThrowError(value)
+pub fn throw_error<A, E>(error: E) -> ThrowError<A, E> {
+    ThrowError {
+        error,
+        _result_type: PhantomData,
+    }
+}
+impl<A, E> Mappable<A> for ThrowError<A, E> {}
+impl<A, E> Sequential<A> for ThrowError<A, E> {}
+impl<A, E> Action<A> for ThrowError<A, E> {}
+/// An error can be thrown with any scaffold which contains a `BuildError`.
+impl<A, E, S> Run<S, A> for ThrowError<A, E>
+where
+    S: BaseError<A, E>,
+    E: Clone,
+{
+    fn run(&self) -> S::R {
+        S::throw_error(self.error.clone())
+    }
 }
 
-pub struct CatchError<'a, ActA, ActB, F, E> {
-    act_a: ActA,
-    func: F,
-    _act_b: PhantomData<&'a ActB>,
+/// Scaffolds which can complete an error in place of a value. An outer
+/// scaffold which is not an error passes the error through to the scaffold it
+/// wraps.
+pub trait BaseError<A, E>: Ap<A> {
+    fn throw_error(error: E) -> Self::R;
+}
+impl<A, E, S> BaseError<A, E> for BuildError<E, S>
+where
+    S: Ap<Result<A, E>>,
+{
+    fn throw_error(error: E) -> Self::R {
+        S::build(Err(error))
+    }
 }
-impl <'a, A, ActA, ActB, F, E> Action<A> for CatchError<'a, ActA, ActB, F, E>
+impl<A, E, S> BaseError<A, E> for BuildValidation<E, S>
+where
+    S: Ap<Result<A, E>>,
+{
+    fn throw_error(error: E) -> Self::R {
+        S::build(Err(error))
+    }
+}
+impl<A, E, S> BaseError<A, E> for BuildId<S>
+where
+    S: BaseError<Identity<A>, E>,
+{
+    fn throw_error(error: E) -> Self::R {
+        S::throw_error(error)
+    }
+}
+impl<A, E, S, W> BaseError<A, E> for BuildWriter<W, S>
+where
+    S: BaseError<(A, W), E>,
+    W: Default,
+{
+    fn throw_error(error: E) -> Self::R {
+        S::throw_error(error)
+    }
+}
+
+/// Scaffolds which can recover from an error completed by `throw_error`. Like
+/// `BaseError`, an outer scaffold which is not an error passes the handler
+/// through to the scaffold it wraps.
+pub trait BaseCatchError<A, E>: BaseError<A, E> {
+    /// Complete `value`, or if it holds an error, the value `func` completes
+    /// for that error.
+    fn catch_error<F>(value: Self::R, func: F) -> Self::R
+    where
+        F: Fn(E) -> Self::R;
+}
+impl<A, E, S> BaseCatchError<A, E> for BuildError<E, S>
+where
+    S: Bind<Result<A, E>, Result<A, E>>,
+{
+    fn catch_error<F>(value: Self::R, func: F) -> Self::R
+    where
+        F: Fn(E) -> Self::R,
+    {
+        S::bind(value, |result| match result {
+            Ok(result) => S::build(Ok(result)),
+            Err(err) => func(err),
+        })
+    }
+}
+impl<A, E, S> BaseCatchError<A, E> for BuildValidation<E, S>
+where
+    S: Bind<Result<A, E>, Result<A, E>>,
+{
+    fn catch_error<F>(value: Self::R, func: F) -> Self::R
+    where
+        F: Fn(E) -> Self::R,
+    {
+        BuildError::<E, S>::catch_error(value, func)
+    }
+}
+impl<A, E, S> BaseCatchError<A, E> for BuildId<S>
+where
+    S: BaseCatchError<Identity<A>, E>,
+{
+    fn catch_error<F>(value: Self::R, func: F) -> Self::R
+    where
+        F: Fn(E) -> Self::R,
+    {
+        S::catch_error(value, func)
+    }
+}
+/// The log written by a failed action is lost with it, and the handler's log
+/// is kept.
+impl<A, E, S, W> BaseCatchError<A, E> for BuildWriter<W, S>
+where
+    S: BaseCatchError<(A, W), E>,
+    W: Default,
+{
+    fn catch_error<F>(value: Self::R, func: F) -> Self::R
+    where
+        F: Fn(E) -> Self::R,
+    {
+        S::catch_error(value, func)
+    }
+}
+
+/// Run an action, and if it fails, use the error to choose an action to run in
+/// its place. Errors can be caught with any scaffold which contains a
+/// `BuildError` or `BuildValidation`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CatchError<E, ActA, ActB, F> {
+    pub(super) act_a: ActA,
+    pub(super) func: F,
+    _error_type: PhantomData<fn() -> E>,
+    _act_b: PhantomData<fn() -> ActB>,
+}
+pub fn catch_error<A, E, ActA, ActB, F>(act_a: ActA, func: F) -> CatchError<E, ActA, ActB, F>
 where
     ActA: Action<A>,
     ActB: Action<A>,
     F: Fn(&E) -> ActB,
-{}
-
-trait ErrorT<A, E> {
-
+{
+    CatchError {
+        act_a,
+        func,
+        _error_type: PhantomData,
+        _act_b: PhantomData,
+    }
 }
-
-trait BaseError<A, S, E>: BaseAction<A> + Run<S, A>
+impl<A, E, ActA, ActB, F> Mappable<A> for CatchError<E, ActA, ActB, F>
 where
-    S: TypeBuilder<A, Output = Self>,
-    Self: Sized,
+    ActA: Mappable<A>,
+    ActB: Mappable<A>,
+    F: Fn(&E) -> ActB,
+{
+}
+impl<A, E, ActA, ActB, F> Sequential<A> for CatchError<E, ActA, ActB, F>
+where
+    ActA: Sequential<A>,
+    ActB: Sequential<A>,
+    F: Fn(&E) -> ActB,
+{
+}
+impl<A, E, ActA, ActB, F> Action<A> for CatchError<E, ActA, ActB, F>
+where
+    ActA: Action<A>,
+    ActB: Action<A>,
+    F: Fn(&E) -> ActB,
 {
-    fn throw_error(err: &E) -> Self;
-    fn catch_error<ActA, ActB, F>(action: &ActA, func: F) -> Self
+}
+impl<E, ActA, ActB, F> CatchError<E, ActA, ActB, F> {
+    /// Run with any scaffold which can catch errors. The run instances below
+    /// use this, one for each scaffold which can contain an error.
+    pub(super) fn run_catch<S, A>(&self) -> <S as Ap<A>>::R
     where
+        S: BaseCatchError<A, E>,
         ActA: Run<S, A>,
         ActB: Run<S, A>,
-        F: Fn(&E) -> ActB;
+        F: Fn(&E) -> ActB,
+    {
+        S::catch_error(self.act_a.run(), |err_a| (self.func)(&err_a).run())
+    }
+}
+impl<A, E, ActA, ActB, F, S> Run<BuildError<E, S>, A> for CatchError<E, ActA, ActB, F>
+where
+    S: Bind<Result<A, E>, Result<A, E>>,
+    ActA: Run<BuildError<E, S>, A>,
+    ActB: Run<BuildError<E, S>, A>,
+    F: Fn(&E) -> ActB,
+{
+    fn run(&self) -> <BuildError<E, S> as Ap<A>>::R {
+        self.run_catch::<BuildError<E, S>, A>()
+    }
+}
+impl<A, E, ActA, ActB, F, S> Run<BuildValidation<E, S>, A> for CatchError<E, ActA, ActB, F>
+where
+    S: Bind<Result<A, E>, Result<A, E>>,
+    ActA: Run<BuildValidation<E, S>, A>,
+    ActB: Run<BuildValidation<E, S>, A>,
+    F: Fn(&E) -> ActB,
+{
+    fn run(&self) -> <BuildValidation<E, S> as Ap<A>>::R {
+        self.run_catch::<BuildValidation<E, S>, A>()
+    }
+}
+impl<A, E, ActA, ActB, F, S> Run<BuildId<S>, A> for CatchError<E, ActA, ActB, F>
+where
+    S: BaseCatchError<Identity<A>, E>,
+    ActA: Run<BuildId<S>, A>,
+    ActB: Run<BuildId<S>, A>,
+    F: Fn(&E) -> ActB,
+{
+    fn run(&self) -> <BuildId<S> as Ap<A>>::R {
+        self.run_catch::<BuildId<S>, A>()
+    }
+}
+impl<A, E, ActA, ActB, F, S, W> Run<BuildWriter<W, S>, A> for CatchError<E, ActA, ActB, F>
+where
+    S: BaseCatchError<(A, W), E>,
+    W: Default,
+    ActA: Run<BuildWriter<W, S>, A>,
+    ActB: Run<BuildWriter<W, S>, A>,
+    F: Fn(&E) -> ActB,
+{
+    fn run(&self) -> <BuildWriter<W, S> as Ap<A>>::R {
+        self.run_catch::<BuildWriter<W, S>, A>()
+    }
 }
 
-impl<'e, A, S, E> TypeBuilder<A> for BuildError<'e, S, E>
+impl<A, B, ActA, E, F, S> Run<BuildError<E, S>, B> for Map<A, ActA, F>
 where
-    S: TypeBuilder<Result<A, E>>,
-    S::Output: BaseAction<A>,
+    S: Bind<Result<A, E>, Result<B, E>>,
+    ActA: Run<BuildError<E, S>, A>,
+    F: MapFn<A, B>,
 {
-    type Output = S::Output;
-    fn build(value: A) -> Self::Output {
-        S::build(Ok(value))
+    fn run(&self) -> <BuildError<E, S> as Ap<B>>::R {
+        S::bind(self.act_a.run(), |result_a| {
+            <S as Ap<Result<B, E>>>::build(result_a.map(|result_a| self.func.call(&result_a)))
+        })
     }
 }
 
+impl<A, B, ActA, ActB, E, F, S> Run<BuildError<E, S>, B> for AndThen<A, ActA, ActB, F>
+where
+    BuildError<E, S>: Bind<A, B>,
+    ActA: Run<BuildError<E, S>, A>,
+    ActB: Run<BuildError<E, S>, B>,
+    F: Fn(&A) -> ActB,
+{
+    fn run(&self) -> <BuildError<E, S> as Ap<B>>::R {
+        <BuildError<E, S> as Bind<A, B>>::bind(self.act_a.run(), |result_a| {
+            (self.func)(&result_a).run()
+        })
+    }
+}
 
-impl<A, BaseA, E> Mappable<A> for Result<BaseA, E> {}
-impl<A, BaseA, E> Sequential<A> for Result<BaseA, E> where BaseA: BaseAction<A> {}
-impl<A, BaseA, E> Action<A> for Result<BaseA, E> where BaseA: BaseAction<A> {}
+impl<A, B, ActA, ActB, E, S> Run<BuildError<E, S>, A> for First<B, ActA, ActB>
+where
+    BuildError<E, S>: Zip<A, B> + Bind<(A, B), A>,
+    ActA: Run<BuildError<E, S>, A>,
+    ActB: Run<BuildError<E, S>, B>,
+{
+    fn run(&self) -> <BuildError<E, S> as Ap<A>>::R {
+        self.run_bind::<BuildError<E, S>, A>()
+    }
+}
+impl<A, B, ActA, ActB, E, S> Run<BuildError<E, S>, B> for Second<A, ActA, ActB>
+where
+    BuildError<E, S>: Zip<A, B> + Bind<(A, B), B>,
+    ActA: Run<BuildError<E, S>, A>,
+    ActB: Run<BuildError<E, S>, B>,
+{
+    fn run(&self) -> <BuildError<E, S> as Ap<B>>::R {
+        self.run_bind::<BuildError<E, S>, B>()
+    }
+}
+impl<A, B, C, ActA, ActB, F, E, S> Run<BuildError<E, S>, C> for Combine<A, B, ActA, ActB, F>
+where
+    BuildError<E, S>: Zip<A, B> + Bind<(A, B), C>,
+    ActA: Run<BuildError<E, S>, A>,
+    ActB: Run<BuildError<E, S>, B>,
+    F: Fn(&A, &B) -> C,
+{
+    fn run(&self) -> <BuildError<E, S> as Ap<C>>::R {
+        self.run_bind::<BuildError<E, S>, C>()
+    }
+}
 
-impl<A, BaseA, E> BaseAction<A> for Result<BaseA, E>
+impl<A, E, S, W> BaseWriter<A, W> for BuildError<E, S>
 where
-    BaseA: BaseAction<A>,
+    S: BaseWriter<Result<A, E>, W>,
 {
-    fn pure(value: A) -> Self {
-        Ok(BaseA::pure(value))
+    fn writer(value: A, log: W) -> Self::R {
+        S::writer(Ok(value), log)
+    }
+}
+impl<A, B, E, S> BaseListen<A, B> for BuildError<E, S>
+where
+    S: BaseListen<Result<A, E>, Result<B, E>>,
+{
+    type Log = S::Log;
+    fn listen<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A, Self::Log) -> (B, Self::Log),
+    {
+        S::listen(value, |result, log| match result {
+            Ok(result) => {
+                let (result, log) = func(result, log);
+                (Ok(result), log)
+            }
+            Err(err) => (Err(err), log),
+        })
+    }
+}
+
+impl<A, B, ActA, E, F, S> Run<BuildValidation<E, S>, B> for Map<A, ActA, F>
+where
+    S: Bind<Result<A, E>, Result<B, E>>,
+    ActA: Run<BuildValidation<E, S>, A>,
+    F: MapFn<A, B>,
+{
+    fn run(&self) -> <BuildValidation<E, S> as Ap<B>>::R {
+        S::bind(self.act_a.run(), |result_a| {
+            <S as Ap<Result<B, E>>>::build(result_a.map(|result_a| self.func.call(&result_a)))
+        })
     }
 }
 
-impl<'e, A, S, R, E> Run<BuildError<'e, S, E>, A> for R
+impl<A, B, ActA, ActB, E, F, S> Run<BuildValidation<E, S>, B> for AndThen<A, ActA, ActB, F>
 where
-    R: BaseAction<A> + BaseAction<Result<A, E>>,
-    S: TypeBuilder<Result<A, E>, Output = R>,
-    Self: Clone,
+    BuildValidation<E, S>: Bind<A, B>,
+    ActA: Run<BuildValidation<E, S>, A>,
+    ActB: Run<BuildValidation<E, S>, B>,
+    F: Fn(&A) -> ActB,
 {
-    fn run(&self) -> <BuildError<'e, S, E> as TypeBuilder<A>>::Output {
-        self.clone()
+    fn run(&self) -> <BuildValidation<E, S> as Ap<B>>::R {
+        <BuildValidation<E, S> as Bind<A, B>>::bind(self.act_a.run(), |result_a| {
+            (self.func)(&result_a).run()
+        })
     }
 }
 
-impl<'e, A, S, R, E> BaseError<A, BuildError<'e, S, E>, E> for R
+impl<A, B, ActA, ActB, E, S> Run<BuildValidation<E, S>, A> for First<B, ActA, ActB>
+where
+    BuildValidation<E, S>: Zip<A, B> + Bind<(A, B), A>,
+    ActA: Run<BuildValidation<E, S>, A>,
+    ActB: Run<BuildValidation<E, S>, B>,
+{
+    fn run(&self) -> <BuildValidation<E, S> as Ap<A>>::R {
+        self.run_bind::<BuildValidation<E, S>, A>()
+    }
+}
+impl<A, B, ActA, ActB, E, S> Run<BuildValidation<E, S>, B> for Second<A, ActA, ActB>
 where
-    R: BaseAction<A> + BaseAction<Result<A, E>> + Clone,
-    S: TypeBuilder<Result<A, E>, Output = R>,
-    E: 'e + Clone,
+    BuildValidation<E, S>: Zip<A, B> + Bind<(A, B), B>,
+    ActA: Run<BuildValidation<E, S>, A>,
+    ActB: Run<BuildValidation<E, S>, B>,
 {
-    fn throw_error(err: &E) -> Self {
-        S::build(Err(err.clone()))
+    fn run(&self) -> <BuildValidation<E, S> as Ap<B>>::R {
+        self.run_bind::<BuildValidation<E, S>, B>()
     }
-    fn catch_error<ActA, ActB, F>(action: &ActA, func: F) -> Self
+}
+impl<A, B, C, ActA, ActB, F, E, S> Run<BuildValidation<E, S>, C> for Combine<A, B, ActA, ActB, F>
+where
+    BuildValidation<E, S>: Zip<A, B> + Bind<(A, B), C>,
+    ActA: Run<BuildValidation<E, S>, A>,
+    ActB: Run<BuildValidation<E, S>, B>,
+    F: Fn(&A, &B) -> C,
+{
+    fn run(&self) -> <BuildValidation<E, S> as Ap<C>>::R {
+        self.run_bind::<BuildValidation<E, S>, C>()
+    }
+}
+
+impl<A, E, S, W> BaseWriter<A, W> for BuildValidation<E, S>
+where
+    S: BaseWriter<Result<A, E>, W>,
+{
+    fn writer(value: A, log: W) -> Self::R {
+        S::writer(Ok(value), log)
+    }
+}
+impl<A, B, E, S> BaseListen<A, B> for BuildValidation<E, S>
+where
+    S: BaseListen<Result<A, E>, Result<B, E>>,
+{
+    type Log = S::Log;
+    fn listen<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
     where
-        ActA: Run<BuildError<'e, S, E>, A>,
-        ActB: Run<BuildError<'e, S, E>, A>,
-        F: Fn(&E) -> ActB,
+        F: Fn(A, Self::Log) -> (B, Self::Log),
     {
-        unimplemented!()
-    }
-}
-
-// impl<'a, 'e, A, B, ActA, S, E, F> Run<BuildError<'e, S, E>, B> for Map<'a, A, ActA, F>
-// where
-//     E: 'e,
-//     S: TypeBuilder<Result<A, E>> + TypeBuilder<Result<B, E>>,
-//     <S as TypeBuilder<Result<A, E>>>::Output: BaseAction<A>,
-//     <S as TypeBuilder<Result<B, E>>>::Output: BaseAction<B>,
-//     ActA: Run<BuildError<'e, S, E>, A>,
-//     F: Fn(&A) -> B,
-//     Map<'a, A, <S as TypeBuilder<Result<A, E>>>::Output, F>: Run<S, Result<B, E>>,
-// {
-//     fn run(&self) -> <BuildError<'e, S, E> as TypeBuilder<B>>::Output {
-//         unimplemented!()
-//     }
-// }
-// impl<'b, 'e, A, B, ActA, ActB, S, E> Run<BuildError<'e, S, E>, A> for First<'b, B, ActA, ActB>
-// where
-//     E: 'e,
-//     S: TypeBuilder<Result<A, E>> + TypeBuilder<Result<B, E>>,
-//     <S as TypeBuilder<Result<A, E>>>::Output: BaseAction<A>,
-//     <S as TypeBuilder<Result<B, E>>>::Output: BaseAction<B>,
-//     ActA: Run<BuildError<'e, S, E>, A>,
-//     ActB: Run<BuildError<'e, S, E>, B>,
-// {
-//     fn run(&self) -> <BuildError<'e, S, E> as TypeBuilder<A>>::Output {
-//         unimplemented!()
-//         // Disambiguation: the and_then used here is the and_then from std::Result.
-//         // self.act_a.run().and_then(|result| {
-//         //     self.act_b.run()?;
-//         //     Ok(result)
-//         // })
-//     }
-// }
-// impl<'a, 'e, A, B, ActA, ActB, S, E> Run<BuildError<'e, S, E>, B> for Second<'a, A, ActA, ActB>
-// where
-//     E: 'e,
-//     S: TypeBuilder<Result<A, E>> + TypeBuilder<Result<B, E>>,
-//     <S as TypeBuilder<Result<A, E>>>::Output: BaseAction<A>,
-//     <S as TypeBuilder<Result<B, E>>>::Output: BaseAction<B>,
-//     ActA: Run<BuildError<'e, S, E>, A>,
-//     ActB: Run<BuildError<'e, S, E>, B>,
-// {
-//     fn run(&self) -> <BuildError<'e, S, E> as TypeBuilder<B>>::Output {
-//         unimplemented!()
-//         // self.act_a.run();
-//         // self.act_b.run()
-//     }
-// }
-// impl<'e, A, B, ActA, ActB, S, E> Run<BuildError<'e, S, E>, (A, B)> for Sequence<ActA, ActB>
-// where
-//     E: 'e,
-//     S: TypeBuilder<Result<A, E>> + TypeBuilder<Result<B, E>> + TypeBuilder<Result<(A, B), E>>,
-//     <S as TypeBuilder<Result<A, E>>>::Output: BaseAction<A>,
-//     <S as TypeBuilder<Result<B, E>>>::Output: BaseAction<B>,
-//     <S as TypeBuilder<Result<(A, B), E>>>::Output: BaseAction<(A, B)>,
-//     ActA: Run<BuildError<'e, S, E>, A>,
-//     ActB: Run<BuildError<'e, S, E>, B>,
-//     Sequence<<S as TypeBuilder<Result<A, E>>>::Output, <S as TypeBuilder<Result<B, E>>>::Output>:
-//         Run<S, Result<(A, B), E>>,
-// {
-//     fn run(&self) -> <BuildError<'e, S, E> as TypeBuilder<(A, B)>>::Output {
-//         unimplemented!()
-
-//         // match self.0.run() {
-//         //     Err(err_a) => Err(err_a),
-//         //     Ok(action_inner_a) => match self.1.run() {
-//         //         Err(err_b) => Err(err_b),
-//         //         Ok(action_inner_b) =>
-//         //             Ok(action_inner_a.sequence(action_inner_b).run()),
-//         //     }
-//         // }
-//     }
-// }
-// impl<'a, 'b, 'e, A, B, C, ActA, ActB, S, E, F> Run<BuildError<'e, S, E>, C>
-//     for Combine<'a, 'b, A, B, ActA, ActB, F>
-// where
-//     E: 'e,
-//     S: TypeBuilder<Result<A, E>> + TypeBuilder<Result<B, E>> + TypeBuilder<Result<C, E>>,
-//     <S as TypeBuilder<Result<A, E>>>::Output: BaseAction<A>,
-//     <S as TypeBuilder<Result<B, E>>>::Output: BaseAction<B>,
-//     <S as TypeBuilder<Result<C, E>>>::Output: BaseAction<C>,
-//     ActA: Run<BuildError<'e, S, E>, A>,
-//     ActB: Run<BuildError<'e, S, E>, B>,
-//     F: Fn(&A, &B) -> C,
-//     Combine<
-//         'a,
-//         'b,
-//         A,
-//         B,
-//         <S as TypeBuilder<Result<A, E>>>::Output,
-//         <S as TypeBuilder<Result<B, E>>>::Output,
-//         F,
-//     >: Run<S, Result<C, E>>,
-// {
-//     fn run(&self) -> <BuildError<'e, S, E> as TypeBuilder<C>>::Output {
-//         unimplemented!()
-//         // match self.act_a.run() {
-//         //     Err(err_a) => Err(err_a),
-//         //     Ok(action_inner_a) => match self.act_b.run() {
-//         //         Err(err_b) => Err(err_b),
-//         //         Ok(action_inner_b) =>
-//         //             Ok(action_inner_a.combine(action_inner_b, self.func).run()),
-//         //     }
-//         // }
-//     }
-// }
-
-// impl<'a, 'ab, 'e, A, B, ActA, ActB, S, E, F> Run<BuildError<'e, S, E>, B>
-//     for AndThen<'a, 'ab, A, ActA, ActB, F>
-// where
-//     E: Clone,
-//     S: TypeBuilder<Result<A, E>> + TypeBuilder<Result<B, E>>,
-//     <S as TypeBuilder<Result<B, E>>>::Output: 'ab + BaseAction<B>,
-//     ActA: Run<S, Result<A, E>> + Action<A>,
-//     ActB: Run<S, Result<B, E>> + Action<B>,
-//     F: Fn(&A) -> ActB,
-//     AndThen<'a, 'ab, A, <S as TypeBuilder<Result<A, E>>>::Output, <S as TypeBuilder<Result<B, E>>>::Output, F>: Run<BuildError<'e, S, E>, B>,
-// {
-//     fn run(&self) -> <BuildError<'e, S, E> as TypeBuilder<B>>::Output {
-//         self.act_a.run().and_then_ctx(self.func, |func, result| {
-//             match result {
-//                 Err(err) => <S as TypeBuilder<Result<B, E>>>::build(Err(err.clone())),
-//                 Ok(ok) => unimplemented!(),
-//             }
-//         })
-//         .run()
-//     }
-// }
-
-impl<'ab, 'e, A, B, ActA, ActB, S, E, Ctx> Run<BuildError<'e, S, E>, B>
-    for AndThenCtx<'ab, A, ActA, ActB, Ctx>
-where
-    E: 'e + Clone,
-    ActA: Run<S, Result<A, E>> + Action<A>,
-    ActB: Run<S, Result<B, E>> + BaseAction<Result<B, E>> + BaseAction<B>,
-    S: TypeBuilder<Result<A, E>> + TypeBuilder<Result<B, E>, Output = ActB>,
-    <S as TypeBuilder<Result<B, E>>>::Output: 'ab + BaseAction<B>,
-    AndThenCtx<'ab, Result<A, E>, ActA, <S as TypeBuilder<Result<B, E>>>::Output, (Ctx, fn(&Ctx, &A) -> ActB)>: Run<BuildError<'e, S, E>, B>,
-{
-    fn run(&self) -> <BuildError<'e, S, E> as TypeBuilder<B>>::Output {
-        self.act_a.and_then_ctx((self.context, self.func), |(context, func), result_a| {
-            match result_a {
-                Err(err_a) => <S as TypeBuilder<Result<B, E>>>::build(Err(err_a.clone())),
-                Ok(result_b) => func(context, result_b),
+        S::listen(value, |result, log| match result {
+            Ok(result) => {
+                let (result, log) = func(result, log);
+                (Ok(result), log)
             }
+            Err(err) => (Err(err), log),
         })
-        .run()
     }
 }
 
+#[cfg(test)]
+mod test {
+    use super::super::{pure, writer, BuildOption, Monoid, Semigroup, Zip};
+    use super::*;
+
+    #[test]
+    fn and_then_short_circuits() {
+        let scaffold: BuildError<&str> = BuildError(PhantomData, ());
+
+        let action = pure(3)
+            .and_then(|x| pure(x + 2))
+            .and_then(|_| throw_error::<i32, _>("failed"))
+            .and_then(|y| pure(y + 3));
+
+        assert_eq!(action.run_qualified(&scaffold), Err("failed"));
+    }
+
+    #[test]
+    fn map_error() {
+        let scaffold: BuildError<&str> = BuildError(PhantomData, ());
+
+        let action = pure(3).map(|x| x * 2);
+        let action2 = throw_error::<i32, _>("failed").map(|x| x * 2);
+
+        assert_eq!(action.run_qualified(&scaffold), Ok(6));
+        assert_eq!(action2.run_qualified(&scaffold), Err("failed"));
+    }
+
+    #[test]
+    fn catch_error_recovers() {
+        let scaffold: BuildError<&str> = BuildError(PhantomData, ());
+
+        let action = catch_error(throw_error::<i32, _>("failed"), |err: &&str| {
+            pure(err.len() as i32)
+        });
+        let action2 = catch_error(pure(3), |_: &&str| pure(0));
+        let action3 = catch_error(throw_error::<i32, _>("failed"), |_: &&str| {
+            throw_error::<i32, _>("failed again")
+        });
+
+        assert_eq!(action.run_qualified(&scaffold), Ok(6));
+        assert_eq!(action2.run_qualified(&scaffold), Ok(3));
+        assert_eq!(action3.run_qualified(&scaffold), Err("failed again"));
+    }
+
+    #[test]
+    fn catch_error_in_chain() {
+        let scaffold: BuildError<&str> = BuildError(PhantomData, ());
+
+        let action = pure(3)
+            .and_then(|&x| {
+                catch_error(
+                    pure(x).and_then(|_| throw_error::<i32, _>("failed")),
+                    move |_: &&str| pure::<i32>(x),
+                )
+            })
+            .and_then(|y| pure(y + 1));
+
+        assert_eq!(action.run_qualified(&scaffold), Ok(4));
+    }
+
+    #[test]
+    fn catch_error_under_other_scaffolds() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let writer_error = BuildWriter(writer_type, BuildError(err_type, ()));
+        let id_error = BuildId(BuildError(err_type, ()));
+        let option_error = BuildOption(BuildError(err_type, ()));
+
+        let action = catch_error(
+            writer(1, 10).and_then(|_| throw_error::<i32, _>("failed")),
+            |err: &&str| writer(err.len() as i32, 5),
+        );
+        let action2 = catch_error(throw_error::<i32, _>("failed"), |_: &&str| pure(3));
+
+        assert_eq!(action.run_qualified(&writer_error), Ok((6, 5)));
+        assert_eq!(action2.run_qualified(&id_error), Ok(Identity(3)));
+        assert_eq!(action2.run_qualified(&option_error), Ok(Some(3)));
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Errors(Vec<&'static str>);
+    impl Semigroup for Errors {
+        fn op(&self, other: &Self) -> Self {
+            Errors(self.0.iter().chain(other.0.iter()).cloned().collect())
+        }
+    }
+    impl Monoid for Errors {}
+
+    fn check_name(name: &str) -> Result<String, Errors> {
+        if name.is_empty() {
+            Err(Errors(vec!["name is empty"]))
+        } else {
+            Ok(name.to_string())
+        }
+    }
+    fn check_age(age: u32) -> Result<u32, Errors> {
+        if age > 150 {
+            Err(Errors(vec!["age is too large"]))
+        } else {
+            Ok(age)
+        }
+    }
+
+    #[test]
+    fn validation_accumulates() {
+        let both = |name, age| {
+            <BuildValidation<Errors> as Zip<String, u32>>::zip(check_name(name), check_age(age))
+        };
 
+        assert_eq!(both("alice", 31), Ok(("alice".to_string(), 31)));
+        assert_eq!(both("", 31), Err(Errors(vec!["name is empty"])));
+        assert_eq!(
+            both("", 200),
+            Err(Errors(vec!["name is empty", "age is too large"]))
+        );
+    }
+
+    #[test]
+    fn validation_combine_accumulates() {
+        let scaffold: BuildValidation<Errors> = BuildValidation(PhantomData, ());
+
+        let action = throw_error::<u32, _>(Errors(vec!["first"]))
+            .combine(throw_error::<u32, _>(Errors(vec!["second"])), |x, y| x + y);
+        let action2 = pure(3).combine(pure(4), |x, y| x + y);
+
+        assert_eq!(
+            action.run_qualified(&scaffold),
+            Err(Errors(vec!["first", "second"]))
+        );
+        assert_eq!(action2.run_qualified(&scaffold), Ok(7));
+    }
+
+    #[test]
+    fn validation_and_then_short_circuits() {
+        let scaffold: BuildValidation<Errors> = BuildValidation(PhantomData, ());
 
+        let action = throw_error::<u32, _>(Errors(vec!["first"]))
+            .and_then(|_| throw_error::<u32, _>(Errors(vec!["second"])))
+            .map(|x| x + 1);
 
+        assert_eq!(action.run_qualified(&scaffold), Err(Errors(vec!["first"])));
+        assert_eq!(
+            action.run_qualified(&BuildError::from(scaffold)),
+            Err(Errors(vec!["first"]))
+        );
+    }
+
+    #[test]
+    fn error_zip_short_circuits() {
+        let both = |name, age| {
+            <BuildError<Errors> as Zip<String, u32>>::zip(check_name(name), check_age(age))
+        };
+
+        assert_eq!(both("", 200), Err(Errors(vec!["name is empty"])));
+    }
+}
@@ -0,0 +1,507 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::mem;
+use std::pin::{pin, Pin};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use super::{
+    Action, AndThen, Ap, Combine, First, Map, MapFn, Mappable, Pure, Run, RunTraced, Second,
+    Sequential, Step, TailRec, Tracer, Zip,
+};
+
+/// The scaffold for actions which run asynchronously.
+///
+/// The completed type is a boxed `Future`, so nothing happens until it is
+/// polled, either by an async runtime or by `block_on`. The future outlives
+/// the action that built it, so everything it captures must be `'static`, and
+/// `BuildFuture` can not be nested inside another scaffold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuildFuture;
+
+/// A future completed by `BuildFuture`.
+pub type BoxFuture<A> = Pin<Box<dyn Future<Output = A>>>;
+
+impl<A> Ap<A> for BuildFuture
+where
+    A: 'static,
+{
+    type R = BoxFuture<A>;
+    fn build(value: A) -> Self::R {
+        Box::pin(std::future::ready(value))
+    }
+}
+
+/// The second future is only polled once the first has finished.
+impl<A, B> Zip<A, B> for BuildFuture
+where
+    A: 'static,
+    B: 'static,
+{
+    fn zip(value_a: BoxFuture<A>, value_b: BoxFuture<B>) -> BoxFuture<(A, B)> {
+        Box::pin(async move { (value_a.await, value_b.await) })
+    }
+}
+
+impl<A, B, ActA, F> Run<BuildFuture, B> for Map<A, ActA, F>
+where
+    A: 'static,
+    B: 'static,
+    ActA: Run<BuildFuture, A>,
+    F: MapFn<A, B> + Clone + 'static,
+{
+    fn run(&self) -> BoxFuture<B> {
+        let future_a = self.act_a.run();
+        let func = self.func.clone();
+        Box::pin(async move { func.call(&future_a.await) })
+    }
+}
+
+impl<A, B, ActA, ActB, F> Run<BuildFuture, B> for AndThen<A, ActA, ActB, F>
+where
+    A: 'static,
+    B: 'static,
+    ActA: Run<BuildFuture, A>,
+    ActB: Run<BuildFuture, B> + 'static,
+    F: Fn(&A) -> ActB + Clone + 'static,
+{
+    fn run(&self) -> BoxFuture<B> {
+        let future_a = self.act_a.run();
+        let func = self.func.clone();
+        Box::pin(async move {
+            let future_b = func(&future_a.await).run();
+            future_b.await
+        })
+    }
+}
+
+/// The sequential actions zip their futures and then map the pair, so like
+/// `map` and `and_then` they never block, and nothing is awaited until the
+/// future is polled.
+impl<A, B, ActA, ActB> Run<BuildFuture, A> for First<B, ActA, ActB>
+where
+    A: 'static,
+    B: 'static,
+    ActA: Run<BuildFuture, A>,
+    ActB: Run<BuildFuture, B>,
+{
+    fn run(&self) -> BoxFuture<A> {
+        let future = <BuildFuture as Zip<A, B>>::zip(self.act_a.run(), self.act_b.run());
+        Box::pin(async move { future.await.0 })
+    }
+}
+
+impl<A, B, ActA, ActB> Run<BuildFuture, B> for Second<A, ActA, ActB>
+where
+    A: 'static,
+    B: 'static,
+    ActA: Run<BuildFuture, A>,
+    ActB: Run<BuildFuture, B>,
+{
+    fn run(&self) -> BoxFuture<B> {
+        let future = <BuildFuture as Zip<A, B>>::zip(self.act_a.run(), self.act_b.run());
+        Box::pin(async move { future.await.1 })
+    }
+}
+
+impl<A, B, C, ActA, ActB, F> Run<BuildFuture, C> for Combine<A, B, ActA, ActB, F>
+where
+    A: 'static,
+    B: 'static,
+    C: 'static,
+    ActA: Run<BuildFuture, A>,
+    ActB: Run<BuildFuture, B>,
+    F: Fn(&A, &B) -> C + Clone + 'static,
+{
+    fn run(&self) -> BoxFuture<C> {
+        let future = <BuildFuture as Zip<A, B>>::zip(self.act_a.run(), self.act_b.run());
+        let func = self.func.clone();
+        Box::pin(async move {
+            let (result_a, result_b) = future.await;
+            func(&result_a, &result_b)
+        })
+    }
+}
+
+/// Run a future inside a span for the step `Act`, from when it is first
+/// polled until it is ready.
+fn traced<Act, A>(
+    future: BoxFuture<A>,
+    tracer: &Rc<Tracer<'static>>,
+    name: &'static str,
+) -> BoxFuture<A>
+where
+    A: 'static,
+{
+    let tracer = tracer.clone();
+    Box::pin(async move {
+        let span = tracer.start::<Act>(name);
+        let result = future.await;
+        tracer.finish(span);
+        result
+    })
+}
+
+/// `Literal` can't run with `BuildFuture`, since a future can't be cloned.
+impl<A> RunTraced<'static, BuildFuture, A> for Pure<A>
+where
+    A: Clone + 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> BoxFuture<A> {
+        traced::<Self, _>(BuildFuture::build(self.value.clone()), tracer, "Pure")
+    }
+}
+
+impl<A, B, ActA, F> RunTraced<'static, BuildFuture, B> for Map<A, ActA, F>
+where
+    A: 'static,
+    B: 'static,
+    ActA: RunTraced<'static, BuildFuture, A>,
+    F: MapFn<A, B> + Clone + 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> BoxFuture<B> {
+        let future_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        traced::<Self, _>(
+            Box::pin(async move { func.call(&future_a.await) }),
+            tracer,
+            "Map",
+        )
+    }
+}
+
+impl<A, B, ActA, ActB, F> RunTraced<'static, BuildFuture, B> for AndThen<A, ActA, ActB, F>
+where
+    A: 'static,
+    B: 'static,
+    ActA: RunTraced<'static, BuildFuture, A>,
+    ActB: RunTraced<'static, BuildFuture, B> + 'static,
+    F: Fn(&A) -> ActB + Clone + 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> BoxFuture<B> {
+        let future_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        let tracer_b = tracer.clone();
+        let future = Box::pin(async move {
+            let future_b = func(&future_a.await).trace(&tracer_b);
+            future_b.await
+        });
+        traced::<Self, _>(future, tracer, "AndThen")
+    }
+}
+
+/// Each iteration is awaited before the next begins, so the loop only needs
+/// constant stack however many times it repeats.
+impl<A, B> TailRec<A, B> for BuildFuture
+where
+    A: 'static,
+    B: 'static,
+{
+    fn tail_rec<F>(init: A, func: F) -> BoxFuture<B>
+    where
+        F: Fn(A) -> BoxFuture<Step<A, B>> + 'static,
+    {
+        Box::pin(async move {
+            let mut value = init;
+            loop {
+                match func(value).await {
+                    Step::Loop(next) => value = next,
+                    Step::Done(result) => return result,
+                }
+            }
+        })
+    }
+}
+
+/// `FromFuture` is an action which awaits a future from outside of `action6`,
+/// such as async I/O. An action may be run more than once, but a future can
+/// only be awaited once, so `FromFuture` holds a function which makes a new
+/// future each time it is run.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FromFuture<F> {
+    make: F,
+}
+pub fn from_future<Fut, F>(make: F) -> FromFuture<F>
+where
+    Fut: Future,
+    F: Fn() -> Fut,
+{
+    FromFuture { make }
+}
+impl<Fut, F> Mappable<Fut::Output> for FromFuture<F>
+where
+    Fut: Future,
+    F: Fn() -> Fut,
+{
+}
+impl<Fut, F> Sequential<Fut::Output> for FromFuture<F>
+where
+    Fut: Future,
+    F: Fn() -> Fut,
+{
+}
+impl<Fut, F> Action<Fut::Output> for FromFuture<F>
+where
+    Fut: Future,
+    F: Fn() -> Fut,
+{
+}
+impl<Fut, F> Run<BuildFuture, Fut::Output> for FromFuture<F>
+where
+    Fut: Future + 'static,
+    F: Fn() -> Fut,
+{
+    fn run(&self) -> BoxFuture<Fut::Output> {
+        Box::pin((self.make)())
+    }
+}
+
+/// Wakes a thread which is parked waiting for a future.
+struct ThreadWaker(Thread);
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run a future to completion on the current thread, parking the thread
+/// whenever the future is waiting.
+pub fn block_on<Fut>(future: Fut) -> Fut::Output
+where
+    Fut: Future,
+{
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// The tasks which have been woken since they were last polled.
+struct Woken {
+    tasks: Mutex<Vec<usize>>,
+    thread: Thread,
+}
+
+struct TaskWaker {
+    task: usize,
+    woken: Arc<Woken>,
+}
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.woken.tasks.lock().unwrap().push(self.task);
+        self.woken.thread.unpark();
+    }
+}
+
+/// A single threaded executor, which interleaves many futures on the thread
+/// that created it. Unlike most executors, the futures do not have to be
+/// `Send`, so they may hold the futures completed by `BuildFuture`.
+pub struct LocalExecutor {
+    tasks: Vec<Option<BoxFuture<()>>>,
+    woken: Arc<Woken>,
+}
+impl LocalExecutor {
+    pub fn new() -> Self {
+        LocalExecutor {
+            tasks: Vec::new(),
+            woken: Arc::new(Woken {
+                tasks: Mutex::new(Vec::new()),
+                thread: thread::current(),
+            }),
+        }
+    }
+
+    /// Add a future to the executor. It won't be polled until `run` is called.
+    pub fn spawn<Fut>(&mut self, future: Fut) -> Task<Fut::Output>
+    where
+        Fut: Future + 'static,
+    {
+        let output = Rc::new(RefCell::new(None));
+        let slot = output.clone();
+        let task = self.tasks.len();
+        self.tasks.push(Some(Box::pin(async move {
+            *slot.borrow_mut() = Some(future.await);
+        })));
+        self.woken.tasks.lock().unwrap().push(task);
+        Task { output }
+    }
+
+    /// Poll the spawned futures until they have all finished. Each future is
+    /// only polled again once it has been woken.
+    pub fn run(&mut self) {
+        loop {
+            let woken = mem::take(&mut *self.woken.tasks.lock().unwrap());
+            if woken.is_empty() {
+                if self.tasks.iter().all(Option::is_none) {
+                    return;
+                }
+                thread::park();
+                continue;
+            }
+            for task in woken {
+                if let Some(future) = &mut self.tasks[task] {
+                    let waker = Waker::from(Arc::new(TaskWaker {
+                        task,
+                        woken: self.woken.clone(),
+                    }));
+                    let mut context = Context::from_waker(&waker);
+                    if future.as_mut().poll(&mut context).is_ready() {
+                        self.tasks[task] = None;
+                    }
+                }
+            }
+        }
+    }
+}
+impl Default for LocalExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The output of a future spawned on a `LocalExecutor`.
+pub struct Task<A> {
+    output: Rc<RefCell<Option<A>>>,
+}
+impl<A> Task<A> {
+    /// Take the output, if the future has finished.
+    pub fn take(&self) -> Option<A> {
+        self.output.borrow_mut().take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{pure, tail_rec_m};
+    use super::*;
+
+    /// A future which is pending the first time it is polled, so the
+    /// executors have to wait to be woken.
+    struct YieldNow(bool);
+    impl Future for YieldNow {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                context.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn existing_chains_run_as_futures() {
+        let action = pure(3).and_then(|x| pure(x + 2)).map(|y| y * 2);
+        let action2 = pure(3).sequence(pure("three"));
+
+        assert_eq!(block_on(action.run_qualified(&BuildFuture)), 10);
+        assert_eq!(block_on(action2.run_qualified(&BuildFuture)), (3, "three"));
+    }
+
+    #[test]
+    fn lifted_futures() {
+        let action = from_future(|| async {
+            YieldNow(false).await;
+            4
+        })
+        .and_then(|&x| from_future(move || async move { x * 10 }))
+        .map(|x| x + 1);
+
+        assert_eq!(block_on(action.run_qualified(&BuildFuture)), 41);
+        assert_eq!(block_on(action.run_qualified(&BuildFuture)), 41);
+    }
+
+    #[test]
+    fn executor_interleaves_tasks() {
+        let order = Rc::new(RefCell::new(Vec::new()));
+        let step = |name: &'static str| {
+            let order = order.clone();
+            from_future(move || {
+                let order = order.clone();
+                async move {
+                    order.borrow_mut().push(name);
+                    YieldNow(false).await;
+                    order.borrow_mut().push(name);
+                    name.len()
+                }
+            })
+        };
+
+        let mut executor = LocalExecutor::new();
+        let task_a = executor.spawn(step("a").run_qualified(&BuildFuture));
+        let task_b = executor.spawn(step("bb").run_qualified(&BuildFuture));
+        assert_eq!(task_a.take(), None);
+        executor.run();
+
+        assert_eq!(*order.borrow(), vec!["a", "bb", "a", "bb"]);
+        assert_eq!((task_a.take(), task_b.take()), (Some(1), Some(2)));
+    }
+
+    #[test]
+    fn sequential_actions_wait_to_be_polled() {
+        let started = Rc::new(RefCell::new(0));
+        let count = |x: u32| {
+            let started = started.clone();
+            from_future(move || {
+                *started.borrow_mut() += 1;
+                async move {
+                    YieldNow(false).await;
+                    x
+                }
+            })
+        };
+
+        let action = count(3).combine(count(4), |x, y| x * y);
+        let future = action.run_qualified(&BuildFuture);
+        let mut executor = LocalExecutor::new();
+        let task = executor.spawn(future);
+        assert_eq!(task.take(), None);
+        executor.run();
+
+        assert_eq!(task.take(), Some(12));
+        assert_eq!(*started.borrow(), 2);
+    }
+
+    #[test]
+    fn sequential_actions() {
+        let yield_then = |x: u32| {
+            from_future(move || async move {
+                YieldNow(false).await;
+                x
+            })
+        };
+
+        let action = yield_then(1).first(yield_then(2));
+        let action2 = yield_then(1).second(yield_then(2));
+        let action3 = yield_then(3).combine(yield_then(4), |x, y| x * y);
+
+        assert_eq!(block_on(action.run_qualified(&BuildFuture)), 1);
+        assert_eq!(block_on(action2.run_qualified(&BuildFuture)), 2);
+        assert_eq!(block_on(action3.run_qualified(&BuildFuture)), 12);
+    }
+
+    #[test]
+    fn tail_rec_m_future() {
+        let action = tail_rec_m((0u64, 0u64), |&(n, total)| {
+            from_future(move || async move {
+                YieldNow(false).await;
+                if n == 100_000 {
+                    Step::Done(total)
+                } else {
+                    Step::Loop((n + 1, total + n))
+                }
+            })
+        });
+
+        assert_eq!(block_on(action.run_qualified(&BuildFuture)), 4_999_950_000);
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use super::{literal, pure, AndThen, Ap, Literal, Map, Pure, Run};
+
+/// A small random number generator (SplitMix64), so that the laws can be
+/// checked with arbitrary values without any dependencies. It is only meant
+/// for tests.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rng(u64);
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// A number less than `bound`, which must not be zero.
+    pub fn below(&mut self, bound: u32) -> u32 {
+        self.next_u32() % bound
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+}
+
+/// How many arbitrary cases each law is checked with.
+pub const CASES: usize = 100;
+const SEED: u64 = 0x1a3_5eed;
+
+/// The functions given to `map` and `and_then` by the checks. They are boxed
+/// so that the types of the actions can be named in the bounds.
+pub type Func = Box<dyn Fn(&u32) -> u32>;
+pub type Kleisli<ActB> = Box<dyn Fn(&u32) -> ActB>;
+pub type Mapped<ActA> = Map<u32, ActA, Func>;
+pub type Then<ActA, ActB> = AndThen<u32, ActA, ActB, Kleisli<ActB>>;
+pub type Arbitrary<S> = Literal<S, u32>;
+
+fn run<S, ActA>(act_a: &ActA) -> S::R
+where
+    S: Ap<u32>,
+    ActA: Run<S, u32>,
+{
+    act_a.run()
+}
+
+fn arbitrary_func(rng: &mut Rng) -> Func {
+    let k = rng.next_u32();
+    match rng.below(3) {
+        0 => Box::new(move |x| x.wrapping_add(k)),
+        1 => Box::new(move |x| x.wrapping_mul(k | 1)),
+        _ => Box::new(move |x| x ^ k),
+    }
+}
+
+/// The completed values a continuation picks from, depending on its argument.
+struct Choices<S: Ap<u32>> {
+    values: Rc<Vec<S::R>>,
+    salt: u32,
+}
+impl<S> Clone for Choices<S>
+where
+    S: Ap<u32>,
+{
+    fn clone(&self) -> Self {
+        Choices {
+            values: self.values.clone(),
+            salt: self.salt,
+        }
+    }
+}
+impl<S> Choices<S>
+where
+    S: Ap<u32> + 'static,
+    S::R: Clone + 'static,
+{
+    fn new<G>(rng: &mut Rng, arbitrary: &G) -> Self
+    where
+        G: Fn(&mut Rng) -> S::R,
+    {
+        Choices {
+            values: Rc::new((0..4).map(|_| arbitrary(rng)).collect()),
+            salt: rng.next_u32(),
+        }
+    }
+
+    fn kleisli(&self) -> Kleisli<Arbitrary<S>> {
+        let values = self.values.clone();
+        let salt = self.salt;
+        Box::new(move |x| literal(values[(x ^ salt) as usize % values.len()].clone()))
+    }
+}
+
+/// Check the monad laws for the scaffold `S`, where `arbitrary` makes the
+/// completed values of arbitrary actions, such as `(value, log)` for a
+/// writer. Panics naming the first law which doesn't hold.
+///
+/// * left identity: `pure(a).and_then(f)` is `f(&a)`
+/// * right identity: `m.and_then(pure)` is `m`
+/// * associativity: `m.and_then(f).and_then(g)` is
+///   `m.and_then(|x| f(x).and_then(g))`
+pub fn check_monad_laws<S, G>(arbitrary: G)
+where
+    S: Ap<u32> + 'static,
+    S::R: Clone + PartialEq + Debug + 'static,
+    G: Fn(&mut Rng) -> S::R,
+    Then<Pure<u32>, Arbitrary<S>>: Run<S, u32>,
+    Then<Arbitrary<S>, Pure<u32>>: Run<S, u32>,
+    Then<Then<Arbitrary<S>, Arbitrary<S>>, Arbitrary<S>>: Run<S, u32>,
+    Then<Arbitrary<S>, Then<Arbitrary<S>, Arbitrary<S>>>: Run<S, u32>,
+{
+    let mut rng = Rng::new(SEED);
+    for case in 0..CASES {
+        let a = rng.next_u32();
+        let m = arbitrary(&mut rng);
+        let f = Choices::<S>::new(&mut rng, &arbitrary);
+        let g = Choices::<S>::new(&mut rng, &arbitrary);
+
+        let bound: Then<Pure<u32>, Arbitrary<S>> = AndThen {
+            act_a: pure(a),
+            func: f.kleisli(),
+            _act_a_type: PhantomData,
+            _act_b: PhantomData,
+        };
+        assert_eq!(
+            run::<S, _>(&bound),
+            run::<S, _>(&f.kleisli()(&a)),
+            "left identity failed in case {} with a = {}",
+            case,
+            a
+        );
+
+        let returned: Then<Arbitrary<S>, Pure<u32>> = AndThen {
+            act_a: literal(m.clone()),
+            func: Box::new(|&x| pure(x)),
+            _act_a_type: PhantomData,
+            _act_b: PhantomData,
+        };
+        assert_eq!(
+            run::<S, _>(&returned),
+            m,
+            "right identity failed in case {} with m = {:?}",
+            case,
+            m
+        );
+
+        let left: Then<Then<Arbitrary<S>, Arbitrary<S>>, Arbitrary<S>> = AndThen {
+            act_a: AndThen {
+                act_a: literal(m.clone()),
+                func: f.kleisli(),
+                _act_a_type: PhantomData,
+                _act_b: PhantomData,
+            },
+            func: g.kleisli(),
+            _act_a_type: PhantomData,
+            _act_b: PhantomData,
+        };
+        let (f_inner, g_inner) = (f.clone(), g.clone());
+        let right: Then<Arbitrary<S>, Then<Arbitrary<S>, Arbitrary<S>>> = AndThen {
+            act_a: literal(m.clone()),
+            func: Box::new(move |x| AndThen {
+                act_a: f_inner.kleisli()(x),
+                func: g_inner.kleisli(),
+                _act_a_type: PhantomData,
+                _act_b: PhantomData,
+            }),
+            _act_a_type: PhantomData,
+            _act_b: PhantomData,
+        };
+        assert_eq!(
+            run::<S, _>(&left),
+            run::<S, _>(&right),
+            "associativity failed in case {} with m = {:?}",
+            case,
+            m
+        );
+    }
+}
+
+/// Check the functor laws for the scaffold `S`, where `arbitrary` makes the
+/// completed values of arbitrary actions. Panics naming the first law which
+/// doesn't hold.
+///
+/// * identity: `m.map(|x| *x)` is `m`
+/// * composition: `m.map(f).map(g)` is `m.map(|x| g(&f(x)))`
+/// * naturality of `pure`: `pure(a).map(f)` is `pure(f(&a))`
+pub fn check_functor_laws<S, G>(arbitrary: G)
+where
+    S: Ap<u32>,
+    S::R: Clone + PartialEq + Debug,
+    G: Fn(&mut Rng) -> S::R,
+    Mapped<Pure<u32>>: Run<S, u32>,
+    Mapped<Arbitrary<S>>: Run<S, u32>,
+    Mapped<Mapped<Arbitrary<S>>>: Run<S, u32>,
+{
+    let mut rng = Rng::new(SEED);
+    for case in 0..CASES {
+        let a = rng.next_u32();
+        let m = arbitrary(&mut rng);
+        let f: Rc<Func> = Rc::new(arbitrary_func(&mut rng));
+        let g: Rc<Func> = Rc::new(arbitrary_func(&mut rng));
+
+        let mapped: Mapped<Arbitrary<S>> = Map {
+            act_a: literal(m.clone()),
+            func: Box::new(|&x| x),
+            _act_a_type: PhantomData,
+        };
+        assert_eq!(
+            run::<S, _>(&mapped),
+            m,
+            "identity failed in case {} with m = {:?}",
+            case,
+            m
+        );
+
+        let (f_first, g_second) = (f.clone(), g.clone());
+        let twice: Mapped<Mapped<Arbitrary<S>>> = Map {
+            act_a: Map {
+                act_a: literal(m.clone()),
+                func: Box::new(move |x| f_first(x)),
+                _act_a_type: PhantomData,
+            },
+            func: Box::new(move |x| g_second(x)),
+            _act_a_type: PhantomData,
+        };
+        let (f_composed, g_composed) = (f.clone(), g.clone());
+        let composed: Mapped<Arbitrary<S>> = Map {
+            act_a: literal(m.clone()),
+            func: Box::new(move |x| g_composed(&f_composed(x))),
+            _act_a_type: PhantomData,
+        };
+        assert_eq!(
+            run::<S, _>(&twice),
+            run::<S, _>(&composed),
+            "composition failed in case {} with m = {:?}",
+            case,
+            m
+        );
+
+        let f_pure = f.clone();
+        let pure_mapped: Mapped<Pure<u32>> = Map {
+            act_a: pure(a),
+            func: Box::new(move |x| f_pure(x)),
+            _act_a_type: PhantomData,
+        };
+        assert_eq!(
+            run::<S, _>(&pure_mapped),
+            run::<S, _>(&pure(f(&a))),
+            "naturality of pure failed in case {} with a = {}",
+            case,
+            a
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        BuildError, BuildId, BuildList, BuildOption, BuildValidation, BuildWriter, Identity,
+        Scaffold, Step,
+    };
+    use super::*;
+
+    fn either<T>(rng: &mut Rng, ok: T, err: T) -> T {
+        if rng.below(4) == 0 {
+            err
+        } else {
+            ok
+        }
+    }
+
+    #[test]
+    fn unit_is_lawful() {
+        check_monad_laws::<(), _>(|rng| rng.next_u32());
+        check_functor_laws::<(), _>(|rng| rng.next_u32());
+    }
+
+    #[test]
+    fn identity_is_lawful() {
+        check_monad_laws::<BuildId, _>(|rng| Identity(rng.next_u32()));
+        check_functor_laws::<BuildId, _>(|rng| Identity(rng.next_u32()));
+    }
+
+    #[test]
+    fn writer_is_lawful() {
+        let arbitrary = |rng: &mut Rng| (rng.next_u32(), rng.below(1000));
+        check_monad_laws::<BuildWriter<u32>, _>(arbitrary);
+        check_functor_laws::<BuildWriter<u32>, _>(arbitrary);
+
+        let arbitrary = |rng: &mut Rng| (rng.next_u32(), vec![rng.below(10)]);
+        check_monad_laws::<BuildWriter<Vec<u32>>, _>(arbitrary);
+    }
+
+    #[test]
+    fn error_is_lawful() {
+        let arbitrary = |rng: &mut Rng| {
+            let (value, error) = (rng.next_u32(), rng.below(3));
+            either(rng, Ok(value), Err(error))
+        };
+        check_monad_laws::<BuildError<u32>, _>(arbitrary);
+        check_functor_laws::<BuildError<u32>, _>(arbitrary);
+        check_monad_laws::<BuildValidation<u32>, _>(arbitrary);
+    }
+
+    #[test]
+    fn nested_scaffolds_are_lawful() {
+        let arbitrary = |rng: &mut Rng| {
+            let (value, error, log) = (rng.next_u32(), rng.below(3), rng.below(1000));
+            either(rng, Ok((value, log)), Err(error))
+        };
+        check_monad_laws::<BuildWriter<u32, BuildError<u32>>, _>(arbitrary);
+
+        let arbitrary = |rng: &mut Rng| {
+            let (value, error, log) = (rng.next_u32(), rng.below(3), rng.below(1000));
+            (either(rng, Ok(value), Err(error)), log)
+        };
+        check_monad_laws::<BuildError<u32, BuildWriter<u32>>, _>(arbitrary);
+    }
+
+    #[test]
+    fn option_and_list_are_lawful() {
+        let arbitrary = |rng: &mut Rng| {
+            let value = rng.next_u32();
+            either(rng, Some(value), None)
+        };
+        check_monad_laws::<BuildOption, _>(arbitrary);
+        check_functor_laws::<BuildOption, _>(arbitrary);
+
+        let arbitrary = |rng: &mut Rng| {
+            let len = rng.below(3);
+            (0..len).map(|_| rng.below(100)).collect::<Vec<_>>()
+        };
+        check_monad_laws::<BuildList, _>(arbitrary);
+        check_functor_laws::<BuildList, _>(arbitrary);
+    }
+
+    fn unlogged<A>(value: A) -> (A, u32) {
+        (value, 0)
+    }
+
+    /// Forgets the log of the first action, which breaks right identity.
+    fn bind_forgetful<A, B, F>((value, _): (A, u32), func: F) -> (B, u32)
+    where
+        F: Fn(A) -> (B, u32),
+    {
+        func(value)
+    }
+
+    fn tail_rec_forgetful<A, B, F>(init: A, func: F) -> (B, u32)
+    where
+        F: Fn(A) -> (Step<A, B>, u32),
+    {
+        let mut state = init;
+        loop {
+            match func(state) {
+                (Step::Loop(next), _) => state = next,
+                (Step::Done(result), log) => return (result, log),
+            }
+        }
+    }
+
+    #[derive(Scaffold)]
+    #[scaffold(
+        complete = "(A, u32)",
+        pure = "unlogged",
+        bind = "bind_forgetful",
+        tail_rec = "tail_rec_forgetful"
+    )]
+    struct BuildForgetful;
+
+    #[test]
+    #[should_panic(expected = "right identity failed")]
+    fn forgetful_writer_is_caught() {
+        check_monad_laws::<BuildForgetful, _>(|rng| (rng.next_u32(), rng.below(1000) + 1));
+    }
+
+    #[test]
+    fn rng_is_deterministic() {
+        let mut rng = Rng::new(7);
+        let first: Vec<u32> = (0..4).map(|_| rng.below(100)).collect();
+        let mut rng = Rng::new(7);
+        let second: Vec<u32> = (0..4).map(|_| rng.below(100)).collect();
+
+        assert_eq!(first, second);
+        assert!(first.iter().all(|&x| x < 100));
+    }
+}
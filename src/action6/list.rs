@@ -0,0 +1,262 @@
+use super::{
+    Action, AndThen, Ap, BaseOption, Bind, BuildError, BuildId, BuildOption, BuildValidation,
+    BuildWriter, Combine, Eager, First, Identity, Map, MapFn, Mappable, Run, Second, Sequential,
+    Zip,
+};
+
+/// The scaffold for nondeterministic actions. `BuildList` produces a `Vec<A>`
+/// of every possible result, and `and_then` runs the next action once for each
+/// of them.
+///
+/// Other scaffolds may be nested inside `BuildList`, but `BuildList` itself
+/// always produces a plain `Vec`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuildList();
+
+impl Eager for BuildList {}
+impl<A> Ap<A> for BuildList {
+    type R = Vec<A>;
+    fn build(value: A) -> Self::R {
+        vec![value]
+    }
+}
+impl<A, B> Bind<A, B> for BuildList {
+    fn bind<F>(value: Vec<A>, func: F) -> Vec<B>
+    where
+        F: Fn(A) -> Vec<B>,
+    {
+        value.into_iter().flat_map(func).collect()
+    }
+}
+impl<A, B> Zip<A, B> for BuildList
+where
+    A: Clone,
+    B: Clone,
+{
+    fn zip(value_a: Vec<A>, value_b: Vec<B>) -> Vec<(A, B)> {
+        value_a
+            .iter()
+            .flat_map(|result_a| {
+                value_b
+                    .iter()
+                    .map(move |result_b| (result_a.clone(), result_b.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Scaffolds which can complete any number of alternative values. An outer
+/// scaffold which is not a list completes each alternative, then passes them
+/// through to the scaffold it wraps.
+pub trait BaseList<A>: Ap<A> {
+    fn choose(values: Vec<A>) -> Self::R;
+}
+impl<A> BaseList<A> for BuildList {
+    fn choose(values: Vec<A>) -> Self::R {
+        values
+    }
+}
+impl<A, S> BaseList<A> for BuildId<S>
+where
+    S: BaseList<Identity<A>>,
+{
+    fn choose(values: Vec<A>) -> Self::R {
+        S::choose(values.into_iter().map(Identity).collect())
+    }
+}
+impl<A, S, W> BaseList<A> for BuildWriter<W, S>
+where
+    S: BaseList<(A, W)>,
+    W: Default,
+{
+    fn choose(values: Vec<A>) -> Self::R {
+        S::choose(
+            values
+                .into_iter()
+                .map(|value| (value, W::default()))
+                .collect(),
+        )
+    }
+}
+impl<A, E, S> BaseList<A> for BuildError<E, S>
+where
+    S: BaseList<Result<A, E>>,
+{
+    fn choose(values: Vec<A>) -> Self::R {
+        S::choose(values.into_iter().map(Ok).collect())
+    }
+}
+impl<A, E, S> BaseList<A> for BuildValidation<E, S>
+where
+    S: BaseList<Result<A, E>>,
+{
+    fn choose(values: Vec<A>) -> Self::R {
+        S::choose(values.into_iter().map(Ok).collect())
+    }
+}
+impl<A, S> BaseList<A> for BuildOption<S>
+where
+    S: BaseList<Option<A>>,
+{
+    fn choose(values: Vec<A>) -> Self::R {
+        S::choose(values.into_iter().map(Some).collect())
+    }
+}
+impl<A> BaseOption<A> for BuildList {
+    fn none() -> Self::R {
+        Vec::new()
+    }
+}
+
+/// `Choose` is an action which produces every value from a collection.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Choose<A> {
+    values: Vec<A>,
+}
+pub fn choose<A, I>(values: I) -> Choose<A>
+where
+    I: IntoIterator<Item = A>,
+{
+    Choose {
+        values: values.into_iter().collect(),
+    }
+}
+impl<A> Mappable<A> for Choose<A> {}
+impl<A> Sequential<A> for Choose<A> {}
+impl<A> Action<A> for Choose<A> {}
+impl<A, S> Run<S, A> for Choose<A>
+where
+    S: BaseList<A>,
+    A: Clone,
+{
+    fn run(&self) -> S::R {
+        S::choose(self.values.clone())
+    }
+}
+
+/// `Guard` is an action which continues when the condition holds, and
+/// produces no results otherwise.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Guard {
+    condition: bool,
+}
+pub fn guard(condition: bool) -> Guard {
+    Guard { condition }
+}
+impl Mappable<()> for Guard {}
+impl Sequential<()> for Guard {}
+impl Action<()> for Guard {}
+impl<S> Run<S, ()> for Guard
+where
+    S: BaseList<()>,
+{
+    fn run(&self) -> S::R {
+        if self.condition {
+            S::build(())
+        } else {
+            S::choose(Vec::new())
+        }
+    }
+}
+
+impl<A, B, ActA, F> Run<BuildList, B> for Map<A, ActA, F>
+where
+    ActA: Run<BuildList, A>,
+    F: MapFn<A, B>,
+{
+    fn run(&self) -> Vec<B> {
+        self.act_a
+            .run()
+            .iter()
+            .map(|result_a| self.func.call(result_a))
+            .collect()
+    }
+}
+
+impl<A, B, ActA, ActB, F> Run<BuildList, B> for AndThen<A, ActA, ActB, F>
+where
+    ActA: Run<BuildList, A>,
+    ActB: Run<BuildList, B>,
+    F: Fn(&A) -> ActB,
+{
+    fn run(&self) -> Vec<B> {
+        BuildList::bind(self.act_a.run(), |result_a| (self.func)(&result_a).run())
+    }
+}
+
+impl<A, B, ActA, ActB> Run<BuildList, A> for First<B, ActA, ActB>
+where
+    BuildList: Zip<A, B> + Bind<(A, B), A>,
+    ActA: Run<BuildList, A>,
+    ActB: Run<BuildList, B>,
+{
+    fn run(&self) -> <BuildList as Ap<A>>::R {
+        self.run_bind::<BuildList, A>()
+    }
+}
+impl<A, B, ActA, ActB> Run<BuildList, B> for Second<A, ActA, ActB>
+where
+    BuildList: Zip<A, B> + Bind<(A, B), B>,
+    ActA: Run<BuildList, A>,
+    ActB: Run<BuildList, B>,
+{
+    fn run(&self) -> <BuildList as Ap<B>>::R {
+        self.run_bind::<BuildList, B>()
+    }
+}
+impl<A, B, C, ActA, ActB, F> Run<BuildList, C> for Combine<A, B, ActA, ActB, F>
+where
+    BuildList: Zip<A, B> + Bind<(A, B), C>,
+    ActA: Run<BuildList, A>,
+    ActB: Run<BuildList, B>,
+    F: Fn(&A, &B) -> C,
+{
+    fn run(&self) -> <BuildList as Ap<C>>::R {
+        self.run_bind::<BuildList, C>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{pure, writer};
+    use super::*;
+    use std::marker::PhantomData;
+
+    #[test]
+    fn all_solutions() {
+        let scaffold = BuildList();
+
+        let action = choose(1..20).and_then(|&a| {
+            choose(a..20).and_then(move |&b| {
+                choose(b..20)
+                    .and_then(move |&c| guard(a * a + b * b == c * c).map(move |_| (a, b, c)))
+            })
+        });
+
+        assert_eq!(
+            action.run_qualified(&scaffold),
+            vec![(3, 4, 5), (5, 12, 13), (6, 8, 10), (8, 15, 17), (9, 12, 15)]
+        );
+    }
+
+    #[test]
+    fn pure_is_a_single_solution() {
+        let scaffold = BuildList();
+
+        let action = pure(3).and_then(|x| pure(x + 2));
+        let action2 = choose(vec![1, 2]).and_then(|_| guard(false));
+
+        assert_eq!(action.run_qualified(&scaffold), vec![5]);
+        assert_eq!(action2.run_qualified(&scaffold), vec![]);
+    }
+
+    #[test]
+    fn writer_over_list() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, BuildList());
+
+        let action = choose(vec![1, 2]).and_then(|&x| writer(x * 10, x));
+
+        assert_eq!(action.run_qualified(&scaffold), vec![(10, 1), (20, 2)]);
+    }
+}
@@ -0,0 +1,595 @@
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use super::{Action, Ap, Map, MapFn, Mappable, Run, Sequential, Step, TailRec};
+
+/// The results collected so far by a loop, most recent first.
+///
+/// A scaffold may run the rest of a loop more than once (for example
+/// `BuildList`), so a loop can't push onto a `Vec` it owns. Instead each
+/// iteration shares the results before it, which only costs a reference count.
+pub struct Collected<A>(Option<Rc<Node<A>>>);
+struct Node<A> {
+    value: A,
+    rest: Collected<A>,
+}
+impl<A> Collected<A> {
+    fn new() -> Self {
+        Collected(None)
+    }
+    fn push(&self, value: A) -> Self {
+        Collected(Some(Rc::new(Node {
+            value,
+            rest: self.clone(),
+        })))
+    }
+    fn to_vec(&self) -> Vec<A>
+    where
+        A: Clone,
+    {
+        let mut values = Vec::new();
+        let mut next = &self.0;
+        while let Some(node) = next {
+            values.push(node.value.clone());
+            next = &node.rest.0;
+        }
+        values.reverse();
+        values
+    }
+}
+impl<A> Clone for Collected<A> {
+    fn clone(&self) -> Self {
+        Collected(self.0.clone())
+    }
+}
+/// Dropping the nodes one at a time, rather than recursively, so that long
+/// loops don't overflow the stack.
+impl<A> Drop for Collected<A> {
+    fn drop(&mut self) {
+        let mut next = self.0.take();
+        while let Some(node) = next {
+            next = match Rc::try_unwrap(node) {
+                Ok(mut node) => node.rest.0.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+/// An action stored in a loop. Each iteration runs it through `Map`, which
+/// needs the action by value, so it is shared rather than cloned.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Shared<Act>(pub(super) Rc<Act>);
+impl<Act> Clone for Shared<Act> {
+    fn clone(&self) -> Self {
+        Shared(self.0.clone())
+    }
+}
+impl<A, Act> Mappable<A> for Shared<Act> where Act: Mappable<A> {}
+impl<A, Act> Sequential<A> for Shared<Act> where Act: Sequential<A> {}
+impl<A, Act> Action<A> for Shared<Act> where Act: Action<A> {}
+impl<S, A, Act> Run<S, A> for Shared<Act>
+where
+    S: Ap<A>,
+    Act: Run<S, A>,
+{
+    fn run(&self) -> <S as Ap<A>>::R {
+        self.0.run()
+    }
+}
+
+/// Map the result of an iteration's action to the next `Step`. The functions
+/// below are named types rather than closures, so the loops can require that
+/// `Map` runs with them, which every scaffold supports, lazy or not.
+pub(super) fn then_step<A, ActA, F>(act_a: ActA, func: F) -> Map<A, ActA, F> {
+    Map {
+        act_a,
+        func,
+        _act_a_type: PhantomData,
+    }
+}
+
+/// Adds the result of the `index`th action to the results collected so far.
+#[derive(Clone)]
+pub struct Push<B> {
+    index: usize,
+    collected: Collected<B>,
+}
+type CollectStep<B> = Step<(usize, Collected<B>), Vec<B>>;
+impl<B> MapFn<B, CollectStep<B>> for Push<B>
+where
+    B: Clone,
+{
+    fn call(&self, result: &B) -> CollectStep<B> {
+        Step::Loop((self.index + 1, self.collected.push(result.clone())))
+    }
+}
+
+/// Moves on from the `index`th item, discarding its result.
+#[derive(Clone)]
+pub struct Next {
+    index: usize,
+}
+impl<B> MapFn<B, Step<usize, ()>> for Next {
+    fn call(&self, _: &B) -> Step<usize, ()> {
+        Step::Loop(self.index + 1)
+    }
+}
+
+/// Moves on from the `index`th item with the new accumulator.
+#[derive(Clone)]
+pub struct NextAcc {
+    index: usize,
+}
+impl<Acc> MapFn<Acc, Step<(usize, Acc), Acc>> for NextAcc
+where
+    Acc: Clone,
+{
+    fn call(&self, acc: &Acc) -> Step<(usize, Acc), Acc> {
+        Step::Loop((self.index + 1, acc.clone()))
+    }
+}
+
+/// The state of `while_m`: the results so far, and whether the condition has
+/// just held, so the body runs next.
+type WhileState<A> = (Collected<A>, bool);
+type WhileStep<A> = Step<WhileState<A>, Vec<A>>;
+
+/// Runs the body next if the condition held, or else finishes the loop.
+#[derive(Clone)]
+pub struct Check<A> {
+    collected: Collected<A>,
+}
+impl<A> MapFn<bool, WhileStep<A>> for Check<A>
+where
+    A: Clone,
+{
+    fn call(&self, condition: &bool) -> WhileStep<A> {
+        if *condition {
+            Step::Loop((self.collected.clone(), true))
+        } else {
+            Step::Done(self.collected.to_vec())
+        }
+    }
+}
+
+/// Adds the result of the body, and checks the condition next.
+#[derive(Clone)]
+pub struct PushBody<A> {
+    collected: Collected<A>,
+}
+impl<A> MapFn<A, WhileStep<A>> for PushBody<A>
+where
+    A: Clone,
+{
+    fn call(&self, result: &A) -> WhileStep<A> {
+        Step::Loop((self.collected.push(result.clone()), false))
+    }
+}
+
+/// Run the `index`th action for every index up to `len`, collecting the
+/// results.
+fn collect<S, B, ActB, F>(len: usize, nth: F) -> <S as Ap<Vec<B>>>::R
+where
+    S: TailRec<(usize, Collected<B>), Vec<B>>,
+    B: Clone,
+    ActB: Action<B>,
+    Map<B, ActB, Push<B>>: Run<S, CollectStep<B>>,
+    F: Fn(usize) -> ActB + 'static,
+{
+    S::tail_rec((0, Collected::new()), move |(index, collected)| {
+        if index == len {
+            <S as Ap<CollectStep<B>>>::build(Step::Done(collected.to_vec()))
+        } else {
+            then_step(nth(index), Push { index, collected }).run()
+        }
+    })
+}
+
+/// Run an action for every item, and collect the results.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Traverse<T, ActB, F> {
+    items: Rc<Vec<T>>,
+    func: F,
+    _act_b: PhantomData<fn() -> ActB>,
+}
+pub fn traverse<T, B, I, ActB, F>(items: I, func: F) -> Traverse<T, ActB, F>
+where
+    I: IntoIterator<Item = T>,
+    ActB: Action<B>,
+    F: Fn(&T) -> ActB,
+{
+    Traverse {
+        items: Rc::new(items.into_iter().collect()),
+        func,
+        _act_b: PhantomData,
+    }
+}
+impl<T, B, ActB, F> Mappable<Vec<B>> for Traverse<T, ActB, F>
+where
+    ActB: Mappable<B>,
+    F: Fn(&T) -> ActB,
+{
+}
+impl<T, B, ActB, F> Sequential<Vec<B>> for Traverse<T, ActB, F>
+where
+    ActB: Sequential<B>,
+    F: Fn(&T) -> ActB,
+{
+}
+impl<T, B, ActB, F> Action<Vec<B>> for Traverse<T, ActB, F>
+where
+    ActB: Action<B>,
+    F: Fn(&T) -> ActB,
+{
+}
+/// The loops only need scaffolds which can run a `TailRec` loop and `Map` an
+/// action, so as with the sequential actions there is one run instance for
+/// every scaffold.
+impl<S, T, B, ActB, F> Run<S, Vec<B>> for Traverse<T, ActB, F>
+where
+    S: TailRec<(usize, Collected<B>), Vec<B>>,
+    T: 'static,
+    B: Clone,
+    ActB: Action<B>,
+    Map<B, ActB, Push<B>>: Run<S, CollectStep<B>>,
+    F: Fn(&T) -> ActB + Clone + 'static,
+{
+    fn run(&self) -> <S as Ap<Vec<B>>>::R {
+        let items = self.items.clone();
+        let func = self.func.clone();
+        collect::<S, B, _, _>(items.len(), move |index| func(&items[index]))
+    }
+}
+
+/// Run every action in turn, and collect the results.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SequenceVec<ActA> {
+    actions: Vec<Shared<ActA>>,
+}
+pub fn sequence_vec<A, ActA>(actions: Vec<ActA>) -> SequenceVec<ActA>
+where
+    ActA: Action<A>,
+{
+    SequenceVec {
+        actions: actions
+            .into_iter()
+            .map(|act_a| Shared(Rc::new(act_a)))
+            .collect(),
+    }
+}
+impl<A, ActA> Mappable<Vec<A>> for SequenceVec<ActA> where ActA: Mappable<A> {}
+impl<A, ActA> Sequential<Vec<A>> for SequenceVec<ActA> where ActA: Sequential<A> {}
+impl<A, ActA> Action<Vec<A>> for SequenceVec<ActA> where ActA: Action<A> {}
+impl<S, A, ActA> Run<S, Vec<A>> for SequenceVec<ActA>
+where
+    S: TailRec<(usize, Collected<A>), Vec<A>>,
+    A: Clone,
+    ActA: Action<A> + 'static,
+    Map<A, Shared<ActA>, Push<A>>: Run<S, CollectStep<A>>,
+{
+    fn run(&self) -> <S as Ap<Vec<A>>>::R {
+        let actions = self.actions.clone();
+        collect::<S, A, _, _>(actions.len(), move |index| actions[index].clone())
+    }
+}
+
+/// Run an action a number of times, and collect the results.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReplicateM<ActA> {
+    count: usize,
+    act_a: Shared<ActA>,
+}
+pub fn replicate_m<A, ActA>(count: usize, act_a: ActA) -> ReplicateM<ActA>
+where
+    ActA: Action<A>,
+{
+    ReplicateM {
+        count,
+        act_a: Shared(Rc::new(act_a)),
+    }
+}
+impl<A, ActA> Mappable<Vec<A>> for ReplicateM<ActA> where ActA: Mappable<A> {}
+impl<A, ActA> Sequential<Vec<A>> for ReplicateM<ActA> where ActA: Sequential<A> {}
+impl<A, ActA> Action<Vec<A>> for ReplicateM<ActA> where ActA: Action<A> {}
+impl<S, A, ActA> Run<S, Vec<A>> for ReplicateM<ActA>
+where
+    S: TailRec<(usize, Collected<A>), Vec<A>>,
+    A: Clone,
+    ActA: Action<A> + 'static,
+    Map<A, Shared<ActA>, Push<A>>: Run<S, CollectStep<A>>,
+{
+    fn run(&self) -> <S as Ap<Vec<A>>>::R {
+        let act_a = self.act_a.clone();
+        collect::<S, A, _, _>(self.count, move |_| act_a.clone())
+    }
+}
+
+/// Run an action for every item, discarding the results.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ForEach<T, B, ActB, F> {
+    items: Rc<Vec<T>>,
+    func: F,
+    _act_b_type: PhantomData<fn() -> B>,
+    _act_b: PhantomData<fn() -> ActB>,
+}
+pub fn for_each<T, B, I, ActB, F>(items: I, func: F) -> ForEach<T, B, ActB, F>
+where
+    I: IntoIterator<Item = T>,
+    ActB: Action<B>,
+    F: Fn(&T) -> ActB,
+{
+    ForEach {
+        items: Rc::new(items.into_iter().collect()),
+        func,
+        _act_b_type: PhantomData,
+        _act_b: PhantomData,
+    }
+}
+impl<T, B, ActB, F> Mappable<()> for ForEach<T, B, ActB, F>
+where
+    ActB: Mappable<B>,
+    F: Fn(&T) -> ActB,
+{
+}
+impl<T, B, ActB, F> Sequential<()> for ForEach<T, B, ActB, F>
+where
+    ActB: Sequential<B>,
+    F: Fn(&T) -> ActB,
+{
+}
+impl<T, B, ActB, F> Action<()> for ForEach<T, B, ActB, F>
+where
+    ActB: Action<B>,
+    F: Fn(&T) -> ActB,
+{
+}
+impl<S, T, B, ActB, F> Run<S, ()> for ForEach<T, B, ActB, F>
+where
+    S: TailRec<usize, ()>,
+    T: 'static,
+    ActB: Action<B>,
+    Map<B, ActB, Next>: Run<S, Step<usize, ()>>,
+    F: Fn(&T) -> ActB + Clone + 'static,
+{
+    fn run(&self) -> <S as Ap<()>>::R {
+        let items = self.items.clone();
+        let func = self.func.clone();
+        S::tail_rec(0, move |index| match items.get(index) {
+            Some(item) => then_step(func(item), Next { index }).run(),
+            None => <S as Ap<Step<usize, ()>>>::build(Step::Done(())),
+        })
+    }
+}
+
+/// Thread an accumulator through an action for every item.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FoldM<T, Acc, ActAcc, F> {
+    items: Rc<Vec<T>>,
+    init: Acc,
+    func: F,
+    _act_acc: PhantomData<fn() -> ActAcc>,
+}
+pub fn fold_m<T, Acc, I, ActAcc, F>(items: I, init: Acc, func: F) -> FoldM<T, Acc, ActAcc, F>
+where
+    I: IntoIterator<Item = T>,
+    ActAcc: Action<Acc>,
+    F: Fn(&Acc, &T) -> ActAcc,
+{
+    FoldM {
+        items: Rc::new(items.into_iter().collect()),
+        init,
+        func,
+        _act_acc: PhantomData,
+    }
+}
+impl<T, Acc, ActAcc, F> Mappable<Acc> for FoldM<T, Acc, ActAcc, F>
+where
+    ActAcc: Mappable<Acc>,
+    F: Fn(&Acc, &T) -> ActAcc,
+{
+}
+impl<T, Acc, ActAcc, F> Sequential<Acc> for FoldM<T, Acc, ActAcc, F>
+where
+    ActAcc: Sequential<Acc>,
+    F: Fn(&Acc, &T) -> ActAcc,
+{
+}
+impl<T, Acc, ActAcc, F> Action<Acc> for FoldM<T, Acc, ActAcc, F>
+where
+    ActAcc: Action<Acc>,
+    F: Fn(&Acc, &T) -> ActAcc,
+{
+}
+impl<S, T, Acc, ActAcc, F> Run<S, Acc> for FoldM<T, Acc, ActAcc, F>
+where
+    S: TailRec<(usize, Acc), Acc>,
+    T: 'static,
+    Acc: Clone,
+    ActAcc: Action<Acc>,
+    Map<Acc, ActAcc, NextAcc>: Run<S, Step<(usize, Acc), Acc>>,
+    F: Fn(&Acc, &T) -> ActAcc + Clone + 'static,
+{
+    fn run(&self) -> <S as Ap<Acc>>::R {
+        let items = self.items.clone();
+        let func = self.func.clone();
+        S::tail_rec((0, self.init.clone()), move |(index, acc)| {
+            match items.get(index) {
+                Some(item) => then_step(func(&acc, item), NextAcc { index }).run(),
+                None => <S as Ap<Step<(usize, Acc), Acc>>>::build(Step::Done(acc)),
+            }
+        })
+    }
+}
+
+/// Run the body for as long as the condition produces `true`, and collect the
+/// results.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WhileM<ActCond, ActA> {
+    condition: Shared<ActCond>,
+    body: Shared<ActA>,
+}
+pub fn while_m<A, ActCond, ActA>(condition: ActCond, body: ActA) -> WhileM<ActCond, ActA>
+where
+    ActCond: Action<bool>,
+    ActA: Action<A>,
+{
+    WhileM {
+        condition: Shared(Rc::new(condition)),
+        body: Shared(Rc::new(body)),
+    }
+}
+impl<A, ActCond, ActA> Mappable<Vec<A>> for WhileM<ActCond, ActA>
+where
+    ActCond: Mappable<bool>,
+    ActA: Mappable<A>,
+{
+}
+impl<A, ActCond, ActA> Sequential<Vec<A>> for WhileM<ActCond, ActA>
+where
+    ActCond: Sequential<bool>,
+    ActA: Sequential<A>,
+{
+}
+impl<A, ActCond, ActA> Action<Vec<A>> for WhileM<ActCond, ActA>
+where
+    ActCond: Action<bool>,
+    ActA: Action<A>,
+{
+}
+impl<S, A, ActCond, ActA> Run<S, Vec<A>> for WhileM<ActCond, ActA>
+where
+    S: TailRec<WhileState<A>, Vec<A>>,
+    A: Clone,
+    ActCond: Action<bool> + 'static,
+    ActA: Action<A> + 'static,
+    Map<bool, Shared<ActCond>, Check<A>>: Run<S, WhileStep<A>>,
+    Map<A, Shared<ActA>, PushBody<A>>: Run<S, WhileStep<A>>,
+{
+    fn run(&self) -> <S as Ap<Vec<A>>>::R {
+        let condition = self.condition.clone();
+        let body = self.body.clone();
+        S::tail_rec((Collected::new(), false), move |(collected, run_body)| {
+            if run_body {
+                then_step(body.clone(), PushBody { collected }).run()
+            } else {
+                then_step(condition.clone(), Check { collected }).run()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::monoid::Sum;
+    use super::super::{
+        asks, block_on, choose, from_future, get, gets, if_then_else, modify, pure, put, tell,
+        throw_error, writer, BuildError, BuildFuture, BuildList, BuildReader, BuildState,
+        BuildWriter,
+    };
+    use super::*;
+
+    #[test]
+    fn traverse_identity() {
+        let action = traverse(vec![1, 2, 3], |&x| pure(x * 2));
+        let action2 = sequence_vec(vec![pure(1), pure(2)]);
+        let action3 = fold_m(1..=4, 0, |acc, &x| pure(acc + x));
+
+        assert_eq!(action.run_qualified(&()), vec![2, 4, 6]);
+        assert_eq!(action2.run_qualified(&()), vec![1, 2]);
+        assert_eq!(action3.run_qualified(&()), 10);
+        assert_eq!(replicate_m(3, pure('a')).run_qualified(&()), vec!['a'; 3]);
+    }
+
+    #[test]
+    fn loops_write_logs() {
+        let writer_type: PhantomData<fn() -> Sum<u64>> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = traverse(0..1_000_000u64, |&x| writer(x % 2, Sum(x)));
+        let action2 = for_each(vec![1, 2, 3], |&x| tell(Sum(x)));
+        let action3 = replicate_m(2, writer('a', Sum(5)));
+
+        let (results, log) = action.run_qualified(&scaffold);
+        assert_eq!((results.len(), results[999_999]), (1_000_000, 1));
+        assert_eq!(log, Sum(499_999_500_000));
+        assert_eq!(action2.run_qualified(&scaffold), ((), Sum(6)));
+        assert_eq!(action3.run_qualified(&scaffold), (vec!['a', 'a'], Sum(10)));
+    }
+
+    #[test]
+    fn loops_stop_at_error() {
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, ());
+
+        let action = |xs: Vec<i32>| {
+            fold_m(xs, 0, |acc, &x| {
+                if_then_else(x >= 0, pure(acc + x), throw_error("negative"))
+            })
+        };
+        let action2 = sequence_vec(vec![throw_error::<u32, _>("first"), throw_error("second")]);
+
+        assert_eq!(action(vec![1, 2, 3]).run_qualified(&scaffold), Ok(6));
+        assert_eq!(
+            action(vec![1, -2, 3]).run_qualified(&scaffold),
+            Err("negative")
+        );
+        assert_eq!(action2.run_qualified(&scaffold), Err("first"));
+    }
+
+    #[test]
+    fn traverse_every_choice() {
+        let scaffold = BuildList();
+
+        let action = traverse(vec![2, 3], |&n| choose(0..n));
+
+        assert_eq!(
+            action.run_qualified(&scaffold),
+            vec![
+                vec![0, 0],
+                vec![0, 1],
+                vec![0, 2],
+                vec![1, 0],
+                vec![1, 1],
+                vec![1, 2]
+            ]
+        );
+    }
+
+    #[test]
+    fn while_condition_holds() {
+        let scaffold: BuildState<u32> = BuildState(PhantomData);
+
+        let condition = gets(|&count: &u32| count < 3);
+        let body = get().and_then(|&count: &u32| put(count + 1).map(move |_| (count + 1) * 10));
+
+        assert_eq!(
+            while_m(condition, body)
+                .run_qualified(&scaffold)
+                .run_state(0),
+            (vec![10, 20, 30], 3)
+        );
+    }
+
+    #[test]
+    fn lazy_scaffolds_loop() {
+        let state_scaffold: BuildState<u64> = BuildState(PhantomData);
+        let reader_scaffold: BuildReader<u32> = BuildReader(PhantomData);
+
+        let action = for_each(1..=1_000_000u64, |&x| modify(move |total: &u64| total + x));
+        let action2 = traverse(vec![1, 2, 3], |&x| asks(move |&env: &u32| env * x));
+        let action3 = fold_m(vec![1, 2, 3], 0, |&acc, &x| {
+            from_future(move || async move { acc + x })
+        });
+
+        assert_eq!(
+            action.run_qualified(&state_scaffold).run_state(0),
+            ((), 500_000_500_000)
+        );
+        assert_eq!(
+            action2.run_qualified(&reader_scaffold).run_reader(&10),
+            vec![10, 20, 30]
+        );
+        assert_eq!(block_on(action3.run_qualified(&BuildFuture)), 6);
+    }
+}
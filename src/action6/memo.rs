@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use super::{Action, Ap, Mappable, Run, Sequential};
+
+/// Somewhere to keep the results of memoized actions. Values are cloned out
+/// of the store, so the completed types are usually cheap to clone.
+pub trait CacheStore<K, V> {
+    fn get(&mut self, key: &K) -> Option<V>;
+    fn insert(&mut self, key: K, value: V);
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn clear(&mut self);
+}
+
+/// A store which keeps every result.
+#[derive(Clone, Debug)]
+pub struct HashMapStore<K, V>(HashMap<K, V>);
+impl<K, V> Default for HashMapStore<K, V> {
+    fn default() -> Self {
+        HashMapStore(HashMap::new())
+    }
+}
+impl<K, V> CacheStore<K, V> for HashMapStore<K, V>
+where
+    K: Hash + Eq,
+    V: Clone,
+{
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.0.get(key).cloned()
+    }
+    fn insert(&mut self, key: K, value: V) {
+        self.0.insert(key, value);
+    }
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+    fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+/// A store which keeps at most `capacity` results, forgetting the least
+/// recently used result to make room for a new one.
+#[derive(Clone, Debug)]
+pub struct LruStore<K, V> {
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    /// The keys in the order they were last used.
+    used: BTreeMap<u64, K>,
+    clock: u64,
+}
+impl<K, V> LruStore<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        LruStore {
+            capacity,
+            entries: HashMap::new(),
+            used: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+}
+impl<K, V> CacheStore<K, V> for LruStore<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+{
+    fn get(&mut self, key: &K) -> Option<V> {
+        let (value, last_used) = self.entries.get_mut(key)?;
+        self.clock += 1;
+        self.used.remove(last_used);
+        self.used.insert(self.clock, key.clone());
+        *last_used = self.clock;
+        Some(value.clone())
+    }
+    fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+        if let Some((_, last_used)) = self.entries.get(&key) {
+            self.used.remove(last_used);
+        } else if self.entries.len() == self.capacity {
+            if let Some((_, oldest)) = self.used.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.used.insert(self.clock, key.clone());
+        self.entries.insert(key, (value, self.clock));
+    }
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.used.clear();
+    }
+}
+
+/// How often a `MemoCache` has found a result, and how often it had to run
+/// the action instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct Cached<C> {
+    store: C,
+    stats: CacheStats,
+}
+
+/// A cache of completed types `V`, shared by every `Memo` made from it. The
+/// completed type depends on the scaffold, so a cache can only be used with
+/// one scaffold and result type.
+pub struct MemoCache<K, V, C = HashMapStore<K, V>> {
+    cached: Arc<Mutex<Cached<C>>>,
+    _types: PhantomData<fn(K) -> V>,
+}
+impl<K, V, C> Clone for MemoCache<K, V, C> {
+    fn clone(&self) -> Self {
+        MemoCache {
+            cached: self.cached.clone(),
+            _types: PhantomData,
+        }
+    }
+}
+impl<K, V> MemoCache<K, V> {
+    pub fn new() -> Self {
+        Self::with_store(HashMapStore::default())
+    }
+}
+impl<K, V> Default for MemoCache<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<K, V> MemoCache<K, V, LruStore<K, V>> {
+    pub fn lru(capacity: usize) -> Self {
+        Self::with_store(LruStore::new(capacity))
+    }
+}
+impl<K, V, C> MemoCache<K, V, C> {
+    pub fn with_store(store: C) -> Self {
+        MemoCache {
+            cached: Arc::new(Mutex::new(Cached {
+                store,
+                stats: CacheStats::default(),
+            })),
+            _types: PhantomData,
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.cached.lock().unwrap().stats
+    }
+
+    /// Wrap an action so that its result is cached under `key`. Every action
+    /// memoized under the same key must produce the same result.
+    pub fn memo_with_key<ActA>(&self, key: K, act_a: ActA) -> Memo<K, V, ActA, C> {
+        Memo {
+            key,
+            act_a,
+            cache: self.clone(),
+        }
+    }
+
+    /// Wrap an action so that its result is cached under the action itself,
+    /// which works for actions without closures, such as `Pure` and `Writer`.
+    pub fn memo(&self, act_a: K) -> Memo<K, V, K, C>
+    where
+        K: Clone,
+    {
+        self.memo_with_key(act_a.clone(), act_a)
+    }
+
+    /// Run an action with the scaffold `S`, or return its result from the
+    /// cache if it has been run before.
+    pub fn run<S, A>(&self, act_a: &K, _scaffold: &S) -> V
+    where
+        S: Ap<A, R = V>,
+        K: Run<S, A> + Clone,
+        V: Clone,
+        C: CacheStore<K, V>,
+    {
+        self.memo(act_a.clone()).run()
+    }
+
+    /// Forget every result, but keep the statistics.
+    pub fn clear(&self)
+    where
+        C: CacheStore<K, V>,
+    {
+        self.cached.lock().unwrap().store.clear()
+    }
+}
+
+/// `Memo` is an action which runs `act_a` the first time it is run, and then
+/// returns the same result from its cache. Other `Memo`s made from the same
+/// `MemoCache` with the same key share the result.
+pub struct Memo<K, V, ActA, C = HashMapStore<K, V>> {
+    key: K,
+    act_a: ActA,
+    cache: MemoCache<K, V, C>,
+}
+impl<K, V, ActA, C> Clone for Memo<K, V, ActA, C>
+where
+    K: Clone,
+    ActA: Clone,
+{
+    fn clone(&self) -> Self {
+        Memo {
+            key: self.key.clone(),
+            act_a: self.act_a.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+impl<A, K, V, ActA, C> Mappable<A> for Memo<K, V, ActA, C> where ActA: Mappable<A> {}
+impl<A, K, V, ActA, C> Sequential<A> for Memo<K, V, ActA, C> where ActA: Sequential<A> {}
+impl<A, K, V, ActA, C> Action<A> for Memo<K, V, ActA, C> where ActA: Action<A> {}
+/// The cache isn't locked while `act_a` runs, so it may contain other `Memo`s
+/// made from the same cache.
+impl<S, A, K, V, ActA, C> Run<S, A> for Memo<K, V, ActA, C>
+where
+    S: Ap<A, R = V>,
+    K: Clone,
+    V: Clone,
+    C: CacheStore<K, V>,
+    ActA: Run<S, A>,
+{
+    fn run(&self) -> V {
+        {
+            let mut cached = self.cache.cached.lock().unwrap();
+            if let Some(value) = cached.store.get(&self.key) {
+                cached.stats.hits += 1;
+                return value;
+            }
+            cached.stats.misses += 1;
+        }
+        let value = self.act_a.run();
+        let mut cached = self.cache.cached.lock().unwrap();
+        cached.store.insert(self.key.clone(), value.clone());
+        value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::monoid::Sum;
+    use super::super::{pure, writer, BoxAction, Boxed, BuildWriter, Sequential, Writer};
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fib(cache: &MemoCache<u64, u64>, calls: &Arc<AtomicU32>, n: u64) -> BoxAction<(), u64> {
+        let inner = cache.clone();
+        let calls = calls.clone();
+        let act = pure(n).and_then(move |&n| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            if n < 2 {
+                pure(n).boxed()
+            } else {
+                fib(&inner, &calls, n - 1)
+                    .combine(fib(&inner, &calls, n - 2), |x, y| x + y)
+                    .boxed()
+            }
+        });
+        cache.memo_with_key(n, act).boxed()
+    }
+
+    #[test]
+    fn dynamic_programming() {
+        let cache = MemoCache::new();
+        let calls = Arc::new(AtomicU32::new(0));
+
+        assert_eq!(fib(&cache, &calls, 60).run(), 1_548_008_755_920);
+        assert_eq!(calls.load(Ordering::SeqCst), 61);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 58,
+                misses: 61
+            }
+        );
+
+        assert_eq!(fib(&cache, &calls, 60).run(), 1_548_008_755_920);
+        assert_eq!(calls.load(Ordering::SeqCst), 61);
+        assert_eq!(cache.stats().hits, 59);
+    }
+
+    #[test]
+    fn keyed_by_action() {
+        let writer_type: PhantomData<fn() -> Sum<u32>> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+        let cache: MemoCache<Writer<u32, Sum<u32>>, (u32, Sum<u32>)> = MemoCache::new();
+
+        let action = cache
+            .memo(writer(3, Sum(1)))
+            .sequence(cache.memo(writer(3, Sum(1))))
+            .sequence(cache.memo(writer(4, Sum(1))));
+
+        assert_eq!(action.run_qualified(&scaffold), (((3, 3), 4), Sum(3)));
+        assert_eq!(cache.run(&writer(4, Sum(1)), &scaffold), (4, Sum(1)));
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 2 });
+    }
+
+    #[test]
+    fn lru_forgets_least_recently_used() {
+        let cache: MemoCache<u32, u32, LruStore<u32, u32>> = MemoCache::lru(2);
+        let square = |n: u32| cache.memo_with_key(n, pure(n).map(|n| n * n));
+
+        assert_eq!(square(1).run_qualified(&()), 1);
+        assert_eq!(square(2).run_qualified(&()), 4);
+        assert_eq!(square(1).run_qualified(&()), 1);
+        assert_eq!(square(3).run_qualified(&()), 9);
+        assert_eq!(square(1).run_qualified(&()), 1);
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 3 });
+        assert_eq!(square(2).run_qualified(&()), 4);
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 4 });
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::ops::{Add, Mul};
+
+/// Values which can be combined with an associative operation.
+///
+/// Some types, such as `Min<T>` for an arbitrary `T: Ord`, can always be
+/// combined but have no identity, so they are only a `Semigroup`.
+pub trait Semigroup {
+    fn op(&self, other: &Self) -> Self;
+
+    /// Combine a non-empty sequence of values, from left to right.
+    fn sconcat<I>(first: Self, rest: I) -> Self
+    where
+        Self: Sized,
+        I: IntoIterator<Item = Self>,
+    {
+        rest.into_iter().fold(first, |acc, value| acc.op(&value))
+    }
+}
+
+/// A `Semigroup` with an identity, given by `Default`. Writer logs and
+/// validation errors are collected with a monoid.
+pub trait Monoid: Semigroup + Default {
+    /// Combine any number of values, from left to right.
+    fn mconcat<I>(values: I) -> Self
+    where
+        I: IntoIterator<Item = Self>,
+    {
+        Self::sconcat(Self::default(), values)
+    }
+}
+
+impl Semigroup for u32 {
+    fn op(&self, other: &Self) -> Self {
+        self + other
+    }
+}
+impl Monoid for u32 {}
+
+impl Semigroup for () {
+    fn op(&self, _other: &Self) -> Self {}
+}
+impl Monoid for () {}
+
+/// Combine numbers by adding them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sum<T>(pub T);
+impl<T> Semigroup for Sum<T>
+where
+    T: Add<Output = T> + Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        Sum(self.0.clone() + other.0.clone())
+    }
+}
+impl<T> Monoid for Sum<T> where T: Add<Output = T> + Clone + Default {}
+
+/// Combine numbers by multiplying them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Product<T>(pub T);
+impl<T> Semigroup for Product<T>
+where
+    T: Mul<Output = T> + Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        Product(self.0.clone() * other.0.clone())
+    }
+}
+
+/// Keep the smallest value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Min<T>(pub T);
+impl<T> Semigroup for Min<T>
+where
+    T: Ord + Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        Min(self.0.clone().min(other.0.clone()))
+    }
+}
+
+/// Keep the largest value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Max<T>(pub T);
+impl<T> Semigroup for Max<T>
+where
+    T: Ord + Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        Max(self.0.clone().max(other.0.clone()))
+    }
+}
+
+/// The identities for `Product`, `Min` and `Max` depend on the number type, so
+/// they are only monoids for the primitive numbers.
+macro_rules! number_monoids {
+    ($one:expr; $($t:ty),*) => {
+        $(
+            impl Default for Product<$t> {
+                fn default() -> Self {
+                    Product($one)
+                }
+            }
+            impl Monoid for Product<$t> {}
+        )*
+    };
+}
+number_monoids!(1; u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+number_monoids!(1.0; f32, f64);
+
+macro_rules! bounded_monoids {
+    ($($t:ty),*) => {
+        $(
+            impl Default for Min<$t> {
+                fn default() -> Self {
+                    Min(<$t>::MAX)
+                }
+            }
+            impl Monoid for Min<$t> {}
+            impl Default for Max<$t> {
+                fn default() -> Self {
+                    Max(<$t>::MIN)
+                }
+            }
+            impl Monoid for Max<$t> {}
+        )*
+    };
+}
+bounded_monoids!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// Keep the first value which is present.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct First<T>(pub Option<T>);
+impl<T> Default for First<T> {
+    fn default() -> Self {
+        First(None)
+    }
+}
+impl<T> Semigroup for First<T>
+where
+    T: Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        First(self.0.clone().or_else(|| other.0.clone()))
+    }
+}
+impl<T> Monoid for First<T> where T: Clone {}
+
+/// Keep the last value which is present.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Last<T>(pub Option<T>);
+impl<T> Default for Last<T> {
+    fn default() -> Self {
+        Last(None)
+    }
+}
+impl<T> Semigroup for Last<T>
+where
+    T: Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        Last(other.0.clone().or_else(|| self.0.clone()))
+    }
+}
+impl<T> Monoid for Last<T> where T: Clone {}
+
+/// Check whether any value is `true`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Any(pub bool);
+impl Semigroup for Any {
+    fn op(&self, other: &Self) -> Self {
+        Any(self.0 || other.0)
+    }
+}
+impl Monoid for Any {}
+
+/// Check whether every value is `true`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct All(pub bool);
+impl Default for All {
+    fn default() -> Self {
+        All(true)
+    }
+}
+impl Semigroup for All {
+    fn op(&self, other: &Self) -> Self {
+        All(self.0 && other.0)
+    }
+}
+impl Monoid for All {}
+
+impl Semigroup for String {
+    fn op(&self, other: &Self) -> Self {
+        let mut result = String::with_capacity(self.len() + other.len());
+        result.push_str(self);
+        result.push_str(other);
+        result
+    }
+}
+impl Monoid for String {}
+
+impl<T> Semigroup for Vec<T>
+where
+    T: Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        let mut result = Vec::with_capacity(self.len() + other.len());
+        result.extend_from_slice(self);
+        result.extend_from_slice(other);
+        result
+    }
+}
+impl<T> Monoid for Vec<T> where T: Clone {}
+
+/// `None` is the identity, so any semigroup can be made into a monoid by
+/// wrapping it in an `Option`.
+impl<M> Semigroup for Option<M>
+where
+    M: Semigroup + Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Some(value_a), Some(value_b)) => Some(value_a.op(value_b)),
+            (Some(value), None) | (None, Some(value)) => Some(value.clone()),
+            (None, None) => None,
+        }
+    }
+}
+impl<M> Monoid for Option<M> where M: Semigroup + Clone {}
+
+/// Merge two maps, combining the values for keys which appear in both.
+impl<K, V> Semigroup for HashMap<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Semigroup + Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        for (key, value_b) in other {
+            let value = match result.get(key) {
+                Some(value_a) => value_a.op(value_b),
+                None => value_b.clone(),
+            };
+            result.insert(key.clone(), value);
+        }
+        result
+    }
+}
+impl<K, V> Monoid for HashMap<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Semigroup + Clone,
+{
+}
+
+/// Merge two maps, combining the values for keys which appear in both.
+impl<K, V> Semigroup for BTreeMap<K, V>
+where
+    K: Ord + Clone,
+    V: Semigroup + Clone,
+{
+    fn op(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        for (key, value_b) in other {
+            let value = match result.get(key) {
+                Some(value_a) => value_a.op(value_b),
+                None => value_b.clone(),
+            };
+            result.insert(key.clone(), value);
+        }
+        result
+    }
+}
+impl<K, V> Monoid for BTreeMap<K, V>
+where
+    K: Ord + Clone,
+    V: Semigroup + Clone,
+{
+}
+
+/// Tuples combine each of their parts, so a writer can collect several logs
+/// at once.
+macro_rules! tuple_monoids {
+    ($(($($t:ident . $i:tt),+))*) => {
+        $(
+            impl<$($t),+> Semigroup for ($($t,)+)
+            where
+                $($t: Semigroup),+
+            {
+                fn op(&self, other: &Self) -> Self {
+                    ($(self.$i.op(&other.$i),)+)
+                }
+            }
+            impl<$($t),+> Monoid for ($($t,)+) where $($t: Monoid),+ {}
+        )*
+    };
+}
+tuple_monoids! {
+    (A.0)
+    (A.0, B.1)
+    (A.0, B.1, C.2)
+    (A.0, B.1, C.2, D.3)
+    (A.0, B.1, C.2, D.3, E.4)
+    (A.0, B.1, C.2, D.3, E.4, F.5)
+    (A.0, B.1, C.2, D.3, E.4, F.5, G.6)
+    (A.0, B.1, C.2, D.3, E.4, F.5, G.6, H.7)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(Sum::mconcat(vec![Sum(1), Sum(2), Sum(3)]), Sum(6));
+        assert_eq!(Product::mconcat(vec![Product(2), Product(3)]), Product(6));
+        assert_eq!(Product::<f64>::mconcat(vec![]), Product(1.0));
+        assert_eq!(Min::mconcat(vec![Min(4u8), Min(2)]), Min(2));
+        assert_eq!(Max::<i32>::mconcat(vec![]), Max(i32::MIN));
+        assert_eq!(Min::sconcat(Min("b"), vec![Min("a"), Min("c")]), Min("a"));
+    }
+
+    #[test]
+    fn first_last_any_all() {
+        let values = vec![None, Some(1), None, Some(2), None];
+
+        assert_eq!(
+            First::mconcat(values.iter().cloned().map(First)),
+            First(Some(1))
+        );
+        assert_eq!(Last::mconcat(values.into_iter().map(Last)), Last(Some(2)));
+        assert_eq!(Any::mconcat(vec![Any(false), Any(true)]), Any(true));
+        assert_eq!(All::mconcat(vec![All(false), All(true)]), All(false));
+        assert_eq!(All::mconcat(vec![]), All(true));
+    }
+
+    #[test]
+    fn collections() {
+        assert_eq!("ab".to_string().op(&"cd".to_string()), "abcd");
+        assert_eq!(vec![1, 2].op(&vec![3]), vec![1, 2, 3]);
+        assert_eq!(Some(Min(3)).op(&None), Some(Min(3)));
+        assert_eq!(Some(Min(3)).op(&Some(Min(1))), Some(Min(1)));
+    }
+
+    #[test]
+    fn maps_merge_values() {
+        let mut counts_a = HashMap::new();
+        counts_a.insert("a", Sum(1));
+        counts_a.insert("b", Sum(2));
+        let mut counts_b = HashMap::new();
+        counts_b.insert("b", Sum(3));
+        counts_b.insert("c", Sum(4));
+
+        let counts = counts_a.op(&counts_b);
+        let ordered: BTreeMap<_, _> = counts.clone().into_iter().collect();
+
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts["b"], Sum(5));
+        assert_eq!(
+            ordered.op(&ordered).into_iter().collect::<Vec<_>>(),
+            vec![("a", Sum(2)), ("b", Sum(10)), ("c", Sum(8))]
+        );
+    }
+
+    #[test]
+    fn tuples() {
+        let log = (Sum(1), vec!["started"], Max(3u8));
+        let log2 = (Sum(2), vec!["finished"], Max(1u8));
+
+        assert_eq!(log.op(&log2), (Sum(3), vec!["started", "finished"], Max(3)));
+        assert_eq!(
+            <(u32, (), String, Any, All, Sum<i8>, First<u8>, Last<u8>)>::default().op(&(
+                1,
+                (),
+                "x".to_string(),
+                Any(true),
+                All(false),
+                Sum(2),
+                First(Some(1)),
+                Last(None)
+            )),
+            (
+                1,
+                (),
+                "x".to_string(),
+                Any(true),
+                All(false),
+                Sum(2),
+                First(Some(1)),
+                Last(None)
+            )
+        );
+    }
+}
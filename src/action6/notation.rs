@@ -0,0 +1,229 @@
+/// Write a chain of actions one step at a time, like `do` notation.
+///
+/// ```ignore
+/// action! {
+///     let x <- pure(3);
+///     tell(x);
+///     let y = x * 2;
+///     let z <- if y > 5 { writer(y, 1) } else { pure(0) };
+///     pure(x + y + z)
+/// }
+/// ```
+///
+/// Each step is one of:
+///
+/// * `let pattern <- act;` runs `act` and binds a clone of its result, so
+///   later steps get owned values rather than references.
+/// * `let pattern = expr;` is an ordinary `let` binding.
+/// * `act;` runs `act` and ignores its result.
+/// * `if cond { ... } else { ... }` runs one of two blocks of steps. The
+///   blocks may produce different types of action, since each is wrapped in an
+///   `EitherAction`. Without an `else`, the other branch is `pure(())`.
+///
+/// The last step is the action which produces the result. Steps are joined
+/// with `Action::and_then`, using `move` closures, so values from outside the
+/// macro which are used after the first step are moved or copied in. When the
+/// last step is `pure(expr)`, it is joined with `Mappable::map` instead.
+#[macro_export]
+macro_rules! action {
+    (@with (stmt) ($act:expr)) => {
+        $act
+    };
+    (@with (stmt) ($act:expr); pure($value:expr)) => {
+        $crate::action6::Mappable::map($act, move |_| $value)
+    };
+    (@with (bind $pattern:tt) ($act:expr); pure($value:expr)) => {
+        $crate::action6::Mappable::map($act, move |value| {
+            let $pattern = ::std::clone::Clone::clone(value);
+            $value
+        })
+    };
+    (@with (bind_mut $name:ident) ($act:expr); pure($value:expr)) => {
+        $crate::action6::Mappable::map($act, move |value| {
+            let mut $name = ::std::clone::Clone::clone(value);
+            $value
+        })
+    };
+    (@with (stmt) ($act:expr); $($rest:tt)+) => {
+        $crate::action6::Action::and_then($act, move |_| $crate::action!($($rest)+))
+    };
+    (@with (bind $pattern:tt) ($act:expr); $($rest:tt)+) => {
+        $crate::action6::Action::and_then($act, move |value| {
+            let $pattern = ::std::clone::Clone::clone(value);
+            $crate::action!($($rest)+)
+        })
+    };
+    (@with (bind_mut $name:ident) ($act:expr); $($rest:tt)+) => {
+        $crate::action6::Action::and_then($act, move |value| {
+            let mut $name = ::std::clone::Clone::clone(value);
+            $crate::action!($($rest)+)
+        })
+    };
+    (@if $mode:tt [$($cond:tt)+] { $($then:tt)+ } else { $($else:tt)+ } $($rest:tt)*) => {
+        $crate::action!(
+            @with $mode (
+                if $($cond)+ {
+                    $crate::action6::EitherAction::Left($crate::action!($($then)+))
+                } else {
+                    $crate::action6::EitherAction::Right($crate::action!($($else)+))
+                }
+            )
+            $($rest)*
+        )
+    };
+    (@if $mode:tt [$($cond:tt)+] { $($then:tt)+ } $($rest:tt)*) => {
+        $crate::action!(
+            @with $mode (
+                if $($cond)+ {
+                    $crate::action6::EitherAction::Left($crate::action!($($then)+))
+                } else {
+                    $crate::action6::EitherAction::Right($crate::action6::pure(()))
+                }
+            )
+            $($rest)*
+        )
+    };
+    (@if $mode:tt [$($cond:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::action!(@if $mode [$($cond)* $next] $($rest)*)
+    };
+    (let mut $name:ident <- if $($rest:tt)+) => {
+        $crate::action!(@if (bind_mut $name) [] $($rest)+)
+    };
+    (let $pattern:tt <- if $($rest:tt)+) => {
+        $crate::action!(@if (bind $pattern) [] $($rest)+)
+    };
+    (let mut $name:ident <- $act:expr; $($rest:tt)+) => {
+        $crate::action!(@with (bind_mut $name) ($act); $($rest)+)
+    };
+    (let $pattern:tt <- $act:expr; $($rest:tt)+) => {
+        $crate::action!(@with (bind $pattern) ($act); $($rest)+)
+    };
+    (let $pattern:pat = $value:expr; $($rest:tt)+) => {{
+        let $pattern = $value;
+        $crate::action!($($rest)+)
+    }};
+    (if $($rest:tt)+) => {
+        $crate::action!(@if (stmt) [] $($rest)+)
+    };
+    ($act:expr; $($rest:tt)+) => {
+        $crate::action!(@with (stmt) ($act); $($rest)+)
+    };
+    ($act:expr) => {
+        $act
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::monoid::Sum;
+    use super::super::{
+        pure, tell, throw_error, writer, Action, BuildError, BuildWriter, Map, Mappable, Run,
+    };
+    use std::marker::PhantomData;
+
+    #[test]
+    fn binds_in_order() {
+        let action = action! {
+            let x <- pure(3);
+            let y <- pure(x + 2);
+            pure(x * y)
+        };
+        let action2 = pure(3).and_then(|&x| pure(x + 2).and_then(move |&y| pure(x * y)));
+
+        assert_eq!(action.run_qualified(&()), action2.run_qualified(&()));
+    }
+
+    #[test]
+    fn final_pure_is_a_map() {
+        fn is_map<A, ActA, F>(_: &Map<A, ActA, F>) {}
+
+        let action = action! {
+            tell(Sum(3));
+            pure(4)
+        };
+        let action2 = action! {
+            let mut x <- pure(3);
+            pure({
+                x += 1;
+                x
+            })
+        };
+
+        is_map(&action);
+        is_map(&action2);
+        let writer_type: PhantomData<fn() -> Sum<u32>> = PhantomData;
+        assert_eq!(
+            action.run_qualified(&BuildWriter(writer_type, ())),
+            (4, Sum(3))
+        );
+        assert_eq!(action2.run_qualified(&()), 4);
+    }
+
+    #[test]
+    fn owned_bindings() {
+        let writer_type: PhantomData<fn() -> Vec<String>> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = action! {
+            let name <- pure("alice".to_string());
+            tell(vec![format!("hello {}", name)]);
+            let (first, rest) = (name[..1].to_string(), name[1..].to_string());
+            let mut upper <- pure(first.to_uppercase());
+            let len = {
+                upper.push_str(&rest);
+                upper.len()
+            };
+            pure(upper).map(move |name| (name.clone(), len))
+        };
+
+        assert_eq!(
+            action.run_qualified(&scaffold),
+            (("Alice".to_string(), 5), vec!["hello alice".to_string()])
+        );
+    }
+
+    #[test]
+    fn if_branches() {
+        let writer_type: PhantomData<fn() -> Sum<u32>> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = |x: u32| {
+            action! {
+                let y <- if x > 5 {
+                    tell(Sum(1));
+                    pure(x)
+                } else {
+                    writer(0, Sum(10))
+                };
+                if y == 0 {
+                    tell(Sum(100))
+                };
+                pure(y * 2)
+            }
+        };
+
+        assert_eq!(action(7).run_qualified(&scaffold), (14, Sum(1)));
+        assert_eq!(action(3).run_qualified(&scaffold), (0, Sum(110)));
+    }
+
+    #[test]
+    fn final_if_short_circuits() {
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, ());
+
+        let action = |x: i32| {
+            action! {
+                let y <- pure(x - 1);
+                if y < 0 {
+                    throw_error("negative")
+                } else {
+                    let z <- pure(y * 10);
+                    pure(z + 1)
+                }
+            }
+        };
+
+        assert_eq!(action(3).run_qualified(&scaffold), Ok(21));
+        assert_eq!(action(0).run_qualified(&scaffold), Err("negative"));
+    }
+}
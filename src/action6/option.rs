@@ -0,0 +1,365 @@
+use std::marker::PhantomData;
+
+use super::{
+    Action, AndThen, Ap, BaseCatchError, BaseError, BaseListen, BaseWriter, Bind, BuildError,
+    BuildId, BuildValidation, BuildWriter, CatchError, Combine, Eager, First, Identity, Map, MapFn,
+    Mappable, Run, Second, Sequential, Zip,
+};
+
+/// The scaffold for actions which may not produce a value. `BuildOption<S>`
+/// produces `Option<A>` completed by the inner scaffold `S`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuildOption<S = ()>(pub S);
+
+impl<S> Eager for BuildOption<S> {}
+impl<A, S> Ap<A> for BuildOption<S>
+where
+    S: Ap<Option<A>>,
+{
+    type R = S::R;
+    fn build(value: A) -> Self::R {
+        S::build(Some(value))
+    }
+}
+impl<A, B, S> Bind<A, B> for BuildOption<S>
+where
+    S: Bind<Option<A>, Option<B>>,
+{
+    fn bind<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<B>>::R,
+    {
+        S::bind(value, |result_a| match result_a {
+            Some(result_a) => func(result_a),
+            None => <S as Ap<Option<B>>>::build(None),
+        })
+    }
+}
+impl<A, B, S> Zip<A, B> for BuildOption<S>
+where
+    S: Zip<Option<A>, Option<B>> + Bind<(Option<A>, Option<B>), Option<(A, B)>>,
+{
+    fn zip(value_a: <Self as Ap<A>>::R, value_b: <Self as Ap<B>>::R) -> <Self as Ap<(A, B)>>::R {
+        S::bind(S::zip(value_a, value_b), |(result_a, result_b)| {
+            <S as Ap<Option<(A, B)>>>::build(result_a.zip(result_b))
+        })
+    }
+}
+
+/// Scaffolds which can complete without a value. An outer scaffold which is not
+/// an option passes the missing value through to the scaffold it wraps.
+pub trait BaseOption<A>: Ap<A> {
+    fn none() -> Self::R;
+}
+impl<A, S> BaseOption<A> for BuildOption<S>
+where
+    S: Ap<Option<A>>,
+{
+    fn none() -> Self::R {
+        S::build(None)
+    }
+}
+impl<A, S> BaseOption<A> for BuildId<S>
+where
+    S: BaseOption<Identity<A>>,
+{
+    fn none() -> Self::R {
+        S::none()
+    }
+}
+impl<A, S, W> BaseOption<A> for BuildWriter<W, S>
+where
+    S: BaseOption<(A, W)>,
+    W: Default,
+{
+    fn none() -> Self::R {
+        S::none()
+    }
+}
+impl<A, E, S> BaseOption<A> for BuildError<E, S>
+where
+    S: BaseOption<Result<A, E>>,
+{
+    fn none() -> Self::R {
+        S::none()
+    }
+}
+impl<A, E, S> BaseOption<A> for BuildValidation<E, S>
+where
+    S: BaseOption<Result<A, E>>,
+{
+    fn none() -> Self::R {
+        S::none()
+    }
+}
+impl<A, S, W> BaseWriter<A, W> for BuildOption<S>
+where
+    S: BaseWriter<Option<A>, W>,
+{
+    fn writer(value: A, log: W) -> Self::R {
+        S::writer(Some(value), log)
+    }
+}
+impl<A, B, S> BaseListen<A, B> for BuildOption<S>
+where
+    S: BaseListen<Option<A>, Option<B>>,
+{
+    type Log = S::Log;
+    fn listen<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A, Self::Log) -> (B, Self::Log),
+    {
+        S::listen(value, |result, log| match result {
+            Some(result) => {
+                let (result, log) = func(result, log);
+                (Some(result), log)
+            }
+            None => (None, log),
+        })
+    }
+}
+impl<A, E, S> BaseError<A, E> for BuildOption<S>
+where
+    S: BaseError<Option<A>, E>,
+{
+    fn throw_error(error: E) -> Self::R {
+        S::throw_error(error)
+    }
+}
+impl<A, E, S> BaseCatchError<A, E> for BuildOption<S>
+where
+    S: BaseCatchError<Option<A>, E>,
+{
+    fn catch_error<F>(value: Self::R, func: F) -> Self::R
+    where
+        F: Fn(E) -> Self::R,
+    {
+        S::catch_error(value, func)
+    }
+}
+impl<A, E, ActA, ActB, F, S> Run<BuildOption<S>, A> for CatchError<E, ActA, ActB, F>
+where
+    S: BaseCatchError<Option<A>, E>,
+    ActA: Run<BuildOption<S>, A>,
+    ActB: Run<BuildOption<S>, A>,
+    F: Fn(&E) -> ActB,
+{
+    fn run(&self) -> <BuildOption<S> as Ap<A>>::R {
+        self.run_catch::<BuildOption<S>, A>()
+    }
+}
+
+/// `Nothing` is an action which produces no value. When run with an option
+/// scaffold, every action following it in an `and_then` chain is skipped.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Nothing<A>(PhantomData<fn() -> A>);
+pub fn none<A>() -> Nothing<A> {
+    Nothing(PhantomData)
+}
+impl<A> Mappable<A> for Nothing<A> {}
+impl<A> Sequential<A> for Nothing<A> {}
+impl<A> Action<A> for Nothing<A> {}
+impl<A, S> Run<S, A> for Nothing<A>
+where
+    S: BaseOption<A>,
+{
+    fn run(&self) -> S::R {
+        S::none()
+    }
+}
+
+/// `FromOption` lifts an `Option` into an action, so that the result of a
+/// lookup can be used in an `and_then` chain.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FromOption<A> {
+    value: Option<A>,
+}
+pub fn from_option<A>(value: Option<A>) -> FromOption<A> {
+    FromOption { value }
+}
+impl<A> From<Option<A>> for FromOption<A> {
+    fn from(value: Option<A>) -> Self {
+        from_option(value)
+    }
+}
+impl<A> Mappable<A> for FromOption<A> {}
+impl<A> Sequential<A> for FromOption<A> {}
+impl<A> Action<A> for FromOption<A> {}
+impl<A, S> Run<S, A> for FromOption<A>
+where
+    S: BaseOption<A>,
+    A: Clone,
+{
+    fn run(&self) -> S::R {
+        match &self.value {
+            Some(value) => S::build(value.clone()),
+            None => S::none(),
+        }
+    }
+}
+
+/// Run an action, and if it produces no value, run a fallback action in its
+/// place.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OrElse<ActA, ActB, F> {
+    act_a: ActA,
+    func: F,
+    _act_b: PhantomData<fn() -> ActB>,
+}
+pub fn or_else<A, ActA, ActB, F>(act_a: ActA, func: F) -> OrElse<ActA, ActB, F>
+where
+    ActA: Action<A>,
+    ActB: Action<A>,
+    F: Fn() -> ActB,
+{
+    OrElse {
+        act_a,
+        func,
+        _act_b: PhantomData,
+    }
+}
+impl<A, ActA, ActB, F> Mappable<A> for OrElse<ActA, ActB, F>
+where
+    ActA: Mappable<A>,
+    ActB: Mappable<A>,
+    F: Fn() -> ActB,
+{
+}
+impl<A, ActA, ActB, F> Sequential<A> for OrElse<ActA, ActB, F>
+where
+    ActA: Sequential<A>,
+    ActB: Sequential<A>,
+    F: Fn() -> ActB,
+{
+}
+impl<A, ActA, ActB, F> Action<A> for OrElse<ActA, ActB, F>
+where
+    ActA: Action<A>,
+    ActB: Action<A>,
+    F: Fn() -> ActB,
+{
+}
+impl<A, ActA, ActB, F, S> Run<BuildOption<S>, A> for OrElse<ActA, ActB, F>
+where
+    S: Bind<Option<A>, Option<A>>,
+    ActA: Run<BuildOption<S>, A>,
+    ActB: Run<BuildOption<S>, A>,
+    F: Fn() -> ActB,
+{
+    fn run(&self) -> <BuildOption<S> as Ap<A>>::R {
+        S::bind(self.act_a.run(), |result_a| match result_a {
+            Some(result_a) => S::build(Some(result_a)),
+            None => (self.func)().run(),
+        })
+    }
+}
+
+impl<A, B, ActA, F, S> Run<BuildOption<S>, B> for Map<A, ActA, F>
+where
+    S: Bind<Option<A>, Option<B>>,
+    ActA: Run<BuildOption<S>, A>,
+    F: MapFn<A, B>,
+{
+    fn run(&self) -> <BuildOption<S> as Ap<B>>::R {
+        S::bind(self.act_a.run(), |result_a| {
+            <S as Ap<Option<B>>>::build(result_a.map(|result_a| self.func.call(&result_a)))
+        })
+    }
+}
+
+impl<A, B, ActA, ActB, F, S> Run<BuildOption<S>, B> for AndThen<A, ActA, ActB, F>
+where
+    BuildOption<S>: Bind<A, B>,
+    ActA: Run<BuildOption<S>, A>,
+    ActB: Run<BuildOption<S>, B>,
+    F: Fn(&A) -> ActB,
+{
+    fn run(&self) -> <BuildOption<S> as Ap<B>>::R {
+        <BuildOption<S> as Bind<A, B>>::bind(self.act_a.run(), |result_a| {
+            (self.func)(&result_a).run()
+        })
+    }
+}
+
+impl<A, B, ActA, ActB, S> Run<BuildOption<S>, A> for First<B, ActA, ActB>
+where
+    BuildOption<S>: Zip<A, B> + Bind<(A, B), A>,
+    ActA: Run<BuildOption<S>, A>,
+    ActB: Run<BuildOption<S>, B>,
+{
+    fn run(&self) -> <BuildOption<S> as Ap<A>>::R {
+        self.run_bind::<BuildOption<S>, A>()
+    }
+}
+impl<A, B, ActA, ActB, S> Run<BuildOption<S>, B> for Second<A, ActA, ActB>
+where
+    BuildOption<S>: Zip<A, B> + Bind<(A, B), B>,
+    ActA: Run<BuildOption<S>, A>,
+    ActB: Run<BuildOption<S>, B>,
+{
+    fn run(&self) -> <BuildOption<S> as Ap<B>>::R {
+        self.run_bind::<BuildOption<S>, B>()
+    }
+}
+impl<A, B, C, ActA, ActB, F, S> Run<BuildOption<S>, C> for Combine<A, B, ActA, ActB, F>
+where
+    BuildOption<S>: Zip<A, B> + Bind<(A, B), C>,
+    ActA: Run<BuildOption<S>, A>,
+    ActB: Run<BuildOption<S>, B>,
+    F: Fn(&A, &B) -> C,
+{
+    fn run(&self) -> <BuildOption<S> as Ap<C>>::R {
+        self.run_bind::<BuildOption<S>, C>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{pure, writer};
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn lookups_chain() {
+        let scaffold = BuildOption(());
+        let mut ages = HashMap::new();
+        ages.insert("alice", 31);
+        ages.insert("bob", 27);
+
+        let action = from_option(ages.get("alice").copied())
+            .and_then(|&a| from_option(ages.get("bob").copied()).map(move |b| a + b));
+        let action2 = from_option(ages.get("alice").copied())
+            .and_then(|_| from_option(ages.get("carol").copied()))
+            .map(|c| c + 1);
+
+        assert_eq!(action.run_qualified(&scaffold), Some(58));
+        assert_eq!(action2.run_qualified(&scaffold), None);
+    }
+
+    #[test]
+    fn or_else_falls_back() {
+        let scaffold = BuildOption(());
+
+        let action = or_else(none::<i32>(), || pure(5));
+        let action2 = or_else(pure(3), || pure(5));
+        let action3 = pure(3).and_then(|_| or_else(none::<i32>(), none));
+
+        assert_eq!(action.run_qualified(&scaffold), Some(5));
+        assert_eq!(action2.run_qualified(&scaffold), Some(3));
+        assert_eq!(action3.run_qualified(&scaffold), None);
+    }
+
+    #[test]
+    fn nested_option() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let option_writer = BuildOption(BuildWriter(writer_type, ()));
+        let writer_option = BuildWriter(writer_type, BuildOption(()));
+
+        let action = writer(3, 10)
+            .and_then(|_| none::<i32>())
+            .and_then(|y| writer(y + 3, 5));
+
+        assert_eq!(action.run_qualified(&option_writer), (None, 10));
+        assert_eq!(action.run_qualified(&writer_option), None);
+    }
+}
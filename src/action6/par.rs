@@ -0,0 +1,249 @@
+use std::panic;
+use std::thread;
+
+use super::{
+    AndThen, Ap, Bind, CatchError, Censor, Choose, Combine, EitherAction, First, FromOption, Guard,
+    Listen, Literal, Map, MapFn, Nothing, OrElse, Pass, Pure, Run, Second, Sequence, TailRecM,
+    Tell, ThrowError, Writer, Zip,
+};
+
+/// Run an action, running the independent branches of `Sequence`, `Combine`,
+/// `First` and `Second` on separate threads.
+///
+/// The results are still combined with `Zip` in the order the actions were
+/// written, so a writer's logs are joined with `Semigroup::op` in the same
+/// order as `run` would join them, whichever thread finishes first. The right
+/// hand branch is sent to another thread, so it must be `Sync`, and its
+/// completed type must be `Send`.
+pub trait RunPar<S, A>: Run<S, A>
+where
+    S: Ap<A>,
+{
+    fn run_par(&self) -> S::R;
+    fn run_par_qualified(&self, _scaffold: &S) -> S::R {
+        self.run_par()
+    }
+}
+
+/// Run the right hand action on a scoped thread while the left hand action
+/// runs on this one.
+fn zip_par<S, A, B, ActA, ActB>(act_a: &ActA, act_b: &ActB) -> <S as Ap<(A, B)>>::R
+where
+    S: Zip<A, B>,
+    ActA: RunPar<S, A>,
+    ActB: RunPar<S, B> + Sync,
+    <S as Ap<B>>::R: Send,
+{
+    thread::scope(|scope| {
+        let handle = scope.spawn(|| act_b.run_par());
+        let value_a = act_a.run_par();
+        let value_b = handle
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload));
+        S::zip(value_a, value_b)
+    })
+}
+
+impl<S, A, B, ActA, ActB> RunPar<S, (A, B)> for Sequence<ActA, ActB>
+where
+    S: Zip<A, B>,
+    ActA: RunPar<S, A>,
+    ActB: RunPar<S, B> + Sync,
+    <S as Ap<B>>::R: Send,
+{
+    fn run_par(&self) -> <S as Ap<(A, B)>>::R {
+        zip_par(&self.0, &self.1)
+    }
+}
+
+impl<S, A, B, C, ActA, ActB, F> RunPar<S, C> for Combine<A, B, ActA, ActB, F>
+where
+    S: Zip<A, B> + Bind<(A, B), C>,
+    Self: Run<S, C>,
+    ActA: RunPar<S, A>,
+    ActB: RunPar<S, B> + Sync,
+    <S as Ap<B>>::R: Send,
+    F: Fn(&A, &B) -> C,
+{
+    fn run_par(&self) -> <S as Ap<C>>::R {
+        S::bind(zip_par(&self.act_a, &self.act_b), |(result_a, result_b)| {
+            <S as Ap<C>>::build((self.func)(&result_a, &result_b))
+        })
+    }
+}
+
+impl<S, A, B, ActA, ActB> RunPar<S, A> for First<B, ActA, ActB>
+where
+    S: Zip<A, B> + Bind<(A, B), A>,
+    Self: Run<S, A>,
+    ActA: RunPar<S, A>,
+    ActB: RunPar<S, B> + Sync,
+    <S as Ap<B>>::R: Send,
+{
+    fn run_par(&self) -> <S as Ap<A>>::R {
+        S::bind(zip_par(&self.act_a, &self.act_b), |(result_a, _)| {
+            <S as Ap<A>>::build(result_a)
+        })
+    }
+}
+
+impl<S, A, B, ActA, ActB> RunPar<S, B> for Second<A, ActA, ActB>
+where
+    S: Zip<A, B> + Bind<(A, B), B>,
+    Self: Run<S, B>,
+    ActA: RunPar<S, A>,
+    ActB: RunPar<S, B> + Sync,
+    <S as Ap<B>>::R: Send,
+{
+    fn run_par(&self) -> <S as Ap<B>>::R {
+        S::bind(zip_par(&self.act_a, &self.act_b), |(_, result_b)| {
+            <S as Ap<B>>::build(result_b)
+        })
+    }
+}
+
+impl<S, A, B, ActA, F> RunPar<S, B> for Map<A, ActA, F>
+where
+    S: Bind<A, B>,
+    Self: Run<S, B>,
+    ActA: RunPar<S, A>,
+    F: MapFn<A, B>,
+{
+    fn run_par(&self) -> <S as Ap<B>>::R {
+        S::bind(self.act_a.run_par(), |result_a| {
+            <S as Ap<B>>::build(self.func.call(&result_a))
+        })
+    }
+}
+
+impl<S, A, B, ActA, ActB, F> RunPar<S, B> for AndThen<A, ActA, ActB, F>
+where
+    S: Bind<A, B>,
+    Self: Run<S, B>,
+    ActA: RunPar<S, A>,
+    ActB: RunPar<S, B>,
+    F: Fn(&A) -> ActB,
+{
+    fn run_par(&self) -> <S as Ap<B>>::R {
+        S::bind(self.act_a.run_par(), |result_a| {
+            (self.func)(&result_a).run_par()
+        })
+    }
+}
+
+impl<S, A, L, R> RunPar<S, A> for EitherAction<L, R>
+where
+    S: Ap<A>,
+    L: RunPar<S, A>,
+    R: RunPar<S, A>,
+{
+    fn run_par(&self) -> S::R {
+        match self {
+            EitherAction::Left(act_l) => act_l.run_par(),
+            EitherAction::Right(act_r) => act_r.run_par(),
+        }
+    }
+}
+
+/// Actions with nothing independent inside them run just as `run` would.
+macro_rules! run_par_with_run {
+    ($(impl<$($param:ident),*> for $action:ty => $value:ty;)*) => {
+        $(
+            impl<S, $($param),*> RunPar<S, $value> for $action
+            where
+                S: Ap<$value>,
+                Self: Run<S, $value>,
+            {
+                fn run_par(&self) -> S::R {
+                    self.run()
+                }
+            }
+        )*
+    };
+}
+
+run_par_with_run! {
+    impl<A> for Pure<A> => A;
+    impl<A> for Literal<S, A> => A;
+    impl<A, W> for Writer<A, W> => A;
+    impl<W> for Tell<W> => ();
+    impl<A, W, ActA> for Listen<A, W, ActA> => (A, W);
+    impl<A, F, ActA> for Pass<F, ActA> => A;
+    impl<A, ActA, F> for Censor<ActA, F> => A;
+    impl<A, E> for ThrowError<A, E> => A;
+    impl<A, E, ActA, ActB, F> for CatchError<E, ActA, ActB, F> => A;
+    impl<A> for Nothing<A> => A;
+    impl<A> for FromOption<A> => A;
+    impl<A, ActA, ActB, F> for OrElse<ActA, ActB, F> => A;
+    impl<A> for Choose<A> => A;
+    impl<> for Guard => ();
+    impl<A, B, ActStep, F> for TailRecM<A, ActStep, F> => B;
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::monoid::Sum;
+    use super::super::{
+        pure, tell, throw_error, writer, Action, BuildError, BuildWriter, Mappable, Sequential,
+    };
+    use super::*;
+    use std::marker::PhantomData;
+    use std::time::Duration;
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    #[test]
+    fn branches_run_on_other_threads() {
+        let thread_id = || pure(()).map(|_| thread::current().id());
+        let action = thread_id().sequence(thread_id().sequence(thread_id()));
+
+        let (first, (second, third)) = action.run_par_qualified(&());
+
+        assert_eq!(first, thread::current().id());
+        assert_ne!(first, second);
+        assert_ne!(second, third);
+        assert_ne!(first, third);
+    }
+
+    #[test]
+    fn logs_merge_in_order() {
+        let writer_type: PhantomData<fn() -> Vec<&'static str>> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let slow = pure(10)
+            .map(|x| {
+                thread::sleep(Duration::from_millis(20));
+                x + 1
+            })
+            .and_then(|&x| writer(x, vec!["slow"]));
+        let fast = tell(vec!["fast"]).second(writer(5, vec!["done"]));
+        let action = slow.combine(fast, |x, y| x * y);
+
+        assert_send_sync(&action);
+        assert_eq!(
+            action.run_par_qualified(&scaffold),
+            (55, vec!["slow", "fast", "done"])
+        );
+        assert_eq!(
+            action.run_par_qualified(&scaffold),
+            action.run_qualified(&scaffold)
+        );
+    }
+
+    #[test]
+    fn errors_match_run() {
+        let writer_type: PhantomData<fn() -> Sum<u32>> = PhantomData;
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, BuildWriter(writer_type, ()));
+
+        let action = writer(3, Sum(1))
+            .first(throw_error::<u32, _>("failed").first(tell(Sum(2))))
+            .and_then(|&x| writer(x, Sum(100)));
+
+        assert_eq!(action.run_par_qualified(&scaffold), (Err("failed"), Sum(3)));
+        assert_eq!(
+            action.run_par_qualified(&scaffold),
+            action.run_qualified(&scaffold)
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use super::{
+    Action, AndThen, Ap, Literal, Map, MapFn, Mappable, Pure, Run, RunTraced, Sequential, Step,
+    TailRec, Tracer,
+};
+
+/// The scaffold for actions which read an environment of type `Env`.
+///
+/// As with `BuildState`, the completed type is a function waiting for the
+/// environment, so everything it captures must live for `'a`, and
+/// `BuildReader` can not be nested inside another scaffold. The closures in a
+/// chain may borrow local variables, as long as the completed `Reader` is run
+/// while they are borrowed.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuildReader<'a, Env>(pub PhantomData<fn() -> &'a Env>);
+
+/// A function of the environment, completed by `BuildReader`.
+pub struct Reader<'a, Env, A>(Rc<dyn Fn(&Env) -> A + 'a>);
+impl<Env, A> Reader<'_, Env, A> {
+    pub fn run_reader(&self, env: &Env) -> A {
+        (self.0)(env)
+    }
+}
+impl<Env, A> Clone for Reader<'_, Env, A> {
+    fn clone(&self) -> Self {
+        Reader(self.0.clone())
+    }
+}
+
+impl<'a, A, Env> Ap<A> for BuildReader<'a, Env>
+where
+    A: Clone + 'a,
+    Env: 'a,
+{
+    type R = Reader<'a, Env, A>;
+    fn build(value: A) -> Self::R {
+        Reader(Rc::new(move |_| value.clone()))
+    }
+}
+
+/// Run an action with the environment supplied at run time.
+pub trait RunWithEnv<'a, Env, A>: Run<BuildReader<'a, Env>, A>
+where
+    A: Clone + 'a,
+    Env: 'a,
+{
+    fn run_with_env(&self, env: &Env) -> A {
+        self.run().run_reader(env)
+    }
+}
+impl<'a, Env, A, Act> RunWithEnv<'a, Env, A> for Act
+where
+    A: Clone + 'a,
+    Env: 'a,
+    Act: Run<BuildReader<'a, Env>, A>,
+{
+}
+
+/// `Ask` is an action which returns the environment.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ask<Env>(PhantomData<fn() -> Env>);
+pub fn ask<Env>() -> Ask<Env> {
+    Ask(PhantomData)
+}
+impl<Env> Mappable<Env> for Ask<Env> {}
+impl<Env> Sequential<Env> for Ask<Env> {}
+impl<Env> Action<Env> for Ask<Env> {}
+impl<'a, Env> Run<BuildReader<'a, Env>, Env> for Ask<Env>
+where
+    Env: Clone + 'a,
+{
+    fn run(&self) -> Reader<'a, Env, Env> {
+        Reader(Rc::new(|env: &Env| env.clone()))
+    }
+}
+
+/// `Asks` is an action which returns a function of the environment.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Asks<Env, F> {
+    func: F,
+    _env_type: PhantomData<fn() -> Env>,
+}
+pub fn asks<A, Env, F>(func: F) -> Asks<Env, F>
+where
+    F: Fn(&Env) -> A,
+{
+    Asks {
+        func,
+        _env_type: PhantomData,
+    }
+}
+impl<A, Env, F> Mappable<A> for Asks<Env, F> where F: Fn(&Env) -> A {}
+impl<A, Env, F> Sequential<A> for Asks<Env, F> where F: Fn(&Env) -> A {}
+impl<A, Env, F> Action<A> for Asks<Env, F> where F: Fn(&Env) -> A {}
+impl<'a, A, Env, F> Run<BuildReader<'a, Env>, A> for Asks<Env, F>
+where
+    A: Clone + 'a,
+    F: Fn(&Env) -> A + Clone + 'a,
+{
+    fn run(&self) -> Reader<'a, Env, A> {
+        let func = self.func.clone();
+        Reader(Rc::new(move |env| func(env)))
+    }
+}
+
+/// `Local` runs an action with a modified copy of the environment.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Local<Env, ActA, F> {
+    act_a: ActA,
+    func: F,
+    _env_type: PhantomData<fn() -> Env>,
+}
+pub fn local<A, Env, ActA, F>(func: F, act_a: ActA) -> Local<Env, ActA, F>
+where
+    ActA: Action<A>,
+    F: Fn(&Env) -> Env,
+{
+    Local {
+        act_a,
+        func,
+        _env_type: PhantomData,
+    }
+}
+impl<A, Env, ActA, F> Mappable<A> for Local<Env, ActA, F>
+where
+    ActA: Mappable<A>,
+    F: Fn(&Env) -> Env,
+{
+}
+impl<A, Env, ActA, F> Sequential<A> for Local<Env, ActA, F>
+where
+    ActA: Sequential<A>,
+    F: Fn(&Env) -> Env,
+{
+}
+impl<A, Env, ActA, F> Action<A> for Local<Env, ActA, F>
+where
+    ActA: Action<A>,
+    F: Fn(&Env) -> Env,
+{
+}
+impl<'a, A, Env, ActA, F> Run<BuildReader<'a, Env>, A> for Local<Env, ActA, F>
+where
+    A: Clone + 'a,
+    Env: 'a,
+    ActA: Run<BuildReader<'a, Env>, A>,
+    F: Fn(&Env) -> Env + Clone + 'a,
+{
+    fn run(&self) -> Reader<'a, Env, A> {
+        let reader_a = self.act_a.run();
+        let func = self.func.clone();
+        Reader(Rc::new(move |env| reader_a.run_reader(&func(env))))
+    }
+}
+
+impl<'a, A, B, ActA, F, Env> Run<BuildReader<'a, Env>, B> for Map<A, ActA, F>
+where
+    A: Clone + 'a,
+    B: Clone + 'a,
+    Env: 'a,
+    ActA: Run<BuildReader<'a, Env>, A>,
+    F: MapFn<A, B> + Clone + 'a,
+{
+    fn run(&self) -> Reader<'a, Env, B> {
+        let reader_a = self.act_a.run();
+        let func = self.func.clone();
+        Reader(Rc::new(move |env| func.call(&reader_a.run_reader(env))))
+    }
+}
+
+impl<'a, A, B, ActA, ActB, F, Env> Run<BuildReader<'a, Env>, B> for AndThen<A, ActA, ActB, F>
+where
+    A: Clone + 'a,
+    B: Clone + 'a,
+    Env: 'a,
+    ActA: Run<BuildReader<'a, Env>, A>,
+    ActB: Run<BuildReader<'a, Env>, B>,
+    F: Fn(&A) -> ActB + Clone + 'a,
+{
+    fn run(&self) -> Reader<'a, Env, B> {
+        let reader_a = self.act_a.run();
+        let func = self.func.clone();
+        Reader(Rc::new(move |env| {
+            func(&reader_a.run_reader(env)).run().run_reader(env)
+        }))
+    }
+}
+
+impl<'a, A, B, Env> TailRec<A, B> for BuildReader<'a, Env>
+where
+    A: Clone + 'a,
+    B: Clone + 'a,
+    Env: 'a,
+{
+    fn tail_rec<F>(init: A, func: F) -> Reader<'a, Env, B>
+    where
+        F: Fn(A) -> Reader<'a, Env, Step<A, B>> + 'static,
+    {
+        Reader(Rc::new(move |env| {
+            let mut value = init.clone();
+            loop {
+                match func(value).run_reader(env) {
+                    Step::Loop(next) => value = next,
+                    Step::Done(result) => return result,
+                }
+            }
+        }))
+    }
+}
+
+impl<'a, Env, A> Reader<'a, Env, A>
+where
+    Env: 'a,
+    A: 'a,
+{
+    /// Run inside a span for the step `Act`, each time the function is
+    /// called.
+    fn traced<'t, Act>(self, tracer: &Rc<Tracer<'t>>, name: &'static str) -> Self
+    where
+        't: 'a,
+    {
+        let tracer = tracer.clone();
+        Reader(Rc::new(move |env| {
+            tracer.span::<Act, _, _>(name, || self.run_reader(env))
+        }))
+    }
+}
+
+impl<'t, 'a, A, Env> RunTraced<'t, BuildReader<'a, Env>, A> for Pure<A>
+where
+    't: 'a,
+    A: Clone + 'a,
+    Env: 'a,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> Reader<'a, Env, A> {
+        <BuildReader<'a, Env> as Ap<A>>::build(self.value.clone()).traced::<Self>(tracer, "Pure")
+    }
+}
+
+impl<'t, 'a, A, Env> RunTraced<'t, BuildReader<'a, Env>, A> for Literal<BuildReader<'a, Env>, A>
+where
+    't: 'a,
+    A: Clone + 'a,
+    Env: 'a,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> Reader<'a, Env, A> {
+        self.value.clone().traced::<Self>(tracer, "Literal")
+    }
+}
+
+impl<'t, 'a, A, B, ActA, F, Env> RunTraced<'t, BuildReader<'a, Env>, B> for Map<A, ActA, F>
+where
+    't: 'a,
+    A: Clone + 'a,
+    B: Clone + 'a,
+    Env: 'a,
+    ActA: RunTraced<'t, BuildReader<'a, Env>, A>,
+    F: MapFn<A, B> + Clone + 'a,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> Reader<'a, Env, B> {
+        let reader_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        Reader(Rc::new(move |env| func.call(&reader_a.run_reader(env))))
+            .traced::<Self>(tracer, "Map")
+    }
+}
+
+impl<'t, 'a, A, B, ActA, ActB, F, Env> RunTraced<'t, BuildReader<'a, Env>, B>
+    for AndThen<A, ActA, ActB, F>
+where
+    't: 'a,
+    A: Clone + 'a,
+    B: Clone + 'a,
+    Env: 'a,
+    ActA: RunTraced<'t, BuildReader<'a, Env>, A>,
+    ActB: RunTraced<'t, BuildReader<'a, Env>, B>,
+    F: Fn(&A) -> ActB + Clone + 'a,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> Reader<'a, Env, B> {
+        let reader_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        let tracer_b = tracer.clone();
+        Reader(Rc::new(move |env| {
+            func(&reader_a.run_reader(env))
+                .trace(&tracer_b)
+                .run_reader(env)
+        }))
+        .traced::<Self>(tracer, "AndThen")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{pure, tail_rec_m};
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Config {
+        verbose: bool,
+        depth: u32,
+    }
+
+    #[test]
+    fn pipeline_reads_environment() {
+        let config = Config {
+            verbose: true,
+            depth: 3,
+        };
+
+        let action = pure(2)
+            .and_then(|&x| asks(move |config: &Config| x * config.depth))
+            .and_then(|&y| ask().map(move |config: &Config| (y, config.verbose)));
+
+        assert_eq!(action.run_with_env(&config), (6, true));
+    }
+
+    #[test]
+    fn local_modifies_environment() {
+        let config = Config {
+            verbose: false,
+            depth: 3,
+        };
+
+        let deeper = |config: &Config| Config {
+            depth: config.depth + 1,
+            ..config.clone()
+        };
+        let action = local(deeper, asks(|config: &Config| config.depth))
+            .and_then(|&x| asks(move |config: &Config| (x, config.depth)));
+
+        assert_eq!(action.run_with_env(&config), (4, 3));
+    }
+
+    #[test]
+    fn closures_borrow_locals() {
+        let config = Config {
+            verbose: true,
+            depth: 2,
+        };
+        let names = ["quiet".to_string(), "verbose".to_string()];
+        let labels = &names;
+        let scale = 10;
+
+        let action = asks(|config: &Config| config.depth * scale).and_then(|&x| {
+            asks(move |config: &Config| (x, labels[usize::from(config.verbose)].as_str()))
+        });
+
+        assert_eq!(action.run_with_env(&config), (20, "verbose"));
+    }
+
+    #[test]
+    fn million_steps() {
+        let config = Config {
+            verbose: false,
+            depth: 1_000_000,
+        };
+
+        let action = tail_rec_m(0, |&n: &u32| {
+            asks(move |config: &Config| {
+                if n < config.depth {
+                    Step::Loop(n + 1)
+                } else {
+                    Step::Done(n)
+                }
+            })
+        });
+
+        assert_eq!(action.run_with_env(&config), 1_000_000);
+    }
+}
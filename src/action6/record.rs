@@ -0,0 +1,556 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use super::{
+    AndThen, Ap, BaseError, BaseWriter, CatchError, Get, Gets, Literal, Map, MapFn, Modify, Pure,
+    Put, Run, RunTraced, Step, TailRec, Tracer,
+};
+
+/// The scaffold for running actions in unit tests. Instead of producing a
+/// log or a state, every effect is recorded as an `Event`, so that a test can
+/// check what an action did as well as what it produced.
+///
+/// The completed type is a function, waiting for a `Recorder`, so everything
+/// it captures must be `'static`, and `BuildRecord` can not be nested inside
+/// another scaffold.
+///
+/// `catch_error` does not roll back the recorder, so any `Put` in the branch
+/// that failed still changes the state seen by the handler, and its events
+/// stay in the log. This differs from layering `BuildError` over a state
+/// scaffold, where the failed branch's state is thrown away with the error.
+///
+/// `BuildRecord` is only compiled for this crate's tests, or with the
+/// `testing` feature, so that other crates can use it in theirs.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuildRecord<W, E, St = ()>(pub PhantomData<Events<W, E, St>>);
+type Events<W, E, St> = fn() -> Vec<Event<W, E, St>>;
+
+/// An effect performed by an action run with `BuildRecord`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Event<W, E, St> {
+    /// A log was written, by `tell` or `writer`.
+    Tell(W),
+    /// An error was thrown.
+    Throw(E),
+    /// An error was handled by `catch_error`.
+    Catch(E),
+    /// The state was read, producing this value.
+    Get(St),
+    /// The state was replaced with this value.
+    Put(St),
+}
+
+/// The events recorded so far, and the state that reads and writes use.
+///
+/// A scripted recorder answers each read with the next of its canned
+/// responses, whatever has been written, so that a test can mock the state an
+/// action sees. Once the script runs out, reads see the state again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recorder<W, E, St> {
+    events: Vec<Event<W, E, St>>,
+    state: St,
+    script: VecDeque<St>,
+}
+impl<W, E, St> Recorder<W, E, St> {
+    pub fn new(state: St) -> Self {
+        Self::scripted(state, None)
+    }
+
+    pub fn scripted<I>(state: St, responses: I) -> Self
+    where
+        I: IntoIterator<Item = St>,
+    {
+        Recorder {
+            events: Vec::new(),
+            state,
+            script: responses.into_iter().collect(),
+        }
+    }
+
+    pub fn events(&self) -> &[Event<W, E, St>] {
+        &self.events
+    }
+
+    pub fn into_events(self) -> Vec<Event<W, E, St>> {
+        self.events
+    }
+
+    pub fn state(&self) -> &St {
+        &self.state
+    }
+
+    /// The responses which haven't been used by a read yet.
+    pub fn remaining_script(&self) -> usize {
+        self.script.len()
+    }
+
+    fn read(&mut self) -> St
+    where
+        St: Clone,
+    {
+        let state = self
+            .script
+            .pop_front()
+            .unwrap_or_else(|| self.state.clone());
+        self.events.push(Event::Get(state.clone()));
+        state
+    }
+
+    fn write(&mut self, state: St)
+    where
+        St: Clone,
+    {
+        self.events.push(Event::Put(state.clone()));
+        self.state = state;
+    }
+}
+
+type RecordFn<W, E, St, A> = dyn Fn(&mut Recorder<W, E, St>) -> Result<A, E>;
+
+/// The result of an action together with every event it recorded.
+pub type Recorded<W, E, St, A> = (Result<A, E>, Vec<Event<W, E, St>>);
+
+/// A recording function, completed by `BuildRecord`.
+pub struct Recording<W, E, St, A>(Rc<RecordFn<W, E, St, A>>);
+impl<W, E, St, A> Recording<W, E, St, A> {
+    pub fn run_recorded(&self, recorder: &mut Recorder<W, E, St>) -> Result<A, E> {
+        (self.0)(recorder)
+    }
+
+    /// Run with a fresh `Recorder`, returning the result and every event.
+    pub fn record(&self, state: St) -> Recorded<W, E, St, A> {
+        let mut recorder = Recorder::new(state);
+        let result = self.run_recorded(&mut recorder);
+        (result, recorder.into_events())
+    }
+}
+impl<W, E, St, A> Clone for Recording<W, E, St, A> {
+    fn clone(&self) -> Self {
+        Recording(self.0.clone())
+    }
+}
+
+impl<A, W, E, St> Ap<A> for BuildRecord<W, E, St>
+where
+    A: Clone + 'static,
+    W: 'static,
+    E: 'static,
+    St: 'static,
+{
+    type R = Recording<W, E, St, A>;
+    fn build(value: A) -> Self::R {
+        Recording(Rc::new(move |_| Ok(value.clone())))
+    }
+}
+
+impl<A, W, E, St> BaseWriter<A, W> for BuildRecord<W, E, St>
+where
+    A: Clone + 'static,
+    W: Clone + 'static,
+    E: 'static,
+    St: 'static,
+{
+    fn writer(value: A, log: W) -> Self::R {
+        Recording(Rc::new(move |recorder| {
+            recorder.events.push(Event::Tell(log.clone()));
+            Ok(value.clone())
+        }))
+    }
+}
+
+impl<A, W, E, St> BaseError<A, E> for BuildRecord<W, E, St>
+where
+    A: Clone + 'static,
+    W: 'static,
+    E: Clone + 'static,
+    St: 'static,
+{
+    fn throw_error(error: E) -> Self::R {
+        Recording(Rc::new(move |recorder| {
+            recorder.events.push(Event::Throw(error.clone()));
+            Err(error.clone())
+        }))
+    }
+}
+
+impl<W, E, St> Run<BuildRecord<W, E, St>, St> for Get<St>
+where
+    W: 'static,
+    E: 'static,
+    St: Clone + 'static,
+{
+    fn run(&self) -> Recording<W, E, St, St> {
+        Recording(Rc::new(|recorder| Ok(recorder.read())))
+    }
+}
+
+impl<W, E, St> Run<BuildRecord<W, E, St>, ()> for Put<St>
+where
+    W: 'static,
+    E: 'static,
+    St: Clone + 'static,
+{
+    fn run(&self) -> Recording<W, E, St, ()> {
+        let state = self.state.clone();
+        Recording(Rc::new(move |recorder| {
+            recorder.write(state.clone());
+            Ok(())
+        }))
+    }
+}
+
+/// Recorded as a read followed by a write.
+impl<W, E, St, F> Run<BuildRecord<W, E, St>, ()> for Modify<St, F>
+where
+    W: 'static,
+    E: 'static,
+    St: Clone + 'static,
+    F: Fn(&St) -> St + Clone + 'static,
+{
+    fn run(&self) -> Recording<W, E, St, ()> {
+        let func = self.func.clone();
+        Recording(Rc::new(move |recorder| {
+            let state = recorder.read();
+            recorder.write(func(&state));
+            Ok(())
+        }))
+    }
+}
+
+impl<A, W, E, St, F> Run<BuildRecord<W, E, St>, A> for Gets<St, F>
+where
+    A: Clone + 'static,
+    W: 'static,
+    E: 'static,
+    St: Clone + 'static,
+    F: Fn(&St) -> A + Clone + 'static,
+{
+    fn run(&self) -> Recording<W, E, St, A> {
+        let func = self.func.clone();
+        Recording(Rc::new(move |recorder| Ok(func(&recorder.read()))))
+    }
+}
+
+impl<A, W, E, St, ActA, ActB, F> Run<BuildRecord<W, E, St>, A> for CatchError<E, ActA, ActB, F>
+where
+    A: Clone + 'static,
+    W: 'static,
+    E: Clone + 'static,
+    St: 'static,
+    ActA: Run<BuildRecord<W, E, St>, A>,
+    ActB: Run<BuildRecord<W, E, St>, A>,
+    F: Fn(&E) -> ActB + Clone + 'static,
+{
+    fn run(&self) -> Recording<W, E, St, A> {
+        let recording_a = self.act_a.run();
+        let func = self.func.clone();
+        Recording(Rc::new(move |recorder| {
+            recording_a.run_recorded(recorder).or_else(|err_a| {
+                recorder.events.push(Event::Catch(err_a.clone()));
+                func(&err_a).run().run_recorded(recorder)
+            })
+        }))
+    }
+}
+
+impl<A, B, W, E, St, ActA, F> Run<BuildRecord<W, E, St>, B> for Map<A, ActA, F>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    W: 'static,
+    E: 'static,
+    St: 'static,
+    ActA: Run<BuildRecord<W, E, St>, A>,
+    F: MapFn<A, B> + Clone + 'static,
+{
+    fn run(&self) -> Recording<W, E, St, B> {
+        let recording_a = self.act_a.run();
+        let func = self.func.clone();
+        Recording(Rc::new(move |recorder| {
+            recording_a
+                .run_recorded(recorder)
+                .map(|result_a| func.call(&result_a))
+        }))
+    }
+}
+
+impl<A, B, W, E, St, ActA, ActB, F> Run<BuildRecord<W, E, St>, B> for AndThen<A, ActA, ActB, F>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    W: 'static,
+    E: 'static,
+    St: 'static,
+    ActA: Run<BuildRecord<W, E, St>, A>,
+    ActB: Run<BuildRecord<W, E, St>, B>,
+    F: Fn(&A) -> ActB + Clone + 'static,
+{
+    fn run(&self) -> Recording<W, E, St, B> {
+        let recording_a = self.act_a.run();
+        let func = self.func.clone();
+        Recording(Rc::new(move |recorder| {
+            let result_a = recording_a.run_recorded(recorder)?;
+            func(&result_a).run().run_recorded(recorder)
+        }))
+    }
+}
+
+impl<A, B, W, E, St> TailRec<A, B> for BuildRecord<W, E, St>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    W: 'static,
+    E: 'static,
+    St: 'static,
+{
+    fn tail_rec<F>(init: A, func: F) -> Recording<W, E, St, B>
+    where
+        F: Fn(A) -> Recording<W, E, St, Step<A, B>> + 'static,
+    {
+        Recording(Rc::new(move |recorder| {
+            let mut value = init.clone();
+            loop {
+                match func(value).run_recorded(recorder)? {
+                    Step::Loop(next) => value = next,
+                    Step::Done(result) => return Ok(result),
+                }
+            }
+        }))
+    }
+}
+
+impl<W, E, St, A> Recording<W, E, St, A>
+where
+    W: 'static,
+    E: 'static,
+    St: 'static,
+    A: 'static,
+{
+    /// Run inside a span for the step `Act`, each time the function is
+    /// called.
+    fn traced<Act>(self, tracer: &Rc<Tracer<'static>>, name: &'static str) -> Self {
+        let tracer = tracer.clone();
+        Recording(Rc::new(move |recorder| {
+            tracer.span::<Act, _, _>(name, || self.run_recorded(recorder))
+        }))
+    }
+}
+
+impl<A, W, E, St> RunTraced<'static, BuildRecord<W, E, St>, A> for Pure<A>
+where
+    A: Clone + 'static,
+    W: 'static,
+    E: 'static,
+    St: 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> Recording<W, E, St, A> {
+        <BuildRecord<W, E, St> as Ap<A>>::build(self.value.clone()).traced::<Self>(tracer, "Pure")
+    }
+}
+
+impl<A, W, E, St> RunTraced<'static, BuildRecord<W, E, St>, A> for Literal<BuildRecord<W, E, St>, A>
+where
+    A: Clone + 'static,
+    W: 'static,
+    E: 'static,
+    St: 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> Recording<W, E, St, A> {
+        self.value.clone().traced::<Self>(tracer, "Literal")
+    }
+}
+
+impl<A, B, W, E, St, ActA, F> RunTraced<'static, BuildRecord<W, E, St>, B> for Map<A, ActA, F>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    W: 'static,
+    E: 'static,
+    St: 'static,
+    ActA: RunTraced<'static, BuildRecord<W, E, St>, A>,
+    F: MapFn<A, B> + Clone + 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> Recording<W, E, St, B> {
+        let recording_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        Recording(Rc::new(move |recorder| {
+            recording_a
+                .run_recorded(recorder)
+                .map(|result_a| func.call(&result_a))
+        }))
+        .traced::<Self>(tracer, "Map")
+    }
+}
+
+impl<A, B, W, E, St, ActA, ActB, F> RunTraced<'static, BuildRecord<W, E, St>, B>
+    for AndThen<A, ActA, ActB, F>
+where
+    A: Clone + 'static,
+    B: Clone + 'static,
+    W: 'static,
+    E: 'static,
+    St: 'static,
+    ActA: RunTraced<'static, BuildRecord<W, E, St>, A>,
+    ActB: RunTraced<'static, BuildRecord<W, E, St>, B>,
+    F: Fn(&A) -> ActB + Clone + 'static,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'static>>) -> Recording<W, E, St, B> {
+        let recording_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        let tracer_b = tracer.clone();
+        Recording(Rc::new(move |recorder| {
+            let result_a = recording_a.run_recorded(recorder)?;
+            func(&result_a).trace(&tracer_b).run_recorded(recorder)
+        }))
+        .traced::<Self>(tracer, "AndThen")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        catch_error, get, gets, modify, pure, put, tail_rec_m, tell, throw_error, writer, Action,
+        EitherAction, Mappable,
+    };
+    use super::*;
+
+    type Scaffold = BuildRecord<&'static str, &'static str, u32>;
+
+    fn withdraw(amount: u32) -> impl Run<Scaffold, u32> {
+        get()
+            .and_then(move |&balance| {
+                if balance < amount {
+                    EitherAction::Left(throw_error("insufficient funds"))
+                } else {
+                    EitherAction::Right(pure(balance - amount))
+                }
+            })
+            .and_then(|&left| put(left).and_then(move |_| writer(left, "withdrew")))
+    }
+
+    #[test]
+    fn effects_are_recorded_in_order() {
+        let scaffold: Scaffold = BuildRecord(PhantomData);
+
+        let (result, events) = withdraw(30).run_qualified(&scaffold).record(100);
+
+        assert_eq!(result, Ok(70));
+        assert_eq!(
+            events,
+            vec![Event::Get(100), Event::Put(70), Event::Tell("withdrew")]
+        );
+    }
+
+    #[test]
+    fn errors_stop_the_recording() {
+        let scaffold: Scaffold = BuildRecord(PhantomData);
+
+        let (result, events) = withdraw(30).run_qualified(&scaffold).record(10);
+
+        assert_eq!(result, Err("insufficient funds"));
+        assert_eq!(
+            events,
+            vec![Event::Get(10), Event::Throw("insufficient funds")]
+        );
+    }
+
+    #[test]
+    fn scripted_reads() {
+        let scaffold: Scaffold = BuildRecord(PhantomData);
+        let mut recorder = Recorder::scripted(0, vec![100, 50]);
+
+        let action = withdraw(30)
+            .and_then(|_| withdraw(30))
+            .and_then(|_| withdraw(30));
+        let result = action.run_qualified(&scaffold).run_recorded(&mut recorder);
+
+        assert_eq!(result, Err("insufficient funds"));
+        assert_eq!(recorder.remaining_script(), 0);
+        assert_eq!(recorder.state(), &20);
+        assert_eq!(
+            recorder.events(),
+            &[
+                Event::Get(100),
+                Event::Put(70),
+                Event::Tell("withdrew"),
+                Event::Get(50),
+                Event::Put(20),
+                Event::Tell("withdrew"),
+                Event::Get(20),
+                Event::Throw("insufficient funds"),
+            ]
+        );
+    }
+
+    #[test]
+    fn caught_errors() {
+        let scaffold: Scaffold = BuildRecord(PhantomData);
+
+        let action = catch_error(withdraw(30), |_: &&str| {
+            tell("retried").and_then(|_| gets(|&balance: &u32| balance))
+        })
+        .and_then(|&x| modify(move |balance: &u32| balance + x));
+
+        assert_eq!(
+            action.run_qualified(&scaffold).record(10),
+            (
+                Ok(()),
+                vec![
+                    Event::Get(10),
+                    Event::Throw("insufficient funds"),
+                    Event::Catch("insufficient funds"),
+                    Event::Tell("retried"),
+                    Event::Get(10),
+                    Event::Get(10),
+                    Event::Put(20),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn caught_errors_keep_writes() {
+        let scaffold: Scaffold = BuildRecord(PhantomData);
+
+        let action = catch_error(
+            put(5).and_then(|_| throw_error::<(), &str>("failed")),
+            |_: &&str| get().map(|_| ()),
+        );
+
+        assert_eq!(
+            action.run_qualified(&scaffold).record(10),
+            (
+                Ok(()),
+                vec![
+                    Event::Put(5),
+                    Event::Throw("failed"),
+                    Event::Catch("failed"),
+                    Event::Get(5),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn loops_and_pure_chains() {
+        let scaffold: Scaffold = BuildRecord(PhantomData);
+
+        let action = pure(3).and_then(|x| pure(x + 2)).map(|y| y * 2);
+        let counted = tail_rec_m(0, |&n: &u32| {
+            tell("step").map(move |_| {
+                if n < 2 {
+                    Step::Loop(n + 1)
+                } else {
+                    Step::Done(n)
+                }
+            })
+        });
+
+        assert_eq!(action.run_qualified(&scaffold).record(0), (Ok(10), vec![]));
+        assert_eq!(
+            counted.run_qualified(&scaffold).record(0),
+            (Ok(2), vec![Event::Tell("step"); 3])
+        );
+    }
+}
@@ -0,0 +1,470 @@
+use std::marker::PhantomData;
+
+use super::{
+    Action, AndThen, Ap, Ask, Asks, BoxAction, CallCc, CatchError, Censor, Choose, Combine,
+    EitherAction, First, FoldM, ForEach, FromFuture, FromOption, Get, Gets, Guard, Jump, Listen,
+    Literal, Local, Map, MapFn, Memo, Modify, Nothing, OrElse, Pass, Pure, Put, RcAction,
+    ReplicateM, Second, Sequence, SequenceVec, TailRecM, Tell, ThrowError, Traverse, WhileM,
+    Writer,
+};
+
+/// Two functions run one after the other, which is the function of a `Map`
+/// made by fusing two `Map`s.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Compose<F, G, B> {
+    first: F,
+    second: G,
+    _middle_type: PhantomData<fn() -> B>,
+}
+impl<A, B, C, F, G> MapFn<A, C> for Compose<F, G, B>
+where
+    F: MapFn<A, B>,
+    G: MapFn<B, C>,
+{
+    fn call(&self, value: &A) -> C {
+        self.second.call(&self.first.call(value))
+    }
+}
+
+/// Rewrite an action using the functor and monad laws, so that it does less
+/// work when it is run. `act.map(f).map(g)` becomes a single `Map` of the
+/// composed function, and `pure(x).and_then(f)` becomes `f(&x)`.
+///
+/// The laws hold for every scaffold, so the simplified action gives the same
+/// result as the original with any scaffold which can run both. Actions made
+/// by a continuation when the action runs can't be simplified ahead of time,
+/// so only the first action of an `AndThen` is looked inside.
+pub trait Simplify<A>: Sized {
+    type Output: Action<A>;
+    fn simplify(self) -> Self::Output;
+}
+
+/// Map over an action which has already been simplified, fusing the function
+/// into the action if it is a `Map` itself.
+pub trait FuseMap<A, B, G>: Sized {
+    type Output: Action<B>;
+    fn fuse_map(self, func: G) -> Self::Output;
+}
+
+/// Bind an action which has already been simplified, applying the
+/// continuation straight away if the action is `Pure`.
+pub trait FuseAndThen<A, B, ActB, F>: Sized {
+    type Output: Action<B>;
+    fn fuse_and_then(self, func: F) -> Self::Output;
+}
+
+impl<A, B, ActA, F> Simplify<B> for Map<A, ActA, F>
+where
+    ActA: Simplify<A>,
+    ActA::Output: FuseMap<A, B, F>,
+{
+    type Output = <ActA::Output as FuseMap<A, B, F>>::Output;
+    fn simplify(self) -> Self::Output {
+        self.act_a.simplify().fuse_map(self.func)
+    }
+}
+
+/// `act.map(f).map(g)` is `act.map(|x| g(&f(x)))`.
+impl<A, B, C, ActA, F, G> FuseMap<B, C, G> for Map<A, ActA, F>
+where
+    ActA: Action<A>,
+    F: MapFn<A, B>,
+    G: MapFn<B, C>,
+{
+    type Output = Map<A, ActA, Compose<F, G, B>>;
+    fn fuse_map(self, func: G) -> Self::Output {
+        Map {
+            act_a: self.act_a,
+            func: Compose {
+                first: self.func,
+                second: func,
+                _middle_type: PhantomData,
+            },
+            _act_a_type: PhantomData,
+        }
+    }
+}
+
+impl<A, B, ActA, ActB, F> Simplify<B> for AndThen<A, ActA, ActB, F>
+where
+    ActA: Simplify<A>,
+    ActA::Output: FuseAndThen<A, B, ActB, F>,
+{
+    type Output = <ActA::Output as FuseAndThen<A, B, ActB, F>>::Output;
+    fn simplify(self) -> Self::Output {
+        self.act_a.simplify().fuse_and_then(self.func)
+    }
+}
+
+/// `pure(x).and_then(f)` is `f(&x)`, which may simplify further.
+impl<A, B, ActB, F> FuseAndThen<A, B, ActB, F> for Pure<A>
+where
+    ActB: Simplify<B>,
+    F: Fn(&A) -> ActB,
+{
+    type Output = ActB::Output;
+    fn fuse_and_then(self, func: F) -> Self::Output {
+        func(&self.value).simplify()
+    }
+}
+
+impl<A, B, ActA, ActB> Simplify<(A, B)> for Sequence<ActA, ActB>
+where
+    ActA: Simplify<A>,
+    ActB: Simplify<B>,
+{
+    type Output = Sequence<ActA::Output, ActB::Output>;
+    fn simplify(self) -> Self::Output {
+        Sequence(self.0.simplify(), self.1.simplify())
+    }
+}
+
+impl<A, B, C, ActA, ActB, F> Simplify<C> for Combine<A, B, ActA, ActB, F>
+where
+    ActA: Simplify<A>,
+    ActB: Simplify<B>,
+    F: Fn(&A, &B) -> C,
+{
+    type Output = Combine<A, B, ActA::Output, ActB::Output, F>;
+    fn simplify(self) -> Self::Output {
+        Combine {
+            act_a: self.act_a.simplify(),
+            act_b: self.act_b.simplify(),
+            func: self.func,
+            _act_a_type: PhantomData,
+            _act_b_type: PhantomData,
+        }
+    }
+}
+
+impl<A, B, ActA, ActB> Simplify<A> for First<B, ActA, ActB>
+where
+    ActA: Simplify<A>,
+    ActB: Simplify<B>,
+{
+    type Output = First<B, ActA::Output, ActB::Output>;
+    fn simplify(self) -> Self::Output {
+        First {
+            act_a: self.act_a.simplify(),
+            act_b: self.act_b.simplify(),
+            _act_b_type: PhantomData,
+        }
+    }
+}
+
+impl<A, B, ActA, ActB> Simplify<B> for Second<A, ActA, ActB>
+where
+    ActA: Simplify<A>,
+    ActB: Simplify<B>,
+{
+    type Output = Second<A, ActA::Output, ActB::Output>;
+    fn simplify(self) -> Self::Output {
+        Second {
+            act_a: self.act_a.simplify(),
+            act_b: self.act_b.simplify(),
+            _act_a_type: PhantomData,
+        }
+    }
+}
+
+impl<A, L, R> Simplify<A> for EitherAction<L, R>
+where
+    L: Simplify<A>,
+    R: Simplify<A>,
+{
+    type Output = EitherAction<L::Output, R::Output>;
+    fn simplify(self) -> Self::Output {
+        match self {
+            EitherAction::Left(act_l) => EitherAction::Left(act_l.simplify()),
+            EitherAction::Right(act_r) => EitherAction::Right(act_r.simplify()),
+        }
+    }
+}
+
+/// Actions which no law applies to are left as they are.
+macro_rules! simplify_as_is {
+    ($(impl<$($param:ident),*> for $action:ty $(where $bound:ty: $trait:path)?;)*) => {
+        $(
+            impl<Out, $($param),*> Simplify<Out> for $action
+            where
+                Self: Action<Out>,
+                $($bound: $trait,)?
+            {
+                type Output = Self;
+                fn simplify(self) -> Self {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+/// Mapping over an action which isn't a `Map` can't be fused.
+macro_rules! fuse_map_as_map {
+    ($(impl<$($param:ident),*> for $action:ty $(where $bound:ty: $trait:path)?;)*) => {
+        $(
+            impl<Out, MapOut, MapFunc, $($param),*> FuseMap<Out, MapOut, MapFunc> for $action
+            where
+                Self: Action<Out>,
+                MapFunc: MapFn<Out, MapOut>,
+                $($bound: $trait,)?
+            {
+                type Output = Map<Out, Self, MapFunc>;
+                fn fuse_map(self, func: MapFunc) -> Self::Output {
+                    Map {
+                        act_a: self,
+                        func,
+                        _act_a_type: PhantomData,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+/// Binding an action which isn't `Pure` can't be applied straight away.
+macro_rules! fuse_and_then_as_and_then {
+    ($(impl<$($param:ident),*> for $action:ty $(where $bound:ty: $trait:path)?;)*) => {
+        $(
+            impl<Out, ThenOut, ThenAct, ThenFunc, $($param),*>
+                FuseAndThen<Out, ThenOut, ThenAct, ThenFunc> for $action
+            where
+                Self: Action<Out>,
+                ThenAct: Action<ThenOut>,
+                ThenFunc: Fn(&Out) -> ThenAct,
+                $($bound: $trait,)?
+            {
+                type Output = AndThen<Out, Self, ThenAct, ThenFunc>;
+                fn fuse_and_then(self, func: ThenFunc) -> Self::Output {
+                    AndThen {
+                        act_a: self,
+                        func,
+                        _act_a_type: PhantomData,
+                        _act_b: PhantomData,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+/// The actions which none of the laws look inside, passed to `$callback`.
+macro_rules! opaque_actions {
+    ($callback:ident) => {
+        $callback! {
+            impl<S, A> for Literal<S, A> where S: Ap<A>;
+            impl<S, A> for BoxAction<S, A> where S: Ap<A>;
+            impl<S, A> for RcAction<S, A> where S: Ap<A>;
+            impl<A, W> for Writer<A, W>;
+            impl<W> for Tell<W>;
+            impl<A, W, ActA> for Listen<A, W, ActA>;
+            impl<F, ActA> for Pass<F, ActA>;
+            impl<ActA, F> for Censor<ActA, F>;
+            impl<A, E> for ThrowError<A, E>;
+            impl<E, ActA, ActB, F> for CatchError<E, ActA, ActB, F>;
+            impl<A> for Nothing<A>;
+            impl<A> for FromOption<A>;
+            impl<ActA, ActB, F> for OrElse<ActA, ActB, F>;
+            impl<A> for Choose<A>;
+            impl<> for Guard;
+            impl<St> for Get<St>;
+            impl<St> for Put<St>;
+            impl<St, F> for Modify<St, F>;
+            impl<St, F> for Gets<St, F>;
+            impl<Env> for Ask<Env>;
+            impl<Env, F> for Asks<Env, F>;
+            impl<Env, ActA, F> for Local<Env, ActA, F>;
+            impl<Res, A, B> for Jump<Res, A, B>;
+            impl<Res, A, ActA, F> for CallCc<Res, A, ActA, F>;
+            impl<A, ActStep, F> for TailRecM<A, ActStep, F>;
+            impl<T, ActB, F> for Traverse<T, ActB, F>;
+            impl<ActA> for SequenceVec<ActA>;
+            impl<ActA> for ReplicateM<ActA>;
+            impl<T, B, ActB, F> for ForEach<T, B, ActB, F>;
+            impl<T, Acc, ActAcc, F> for FoldM<T, Acc, ActAcc, F>;
+            impl<ActCond, ActA> for WhileM<ActCond, ActA>;
+            impl<F> for FromFuture<F>;
+            impl<K, V, ActA, C> for Memo<K, V, ActA, C>;
+        }
+    };
+}
+
+opaque_actions!(simplify_as_is);
+opaque_actions!(fuse_map_as_map);
+opaque_actions!(fuse_and_then_as_and_then);
+
+simplify_as_is! {
+    impl<A> for Pure<A>;
+}
+
+fuse_map_as_map! {
+    impl<A> for Pure<A>;
+    impl<A, ActA, ActB, F> for AndThen<A, ActA, ActB, F>;
+    impl<ActA, ActB> for Sequence<ActA, ActB>;
+    impl<A, B, ActA, ActB, F> for Combine<A, B, ActA, ActB, F>;
+    impl<B, ActA, ActB> for First<B, ActA, ActB>;
+    impl<A, ActA, ActB> for Second<A, ActA, ActB>;
+    impl<L, R> for EitherAction<L, R>;
+}
+
+fuse_and_then_as_and_then! {
+    impl<A, ActA, F> for Map<A, ActA, F>;
+    impl<A, ActA, ActB, F> for AndThen<A, ActA, ActB, F>;
+    impl<ActA, ActB> for Sequence<ActA, ActB>;
+    impl<A, B, ActA, ActB, F> for Combine<A, B, ActA, ActB, F>;
+    impl<B, ActA, ActB> for First<B, ActA, ActB>;
+    impl<A, ActA, ActB> for Second<A, ActA, ActB>;
+    impl<L, R> for EitherAction<L, R>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        ask, block_on, choose, get, none, pure, put, tell, throw_error, writer, BuildCont,
+        BuildError, BuildFuture, BuildId, BuildList, BuildOption, BuildReader, BuildState,
+        BuildValidation, BuildWriter, Mappable, Run, Sequential,
+    };
+    use super::*;
+    use std::fmt::Debug;
+
+    fn assert_same<S, A, Act, F>(scaffold: &S, make: &F)
+    where
+        S: Ap<A>,
+        S::R: PartialEq + Debug,
+        Act: Run<S, A> + Simplify<A>,
+        Act::Output: Run<S, A>,
+        F: Fn() -> Act,
+    {
+        assert_eq!(
+            make().simplify().run_qualified(scaffold),
+            make().run_qualified(scaffold)
+        );
+    }
+
+    #[test]
+    fn maps_are_fused() {
+        let simple: Map<u32, Pure<u32>, Compose<_, _, u32>> = pure_chain().simplify();
+
+        assert_eq!(simple.run_qualified(&()), pure_chain().run_qualified(&()));
+        assert_eq!(simple.run_qualified(&()), 13);
+    }
+
+    #[test]
+    fn pure_binds_are_applied() {
+        let applied: Writer<u32, u32> = pure(3).and_then(|&x| writer(x + 1, x)).simplify();
+
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        assert_eq!(applied.run_qualified(&BuildWriter(writer_type, ())), (4, 3));
+    }
+
+    type Step = fn(&u32) -> u32;
+    type Chain = Map<u32, Map<u32, Map<u32, Pure<u32>, Step>, Step>, Step>;
+
+    fn pure_chain() -> Chain {
+        pure(3)
+            .map((|x| x + 1) as Step)
+            .map((|x| x * 2) as Step)
+            .map((|x| x + 5) as Step)
+    }
+
+    #[test]
+    fn same_result_for_strict_scaffolds() {
+        let make = || {
+            pure(3)
+                .map(|x| x + 1)
+                .map(|x| x * 2)
+                .and_then(|&x| pure(x).map(|y| y + 1).map(|y| y * 10))
+                .sequence(pure(1).and_then(|&x| pure(x + 1)))
+                .map(|&(x, y)| x + y)
+        };
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let errs_type: PhantomData<fn() -> Vec<&'static str>> = PhantomData;
+
+        assert_same(&(), &make);
+        assert_same(&BuildId(()), &make);
+        assert_same(&BuildWriter(writer_type, ()), &make);
+        assert_same(&BuildError(err_type, ()), &make);
+        assert_same(&BuildValidation(errs_type, ()), &make);
+        assert_same(&BuildOption(()), &make);
+        assert_same(&BuildList(), &make);
+        assert_same(&BuildError(err_type, BuildWriter(writer_type, ())), &make);
+    }
+
+    #[test]
+    fn same_effects() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+
+        let logged = || {
+            pure(2)
+                .and_then(|&x| tell(x).second(writer(x + 1, 10)))
+                .map(|x| x * 3)
+                .map(|x| x + 1)
+                .first(pure(()).and_then(|_| tell(100)))
+        };
+        assert_same(&BuildWriter(writer_type, ()), &logged);
+
+        let failed = || {
+            pure(2)
+                .map(|x| x + 1)
+                .and_then(|_| throw_error::<u32, _>("failed"))
+                .map(|x| x * 2)
+        };
+        assert_same(&BuildError(err_type, ()), &failed);
+        assert_same(&BuildWriter(writer_type, BuildError(err_type, ())), &failed);
+
+        let missing = || pure(2).and_then(|_| none::<u32>()).map(|x| x + 1);
+        assert_same(&BuildOption(()), &missing);
+
+        let chosen = || {
+            choose(vec![1, 2, 3])
+                .map(|x| x * 2)
+                .map(|x| x + 1)
+                .and_then(|&x| pure(x).and_then(move |_| choose(vec![x, x + 10])))
+        };
+        assert_same(&BuildList(), &chosen);
+    }
+
+    #[test]
+    fn same_result_for_lazy_scaffolds() {
+        let state_scaffold: BuildState<u32> = BuildState(PhantomData);
+        let stateful = || {
+            get()
+                .map(|s| s + 1)
+                .map(|s| s * 2)
+                .and_then(|&x| pure(x).and_then(|&y| put(y)))
+                .and_then(|_| get::<u32>())
+        };
+        assert_eq!(
+            stateful()
+                .simplify()
+                .run_qualified(&state_scaffold)
+                .run_state(3),
+            stateful().run_qualified(&state_scaffold).run_state(3)
+        );
+
+        let reader_scaffold: BuildReader<u32> = BuildReader(PhantomData);
+        let reading = || ask().map(|env| env + 1).map(|env| env * 2);
+        assert_eq!(
+            reading()
+                .simplify()
+                .run_qualified(&reader_scaffold)
+                .run_reader(&5),
+            reading().run_qualified(&reader_scaffold).run_reader(&5)
+        );
+
+        let res_type: PhantomData<fn() -> u32> = PhantomData;
+        assert_eq!(
+            pure_chain()
+                .simplify()
+                .run_qualified(&BuildCont(res_type))
+                .eval_cont(),
+            pure_chain().run_qualified(&BuildCont(res_type)).eval_cont()
+        );
+        assert_eq!(
+            block_on(pure_chain().simplify().run_qualified(&BuildFuture)),
+            block_on(pure_chain().run_qualified(&BuildFuture))
+        );
+    }
+}
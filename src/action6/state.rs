@@ -0,0 +1,370 @@
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use super::{
+    Action, AndThen, Ap, Literal, Map, MapFn, Mappable, Pure, Run, RunTraced, Sequential, Step,
+    TailRec, Tracer,
+};
+
+/// The scaffold for actions which read and write a state of type `St`.
+///
+/// Unlike the other scaffolds, the completed type is a function waiting for an
+/// initial state, so nothing happens until `run_state` is called. Because the
+/// function outlives the action that built it, everything it captures must
+/// live for `'a`, and `BuildState` can not be nested inside another scaffold.
+/// The closures in a chain may still borrow local variables, as long as the
+/// completed `State` is run while they are borrowed.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BuildState<'a, St>(pub PhantomData<fn() -> &'a St>);
+
+/// A state passing function, completed by `BuildState`.
+pub struct State<'a, St, A>(Rc<dyn Fn(St) -> (A, St) + 'a>);
+impl<St, A> State<'_, St, A> {
+    pub fn run_state(&self, state: St) -> (A, St) {
+        (self.0)(state)
+    }
+    pub fn eval_state(&self, state: St) -> A {
+        self.run_state(state).0
+    }
+    pub fn exec_state(&self, state: St) -> St {
+        self.run_state(state).1
+    }
+}
+impl<St, A> Clone for State<'_, St, A> {
+    fn clone(&self) -> Self {
+        State(self.0.clone())
+    }
+}
+
+impl<'a, A, St> Ap<A> for BuildState<'a, St>
+where
+    A: Clone + 'a,
+    St: 'a,
+{
+    type R = State<'a, St, A>;
+    fn build(value: A) -> Self::R {
+        State(Rc::new(move |state| (value.clone(), state)))
+    }
+}
+
+/// `Get` is an action which returns the current state.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Get<St>(PhantomData<fn() -> St>);
+pub fn get<St>() -> Get<St> {
+    Get(PhantomData)
+}
+impl<St> Mappable<St> for Get<St> {}
+impl<St> Sequential<St> for Get<St> {}
+impl<St> Action<St> for Get<St> {}
+impl<'a, St> Run<BuildState<'a, St>, St> for Get<St>
+where
+    St: Clone + 'a,
+{
+    fn run(&self) -> State<'a, St, St> {
+        State(Rc::new(|state: St| (state.clone(), state)))
+    }
+}
+
+/// `Put` is an action which replaces the current state.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Put<St> {
+    pub(super) state: St,
+}
+pub fn put<St>(state: St) -> Put<St> {
+    Put { state }
+}
+impl<St> Mappable<()> for Put<St> {}
+impl<St> Sequential<()> for Put<St> {}
+impl<St> Action<()> for Put<St> {}
+impl<'a, St> Run<BuildState<'a, St>, ()> for Put<St>
+where
+    St: Clone + 'a,
+{
+    fn run(&self) -> State<'a, St, ()> {
+        let state = self.state.clone();
+        State(Rc::new(move |_| ((), state.clone())))
+    }
+}
+
+/// `Modify` is an action which replaces the current state with a function of
+/// the current state.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Modify<St, F> {
+    pub(super) func: F,
+    _state_type: PhantomData<fn() -> St>,
+}
+pub fn modify<St, F>(func: F) -> Modify<St, F>
+where
+    F: Fn(&St) -> St,
+{
+    Modify {
+        func,
+        _state_type: PhantomData,
+    }
+}
+impl<St, F> Mappable<()> for Modify<St, F> where F: Fn(&St) -> St {}
+impl<St, F> Sequential<()> for Modify<St, F> where F: Fn(&St) -> St {}
+impl<St, F> Action<()> for Modify<St, F> where F: Fn(&St) -> St {}
+impl<'a, St, F> Run<BuildState<'a, St>, ()> for Modify<St, F>
+where
+    St: 'a,
+    F: Fn(&St) -> St + Clone + 'a,
+{
+    fn run(&self) -> State<'a, St, ()> {
+        let func = self.func.clone();
+        State(Rc::new(move |state| ((), func(&state))))
+    }
+}
+
+/// `Gets` is an action which returns a function of the current state.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Gets<St, F> {
+    pub(super) func: F,
+    _state_type: PhantomData<fn() -> St>,
+}
+pub fn gets<A, St, F>(func: F) -> Gets<St, F>
+where
+    F: Fn(&St) -> A,
+{
+    Gets {
+        func,
+        _state_type: PhantomData,
+    }
+}
+impl<A, St, F> Mappable<A> for Gets<St, F> where F: Fn(&St) -> A {}
+impl<A, St, F> Sequential<A> for Gets<St, F> where F: Fn(&St) -> A {}
+impl<A, St, F> Action<A> for Gets<St, F> where F: Fn(&St) -> A {}
+impl<'a, A, St, F> Run<BuildState<'a, St>, A> for Gets<St, F>
+where
+    A: Clone + 'a,
+    St: 'a,
+    F: Fn(&St) -> A + Clone + 'a,
+{
+    fn run(&self) -> State<'a, St, A> {
+        let func = self.func.clone();
+        State(Rc::new(move |state| (func(&state), state)))
+    }
+}
+
+impl<'a, A, B, ActA, F, St> Run<BuildState<'a, St>, B> for Map<A, ActA, F>
+where
+    A: Clone + 'a,
+    B: Clone + 'a,
+    St: 'a,
+    ActA: Run<BuildState<'a, St>, A>,
+    F: MapFn<A, B> + Clone + 'a,
+{
+    fn run(&self) -> State<'a, St, B> {
+        let state_a = self.act_a.run();
+        let func = self.func.clone();
+        State(Rc::new(move |state| {
+            let (result_a, state) = state_a.run_state(state);
+            (func.call(&result_a), state)
+        }))
+    }
+}
+
+impl<'a, A, B, ActA, ActB, F, St> Run<BuildState<'a, St>, B> for AndThen<A, ActA, ActB, F>
+where
+    A: Clone + 'a,
+    B: Clone + 'a,
+    St: 'a,
+    ActA: Run<BuildState<'a, St>, A>,
+    ActB: Run<BuildState<'a, St>, B>,
+    F: Fn(&A) -> ActB + Clone + 'a,
+{
+    fn run(&self) -> State<'a, St, B> {
+        let state_a = self.act_a.run();
+        let func = self.func.clone();
+        State(Rc::new(move |state| {
+            let (result_a, state) = state_a.run_state(state);
+            func(&result_a).run().run_state(state)
+        }))
+    }
+}
+
+/// Each iteration runs to completion before the next begins, so the loop only
+/// needs constant stack however many times it repeats.
+impl<'a, A, B, St> TailRec<A, B> for BuildState<'a, St>
+where
+    A: Clone + 'a,
+    B: Clone + 'a,
+    St: 'a,
+{
+    fn tail_rec<F>(init: A, func: F) -> State<'a, St, B>
+    where
+        F: Fn(A) -> State<'a, St, Step<A, B>> + 'static,
+    {
+        State(Rc::new(move |mut state| {
+            let mut value = init.clone();
+            loop {
+                let (step, next_state) = func(value).run_state(state);
+                state = next_state;
+                match step {
+                    Step::Loop(next) => value = next,
+                    Step::Done(result) => return (result, state),
+                }
+            }
+        }))
+    }
+}
+
+impl<'a, St, A> State<'a, St, A>
+where
+    St: 'a,
+    A: 'a,
+{
+    /// Run inside a span for the step `Act`, each time the function is
+    /// called.
+    fn traced<'t, Act>(self, tracer: &Rc<Tracer<'t>>, name: &'static str) -> Self
+    where
+        't: 'a,
+    {
+        let tracer = tracer.clone();
+        State(Rc::new(move |state| {
+            tracer.span::<Act, _, _>(name, || self.run_state(state))
+        }))
+    }
+}
+
+impl<'t, 'a, A, St> RunTraced<'t, BuildState<'a, St>, A> for Pure<A>
+where
+    't: 'a,
+    A: Clone + 'a,
+    St: 'a,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> State<'a, St, A> {
+        <BuildState<'a, St> as Ap<A>>::build(self.value.clone()).traced::<Self>(tracer, "Pure")
+    }
+}
+
+impl<'t, 'a, A, St> RunTraced<'t, BuildState<'a, St>, A> for Literal<BuildState<'a, St>, A>
+where
+    't: 'a,
+    A: Clone + 'a,
+    St: 'a,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> State<'a, St, A> {
+        self.value.clone().traced::<Self>(tracer, "Literal")
+    }
+}
+
+impl<'t, 'a, A, B, ActA, F, St> RunTraced<'t, BuildState<'a, St>, B> for Map<A, ActA, F>
+where
+    't: 'a,
+    A: Clone + 'a,
+    B: Clone + 'a,
+    St: 'a,
+    ActA: RunTraced<'t, BuildState<'a, St>, A>,
+    F: MapFn<A, B> + Clone + 'a,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> State<'a, St, B> {
+        let state_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        State(Rc::new(move |state| {
+            let (result_a, state) = state_a.run_state(state);
+            (func.call(&result_a), state)
+        }))
+        .traced::<Self>(tracer, "Map")
+    }
+}
+
+impl<'t, 'a, A, B, ActA, ActB, F, St> RunTraced<'t, BuildState<'a, St>, B>
+    for AndThen<A, ActA, ActB, F>
+where
+    't: 'a,
+    A: Clone + 'a,
+    B: Clone + 'a,
+    St: 'a,
+    ActA: RunTraced<'t, BuildState<'a, St>, A>,
+    ActB: RunTraced<'t, BuildState<'a, St>, B>,
+    F: Fn(&A) -> ActB + Clone + 'a,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> State<'a, St, B> {
+        let state_a = self.act_a.trace(tracer);
+        let func = self.func.clone();
+        let tracer_b = tracer.clone();
+        State(Rc::new(move |state| {
+            let (result_a, state) = state_a.run_state(state);
+            func(&result_a).trace(&tracer_b).run_state(state)
+        }))
+        .traced::<Self>(tracer, "AndThen")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{pure, tail_rec_m};
+    use super::*;
+
+    #[test]
+    fn existing_chains_run_with_state() {
+        let scaffold: BuildState<u32> = BuildState(PhantomData);
+
+        let action = pure(3).and_then(|x| pure(x + 2)).map(|y| y * 2);
+
+        assert_eq!(action.run_qualified(&scaffold).run_state(7), (10, 7));
+    }
+
+    #[test]
+    fn get_and_put() {
+        let scaffold: BuildState<u32> = BuildState(PhantomData);
+
+        let action = get()
+            .and_then(|&x| put(x + 1))
+            .and_then(|_| get())
+            .map(|x| x * 10);
+
+        assert_eq!(action.run_qualified(&scaffold).run_state(4), (50, 5));
+    }
+
+    #[test]
+    fn modify_and_gets() {
+        let scaffold: BuildState<Vec<u32>> = BuildState(PhantomData);
+
+        let action = modify(|xs: &Vec<u32>| {
+            let mut xs = xs.clone();
+            xs.push(3);
+            xs
+        })
+        .and_then(|_| gets(|xs: &Vec<u32>| xs.len()));
+
+        let state = action.run_qualified(&scaffold);
+
+        assert_eq!(state.eval_state(vec![1, 2]), 3);
+        assert_eq!(state.exec_state(vec![]), vec![3]);
+    }
+
+    #[test]
+    fn closures_borrow_locals() {
+        let scaffold: BuildState<u32> = BuildState(PhantomData);
+        let step = 5;
+        let names = ["zero".to_string(), "one".to_string()];
+
+        let action = get()
+            .and_then(|&x| put(x + step))
+            .and_then(|_| gets(|&x: &u32| names[(x % 2) as usize].as_str()));
+
+        assert_eq!(action.run_qualified(&scaffold).run_state(2), ("one", 7));
+    }
+
+    #[test]
+    fn million_steps() {
+        let scaffold: BuildState<u64> = BuildState(PhantomData);
+
+        let action = tail_rec_m(0, |&n: &u32| {
+            modify(move |total: &u64| total + u64::from(n)).map(move |_| {
+                if n < 1_000_000 {
+                    Step::Loop(n + 1)
+                } else {
+                    Step::Done(n)
+                }
+            })
+        });
+
+        assert_eq!(
+            action.run_qualified(&scaffold).run_state(0),
+            (1_000_000, 500_000_500_000)
+        );
+    }
+}
@@ -0,0 +1,641 @@
+use std::any;
+use std::cell::{Cell, RefCell};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use super::{
+    AndThen, Ap, Ask, Asks, Bind, BoxAction, CallCc, CatchError, Censor, Choose, Combine, Eager,
+    EitherAction, First, FoldM, ForEach, FromFuture, FromOption, Get, Gets, Guard, Jump, Listen,
+    Literal, Local, Map, MapFn, Memo, Modify, Nothing, OrElse, Pass, Pure, Put, RcAction,
+    ReplicateM, Run, Second, Sequence, SequenceVec, TailRecM, Tell, ThrowError, Traverse, WhileM,
+    Writer, Zip,
+};
+
+/// One step of an action which has finished running.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Ids are given out in the order the steps start, from zero.
+    pub id: u64,
+    pub parent: Option<u64>,
+    /// The kind of step, such as `"Map"`.
+    pub name: &'static str,
+    /// The full type of the step, including the types of its closures.
+    pub type_name: &'static str,
+    /// How many steps this step is nested inside.
+    pub depth: usize,
+    /// When the step started, measured from when the trace started.
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Somewhere to send spans. Each span is recorded when its step finishes, so
+/// a step is recorded after the steps nested inside it.
+pub trait TraceSink {
+    fn record(&mut self, span: Span);
+}
+impl<T> TraceSink for &mut T
+where
+    T: TraceSink + ?Sized,
+{
+    fn record(&mut self, span: Span) {
+        (**self).record(span);
+    }
+}
+/// A shared sink, which can still be read after the completed value has run.
+/// The scaffolds whose completed values must be `'static` need one of these.
+impl<T> TraceSink for Rc<RefCell<T>>
+where
+    T: TraceSink + ?Sized,
+{
+    fn record(&mut self, span: Span) {
+        self.borrow_mut().record(span);
+    }
+}
+
+/// A sink which keeps every span in memory.
+#[derive(Clone, Debug, Default)]
+pub struct TraceCollector {
+    pub spans: Vec<Span>,
+}
+impl TraceSink for TraceCollector {
+    fn record(&mut self, span: Span) {
+        self.spans.push(span);
+    }
+}
+
+/// A sink which writes spans to a file in the Chrome trace event format, to
+/// be opened with `chrome://tracing` or Perfetto. Spans are written as they
+/// are recorded, and `finish` must be called to close the file.
+pub struct ChromeTraceFile {
+    out: BufWriter<File>,
+    events: usize,
+    error: Option<io::Error>,
+}
+impl ChromeTraceFile {
+    pub fn create<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(b"[")?;
+        Ok(ChromeTraceFile {
+            out,
+            events: 0,
+            error: None,
+        })
+    }
+
+    /// Close the list of events, and return the first error from writing any
+    /// of them.
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.out.write_all(b"\n]\n")?;
+        self.out.flush()
+    }
+
+    fn write_span(&mut self, span: &Span) -> io::Result<()> {
+        let separator = if self.events == 0 { "" } else { "," };
+        write!(
+            self.out,
+            "{}\n{{\"name\":\"{}\",\"cat\":\"action6\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\
+             \"pid\":1,\"tid\":1,\"args\":{{\"id\":{},\"depth\":{},\"type\":\"{}\"}}}}",
+            separator,
+            escape_json(span.name),
+            span.start.as_nanos() as f64 / 1000.0,
+            span.duration.as_nanos() as f64 / 1000.0,
+            span.id,
+            span.depth,
+            escape_json(span.type_name),
+        )?;
+        self.events += 1;
+        Ok(())
+    }
+}
+impl TraceSink for ChromeTraceFile {
+    fn record(&mut self, span: Span) {
+        if self.error.is_none() {
+            if let Err(error) = self.write_span(&span) {
+                self.error = Some(error);
+            }
+        }
+    }
+}
+
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Times the steps of an action, and sends a span for each of them to a sink.
+///
+/// The lazy scaffolds run their steps after `trace` has returned, so the
+/// completed value keeps an `Rc` of the tracer, and the sink must live as
+/// long as the completed value does.
+pub struct Tracer<'a> {
+    sink: RefCell<Box<dyn TraceSink + 'a>>,
+    started: Instant,
+    next_id: Cell<u64>,
+    open: RefCell<Vec<u64>>,
+}
+impl<'a> Tracer<'a> {
+    pub fn new<Sink>(sink: Sink) -> Self
+    where
+        Sink: TraceSink + 'a,
+    {
+        Tracer {
+            sink: RefCell::new(Box::new(sink)),
+            started: Instant::now(),
+            next_id: Cell::new(0),
+            open: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Run `body` inside a span for the step `Step`. Any spans started by
+    /// `body` are nested inside it.
+    pub fn span<Step, R, F>(&self, name: &'static str, body: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let span = self.start::<Step>(name);
+        let result = body();
+        self.finish(span);
+        result
+    }
+
+    /// Start a span for the step `Step`, which is nested inside every span
+    /// that has started and not yet finished.
+    pub(super) fn start<Step>(&self, name: &'static str) -> OpenSpan {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let mut open = self.open.borrow_mut();
+        let span = OpenSpan {
+            id,
+            parent: open.last().copied(),
+            name,
+            type_name: any::type_name::<Step>(),
+            depth: open.len(),
+            start: self.started.elapsed(),
+            timer: Instant::now(),
+        };
+        open.push(id);
+        span
+    }
+
+    /// Finish a span, and send it to the sink.
+    pub(super) fn finish(&self, span: OpenSpan) {
+        let duration = span.timer.elapsed();
+        let mut open = self.open.borrow_mut();
+        if let Some(index) = open.iter().rposition(|&id| id == span.id) {
+            open.remove(index);
+        }
+        drop(open);
+        self.sink.borrow_mut().record(Span {
+            id: span.id,
+            parent: span.parent,
+            name: span.name,
+            type_name: span.type_name,
+            depth: span.depth,
+            start: span.start,
+            duration,
+        });
+    }
+}
+
+/// A span which has started, but whose step hasn't finished yet.
+pub(super) struct OpenSpan {
+    id: u64,
+    parent: Option<u64>,
+    name: &'static str,
+    type_name: &'static str,
+    depth: usize,
+    start: Duration,
+    timer: Instant,
+}
+
+/// Run an action, recording a span for each step.
+///
+/// `Map`, `AndThen`, `Pure` and `Literal` have a span around each time they
+/// run, under every scaffold. With the scaffolds which can `Bind`, the steps
+/// inside `Map` and `AndThen` are traced using `Bind`. The lazy scaffolds,
+/// `BuildState`, `BuildReader`, `BuildCont`, `BuildFuture` and `BuildRecord`,
+/// have their own impls, whose completed values open the span when they run,
+/// rather than when they are built. Under `BuildCont`, a span ends when its
+/// step passes on its result, so a step abandoned by a `Jump` is never
+/// recorded, and steps started after it are nested inside it.
+///
+/// The other sequential actions are traced using `Bind` too, so the lazy
+/// scaffolds can't trace them. Every other action, such as `CatchError`, the
+/// loops and the boxed actions, has a single span around `run`, and the steps
+/// inside it are not traced. These can be traced with any scaffold they run
+/// with, but with the lazy scaffolds the span only covers building the
+/// completed value, not running it.
+///
+/// The completed values of `BuildCont`, `BuildFuture` and `BuildRecord` must
+/// be `'static`, so they need a sink which is too, such as an
+/// `Rc<RefCell<TraceCollector>>`.
+pub trait RunTraced<'t, S, A>: Run<S, A>
+where
+    S: Ap<A>,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> S::R;
+    fn run_traced<Sink>(&self, sink: Sink) -> S::R
+    where
+        Sink: TraceSink + 't,
+    {
+        self.trace(&Rc::new(Tracer::new(sink)))
+    }
+    fn run_traced_qualified<Sink>(&self, _scaffold: &S, sink: Sink) -> S::R
+    where
+        Sink: TraceSink + 't,
+    {
+        self.run_traced(sink)
+    }
+}
+
+impl<'t, S, A, B, ActA, F> RunTraced<'t, S, B> for Map<A, ActA, F>
+where
+    S: Bind<A, B>,
+    Self: Run<S, B>,
+    ActA: RunTraced<'t, S, A>,
+    F: MapFn<A, B>,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> <S as Ap<B>>::R {
+        tracer.span::<Self, _, _>("Map", || {
+            S::bind(self.act_a.trace(tracer), |result_a| {
+                <S as Ap<B>>::build(self.func.call(&result_a))
+            })
+        })
+    }
+}
+
+impl<'t, S, A, B, ActA, ActB, F> RunTraced<'t, S, B> for AndThen<A, ActA, ActB, F>
+where
+    S: Bind<A, B>,
+    Self: Run<S, B>,
+    ActA: RunTraced<'t, S, A>,
+    ActB: RunTraced<'t, S, B>,
+    F: Fn(&A) -> ActB,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> <S as Ap<B>>::R {
+        tracer.span::<Self, _, _>("AndThen", || {
+            S::bind(self.act_a.trace(tracer), |result_a| {
+                (self.func)(&result_a).trace(tracer)
+            })
+        })
+    }
+}
+
+impl<'t, S, A, B, ActA, ActB> RunTraced<'t, S, (A, B)> for Sequence<ActA, ActB>
+where
+    S: Zip<A, B>,
+    ActA: RunTraced<'t, S, A>,
+    ActB: RunTraced<'t, S, B>,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> <S as Ap<(A, B)>>::R {
+        tracer.span::<Self, _, _>("Sequence", || {
+            S::zip(self.0.trace(tracer), self.1.trace(tracer))
+        })
+    }
+}
+
+impl<'t, S, A, B, C, ActA, ActB, F> RunTraced<'t, S, C> for Combine<A, B, ActA, ActB, F>
+where
+    S: Zip<A, B> + Bind<(A, B), C>,
+    Self: Run<S, C>,
+    ActA: RunTraced<'t, S, A>,
+    ActB: RunTraced<'t, S, B>,
+    F: Fn(&A, &B) -> C,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> <S as Ap<C>>::R {
+        tracer.span::<Self, _, _>("Combine", || {
+            S::bind(
+                S::zip(self.act_a.trace(tracer), self.act_b.trace(tracer)),
+                |(result_a, result_b)| <S as Ap<C>>::build((self.func)(&result_a, &result_b)),
+            )
+        })
+    }
+}
+
+impl<'t, S, A, B, ActA, ActB> RunTraced<'t, S, A> for First<B, ActA, ActB>
+where
+    S: Zip<A, B> + Bind<(A, B), A>,
+    Self: Run<S, A>,
+    ActA: RunTraced<'t, S, A>,
+    ActB: RunTraced<'t, S, B>,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> <S as Ap<A>>::R {
+        tracer.span::<Self, _, _>("First", || {
+            S::bind(
+                S::zip(self.act_a.trace(tracer), self.act_b.trace(tracer)),
+                |(result_a, _)| <S as Ap<A>>::build(result_a),
+            )
+        })
+    }
+}
+
+impl<'t, S, A, B, ActA, ActB> RunTraced<'t, S, B> for Second<A, ActA, ActB>
+where
+    S: Zip<A, B> + Bind<(A, B), B>,
+    Self: Run<S, B>,
+    ActA: RunTraced<'t, S, A>,
+    ActB: RunTraced<'t, S, B>,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> <S as Ap<B>>::R {
+        tracer.span::<Self, _, _>("Second", || {
+            S::bind(
+                S::zip(self.act_a.trace(tracer), self.act_b.trace(tracer)),
+                |(_, result_b)| <S as Ap<B>>::build(result_b),
+            )
+        })
+    }
+}
+
+/// `EitherAction` has no span of its own, only the action it holds.
+impl<'t, S, A, L, R> RunTraced<'t, S, A> for EitherAction<L, R>
+where
+    S: Ap<A>,
+    L: RunTraced<'t, S, A>,
+    R: RunTraced<'t, S, A>,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> S::R {
+        match self {
+            EitherAction::Left(act_l) => act_l.trace(tracer),
+            EitherAction::Right(act_r) => act_r.trace(tracer),
+        }
+    }
+}
+
+/// `Pure` and `Literal` run when they are built by the eager scaffolds. The
+/// lazy scaffolds have their own impls.
+impl<'t, S, A> RunTraced<'t, S, A> for Pure<A>
+where
+    S: Eager + Ap<A>,
+    Self: Run<S, A>,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> S::R {
+        tracer.span::<Self, _, _>("Pure", || self.run())
+    }
+}
+
+impl<'t, S, A> RunTraced<'t, S, A> for Literal<S, A>
+where
+    S: Eager + Ap<A>,
+    Self: Run<S, A>,
+{
+    fn trace(&self, tracer: &Rc<Tracer<'t>>) -> S::R {
+        tracer.span::<Self, _, _>("Literal", || self.run())
+    }
+}
+
+/// Actions with no steps inside them, or whose steps aren't traced, have a
+/// single span around `run`.
+macro_rules! trace_run {
+    ($(impl<$($param:ident),*> for $action:ty => $value:ty, $name:expr;)*) => {
+        $(
+            impl<'t, S, $($param),*> RunTraced<'t, S, $value> for $action
+            where
+                S: Ap<$value>,
+                Self: Run<S, $value>,
+            {
+                fn trace(&self, tracer: &Rc<Tracer<'t>>) -> S::R {
+                    tracer.span::<Self, _, _>($name, || self.run())
+                }
+            }
+        )*
+    };
+}
+
+trace_run! {
+    impl<A, W> for Writer<A, W> => A, "Writer";
+    impl<W> for Tell<W> => (), "Tell";
+    impl<A, E> for ThrowError<A, E> => A, "ThrowError";
+    impl<A> for BoxAction<S, A> => A, "BoxAction";
+    impl<A> for RcAction<S, A> => A, "RcAction";
+    impl<A, W, ActA> for Listen<A, W, ActA> => (A, W), "Listen";
+    impl<A, F, ActA> for Pass<F, ActA> => A, "Pass";
+    impl<A, ActA, F> for Censor<ActA, F> => A, "Censor";
+    impl<A, E, ActA, ActB, F> for CatchError<E, ActA, ActB, F> => A, "CatchError";
+    impl<A> for Nothing<A> => A, "Nothing";
+    impl<A> for FromOption<A> => A, "FromOption";
+    impl<A, ActA, ActB, F> for OrElse<ActA, ActB, F> => A, "OrElse";
+    impl<A> for Choose<A> => A, "Choose";
+    impl<> for Guard => (), "Guard";
+    impl<St> for Get<St> => St, "Get";
+    impl<St> for Put<St> => (), "Put";
+    impl<St, F> for Modify<St, F> => (), "Modify";
+    impl<A, St, F> for Gets<St, F> => A, "Gets";
+    impl<Env> for Ask<Env> => Env, "Ask";
+    impl<A, Env, F> for Asks<Env, F> => A, "Asks";
+    impl<A, Env, ActA, F> for Local<Env, ActA, F> => A, "Local";
+    impl<Res, A, B> for Jump<Res, A, B> => B, "Jump";
+    impl<Res, A, ActA, F> for CallCc<Res, A, ActA, F> => A, "CallCc";
+    impl<A, B, ActStep, F> for TailRecM<A, ActStep, F> => B, "TailRecM";
+    impl<T, B, ActB, F> for Traverse<T, ActB, F> => Vec<B>, "Traverse";
+    impl<A, ActA> for SequenceVec<ActA> => Vec<A>, "SequenceVec";
+    impl<A, ActA> for ReplicateM<ActA> => Vec<A>, "ReplicateM";
+    impl<T, B, ActB, F> for ForEach<T, B, ActB, F> => (), "ForEach";
+    impl<T, Acc, ActAcc, F> for FoldM<T, Acc, ActAcc, F> => Acc, "FoldM";
+    impl<A, ActCond, ActA> for WhileM<ActCond, ActA> => Vec<A>, "WhileM";
+    impl<A, F> for FromFuture<F> => A, "FromFuture";
+    impl<A, K, V, ActA, C> for Memo<K, V, ActA, C> => A, "Memo";
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{
+        block_on, catch_error, for_each, get, literal, pure, tell, throw_error, writer, Action,
+        Boxed, BuildCont, BuildError, BuildFuture, BuildReader, BuildRecord, BuildState,
+        BuildWriter, Mappable, Sequential,
+    };
+    use super::*;
+    use std::fs;
+    use std::marker::PhantomData;
+
+    fn summary(spans: &[Span]) -> Vec<(u64, Option<u64>, &'static str, usize)> {
+        spans
+            .iter()
+            .map(|span| (span.id, span.parent, span.name, span.depth))
+            .collect()
+    }
+
+    #[test]
+    fn spans_nest() {
+        let action = pure(3).and_then(|x| pure(x + 1)).map(|x| x * 2);
+        let mut collector = TraceCollector::default();
+
+        assert_eq!(action.run_traced_qualified(&(), &mut collector), 8);
+        assert_eq!(
+            summary(&collector.spans),
+            vec![
+                (2, Some(1), "Pure", 2),
+                (3, Some(1), "Pure", 2),
+                (1, Some(0), "AndThen", 1),
+                (0, None, "Map", 0),
+            ]
+        );
+        let map = &collector.spans[3];
+        assert!(map.type_name.contains("Map<"));
+        assert!(collector.spans[..3]
+            .iter()
+            .all(|span| span.start >= map.start && span.duration <= map.duration));
+    }
+
+    #[test]
+    fn writer_spans() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = writer(3, 1)
+            .sequence(tell(2))
+            .and_then(|&(x, ())| writer(x, 10));
+        let mut collector = TraceCollector::default();
+
+        assert_eq!(
+            action.run_traced_qualified(&scaffold, &mut collector),
+            (3, 13)
+        );
+        assert_eq!(
+            summary(&collector.spans),
+            vec![
+                (2, Some(1), "Writer", 2),
+                (3, Some(1), "Tell", 2),
+                (1, Some(0), "Sequence", 1),
+                (4, Some(0), "Writer", 1),
+                (0, None, "AndThen", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn lazy_spans_open_when_run() {
+        let action = || pure(3).and_then(|&x| pure(x + 1)).map(|x| x * 2);
+        let nested = vec![
+            (2, Some(1), "Pure", 2),
+            (3, Some(1), "Pure", 2),
+            (1, Some(0), "AndThen", 1),
+            (0, None, "Map", 0),
+        ];
+
+        let state_scaffold: BuildState<u32> = BuildState(PhantomData);
+        let mut collector = TraceCollector::default();
+        let state = action().run_traced_qualified(&state_scaffold, &mut collector);
+        assert_eq!(state.run_state(5), (8, 5));
+        drop(state);
+        assert_eq!(summary(&collector.spans), nested);
+
+        let reader_scaffold: BuildReader<u32> = BuildReader(PhantomData);
+        let mut collector = TraceCollector::default();
+        let reader = action().run_traced_qualified(&reader_scaffold, &mut collector);
+        assert_eq!(reader.run_reader(&5), 8);
+        drop(reader);
+        assert_eq!(summary(&collector.spans), nested);
+
+        let cont_scaffold: BuildCont<u32> = BuildCont(PhantomData);
+        let collector = Rc::new(RefCell::new(TraceCollector::default()));
+        let cont = action().run_traced_qualified(&cont_scaffold, collector.clone());
+        assert!(collector.borrow().spans.is_empty());
+        assert_eq!(cont.eval_cont(), 8);
+        assert_eq!(summary(&collector.borrow().spans), nested);
+
+        let record_scaffold: BuildRecord<String, String> = BuildRecord(PhantomData);
+        let collector = Rc::new(RefCell::new(TraceCollector::default()));
+        let recording = action().run_traced_qualified(&record_scaffold, collector.clone());
+        assert!(collector.borrow().spans.is_empty());
+        assert_eq!(recording.record(()), (Ok(8), vec![]));
+        assert_eq!(summary(&collector.borrow().spans), nested);
+
+        let collector = Rc::new(RefCell::new(TraceCollector::default()));
+        let future = action().run_traced_qualified(&BuildFuture, collector.clone());
+        assert!(collector.borrow().spans.is_empty());
+        assert_eq!(block_on(future), 8);
+        assert_eq!(summary(&collector.borrow().spans), nested);
+    }
+
+    #[test]
+    fn lazy_literals() {
+        let state_scaffold: BuildState<u32> = BuildState(PhantomData);
+        let action = literal(get::<u32>().run_qualified(&state_scaffold)).map(|x| x + 1);
+        let collector = Rc::new(RefCell::new(TraceCollector::default()));
+
+        let state = action.run_traced_qualified(&state_scaffold, collector.clone());
+        assert_eq!(state.run_state(4), (5, 4));
+        assert_eq!(state.run_state(6), (7, 6));
+        assert_eq!(
+            summary(&collector.borrow().spans),
+            vec![
+                (1, Some(0), "Literal", 1),
+                (0, None, "Map", 0),
+                (3, Some(2), "Literal", 1),
+                (2, None, "Map", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn chrome_trace_file() {
+        let path = std::env::temp_dir().join(format!("action6-trace-{}.json", std::process::id()));
+        let action = pure(3).and_then(|x| pure(x + 1)).map(|x| x * 2);
+
+        let mut file = ChromeTraceFile::create(&path).unwrap();
+        assert_eq!(action.run_traced_qualified(&(), &mut file), 8);
+        file.finish().unwrap();
+
+        let json = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(json.starts_with("[\n{\"name\":\"Pure\",\"cat\":\"action6\",\"ph\":\"X\""));
+        assert!(json.ends_with("}\n]\n"));
+        assert_eq!(json.matches("\"ph\":\"X\"").count(), 4);
+        assert!(json.contains("\"args\":{\"id\":0,\"depth\":0,\"type\":\""));
+    }
+
+    #[test]
+    fn escapes_json() {
+        assert_eq!(escape_json("a\"b\\c\nd"), "a\\\"b\\\\c\\u000ad");
+    }
+
+    #[test]
+    fn opaque_actions_have_one_span() {
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, ());
+        let state_scaffold: BuildState<u32> = BuildState(PhantomData);
+
+        let action = catch_error(throw_error::<u32, _>("failed"), |_| pure(0))
+            .and_then(|&x| for_each(vec![1, 2], move |&y| pure(x + y)).boxed());
+        let action2 = get::<u32>();
+        let mut collector = TraceCollector::default();
+        let mut collector2 = TraceCollector::default();
+
+        assert_eq!(
+            action.run_traced_qualified(&scaffold, &mut collector),
+            Ok(())
+        );
+        assert_eq!(
+            action2
+                .run_traced_qualified(&state_scaffold, &mut collector2)
+                .run_state(5),
+            (5, 5)
+        );
+        assert_eq!(
+            summary(&collector.spans),
+            vec![
+                (1, Some(0), "CatchError", 1),
+                (2, Some(0), "BoxAction", 1),
+                (0, None, "AndThen", 0),
+            ]
+        );
+        assert_eq!(summary(&collector2.spans), vec![(0, None, "Get", 0)]);
+    }
+}
@@ -0,0 +1,861 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use super::boxed::Parts;
+use super::loops::{then_step, Shared};
+use super::{
+    Action, AndThen, Ap, Ask, Asks, Bind, BoxAction, BuildError, BuildId, BuildList, BuildOption,
+    BuildValidation, BuildWriter, CallCc, CatchError, Censor, Choose, Combine, EitherAction, First,
+    FoldM, ForEach, FromFuture, FromOption, Get, Gets, Guard, Identity, Jump, Listen, Literal,
+    Local, Map, MapFn, Mappable, Memo, Modify, Monoid, Nothing, OrElse, Pass, Pure, Put, RcAction,
+    ReplicateM, Run, Second, Sequence, SequenceVec, Sequential, Tell, ThrowError, Traverse, WhileM,
+    Writer,
+};
+
+/// The result of one iteration of a loop: either the state to run the next
+/// iteration with, or the final result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Step<A, B> {
+    Loop(A),
+    Done(B),
+}
+
+/// Scaffolds which can run a loop in constant stack.
+///
+/// Binding an action to itself nests a call to `Bind::bind` for every
+/// iteration, so deep recursion overflows the stack. `tail_rec` instead runs
+/// `func` in a plain loop until it produces `Step::Done`, combining the effects
+/// of each iteration as it goes.
+///
+/// The lazy scaffolds, such as `BuildState`, complete a function which runs
+/// the loop later, so `func` must be `'static`.
+pub trait TailRec<A, B>: Ap<Step<A, B>> + Ap<B> {
+    fn tail_rec<F>(init: A, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<Step<A, B>>>::R + 'static;
+}
+impl<A, B> TailRec<A, B> for () {
+    fn tail_rec<F>(init: A, func: F) -> B
+    where
+        F: Fn(A) -> Step<A, B> + 'static,
+    {
+        let mut state = init;
+        loop {
+            match func(state) {
+                Step::Loop(next) => state = next,
+                Step::Done(result) => return result,
+            }
+        }
+    }
+}
+impl<A, B, S> TailRec<A, B> for BuildId<S>
+where
+    S: TailRec<A, Identity<B>> + Bind<Identity<Step<A, B>>, Step<A, Identity<B>>>,
+{
+    fn tail_rec<F>(init: A, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<Step<A, B>>>::R + 'static,
+    {
+        S::tail_rec(init, move |state| {
+            S::bind(func(state), |Identity(step)| {
+                <S as Ap<Step<A, Identity<B>>>>::build(match step {
+                    Step::Loop(next) => Step::Loop(next),
+                    Step::Done(result) => Step::Done(Identity(result)),
+                })
+            })
+        })
+    }
+}
+impl<A, B, S, W> TailRec<A, B> for BuildWriter<W, S>
+where
+    S: TailRec<(A, W), (B, W)> + Bind<(Step<A, B>, W), Step<(A, W), (B, W)>>,
+    W: Monoid,
+{
+    fn tail_rec<F>(init: A, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<Step<A, B>>>::R + 'static,
+    {
+        S::tail_rec((init, W::default()), move |(state, log_a)| {
+            S::bind(func(state), move |(step, log_b)| {
+                let log = log_a.op(&log_b);
+                <S as Ap<Step<(A, W), (B, W)>>>::build(match step {
+                    Step::Loop(next) => Step::Loop((next, log)),
+                    Step::Done(result) => Step::Done((result, log)),
+                })
+            })
+        })
+    }
+}
+impl<A, B, S, E> TailRec<A, B> for BuildError<E, S>
+where
+    S: TailRec<A, Result<B, E>> + Bind<Result<Step<A, B>, E>, Step<A, Result<B, E>>>,
+{
+    fn tail_rec<F>(init: A, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<Step<A, B>>>::R + 'static,
+    {
+        S::tail_rec(init, move |state| {
+            S::bind(func(state), |step| {
+                <S as Ap<Step<A, Result<B, E>>>>::build(match step {
+                    Ok(Step::Loop(next)) => Step::Loop(next),
+                    Ok(Step::Done(result)) => Step::Done(Ok(result)),
+                    Err(err) => Step::Done(Err(err)),
+                })
+            })
+        })
+    }
+}
+impl<A, B, S, E> TailRec<A, B> for BuildValidation<E, S>
+where
+    S: TailRec<A, Result<B, E>> + Bind<Result<Step<A, B>, E>, Step<A, Result<B, E>>>,
+{
+    fn tail_rec<F>(init: A, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<Step<A, B>>>::R + 'static,
+    {
+        <BuildError<E, S> as TailRec<A, B>>::tail_rec(init, func)
+    }
+}
+impl<A, B, S> TailRec<A, B> for BuildOption<S>
+where
+    S: TailRec<A, Option<B>> + Bind<Option<Step<A, B>>, Step<A, Option<B>>>,
+{
+    fn tail_rec<F>(init: A, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A) -> <Self as Ap<Step<A, B>>>::R + 'static,
+    {
+        S::tail_rec(init, move |state| {
+            S::bind(func(state), |step| {
+                <S as Ap<Step<A, Option<B>>>>::build(match step {
+                    Some(Step::Loop(next)) => Step::Loop(next),
+                    Some(Step::Done(result)) => Step::Done(Some(result)),
+                    None => Step::Done(None),
+                })
+            })
+        })
+    }
+}
+/// Every branch is explored depth first, using a stack of pending steps in
+/// place of the native stack, so the results are in the same order as with
+/// `and_then`.
+impl<A, B> TailRec<A, B> for BuildList {
+    fn tail_rec<F>(init: A, func: F) -> Vec<B>
+    where
+        F: Fn(A) -> Vec<Step<A, B>> + 'static,
+    {
+        let mut results = Vec::new();
+        let mut pending = vec![Step::Loop(init)];
+        while let Some(step) = pending.pop() {
+            match step {
+                Step::Loop(state) => pending.extend(func(state).into_iter().rev()),
+                Step::Done(result) => results.push(result),
+            }
+        }
+        results
+    }
+}
+
+/// `TailRecM` is an action which loops in constant stack. Each iteration runs
+/// the action produced by `func`, until it produces `Step::Done`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TailRecM<A, ActStep, F> {
+    pub(super) init: A,
+    pub(super) func: F,
+    _act_step: PhantomData<fn() -> ActStep>,
+}
+pub fn tail_rec_m<A, B, ActStep, F>(init: A, func: F) -> TailRecM<A, ActStep, F>
+where
+    ActStep: Action<Step<A, B>>,
+    F: Fn(&A) -> ActStep,
+{
+    TailRecM {
+        init,
+        func,
+        _act_step: PhantomData,
+    }
+}
+impl<A, B, ActStep, F> Mappable<B> for TailRecM<A, ActStep, F>
+where
+    ActStep: Mappable<Step<A, B>>,
+    F: Fn(&A) -> ActStep,
+{
+}
+impl<A, B, ActStep, F> Sequential<B> for TailRecM<A, ActStep, F>
+where
+    ActStep: Sequential<Step<A, B>>,
+    F: Fn(&A) -> ActStep,
+{
+}
+impl<A, B, ActStep, F> Action<B> for TailRecM<A, ActStep, F>
+where
+    ActStep: Action<Step<A, B>>,
+    F: Fn(&A) -> ActStep,
+{
+}
+/// The loop is run by the scaffold's `TailRec` instance, so there is one run
+/// instance for every scaffold.
+impl<S, A, B, ActStep, F> Run<S, B> for TailRecM<A, ActStep, F>
+where
+    S: TailRec<A, B>,
+    A: Clone,
+    ActStep: Run<S, Step<A, B>>,
+    F: Fn(&A) -> ActStep + Clone + 'static,
+{
+    fn run(&self) -> <S as Ap<B>>::R {
+        let func = self.func.clone();
+        S::tail_rec(self.init.clone(), move |state| func(&state).run())
+    }
+}
+
+/// A value produced while running a `Program`, with its type erased.
+pub type Value = Rc<dyn Any>;
+type Continue<S> = Rc<dyn Fn(&Value) -> Rc<Instr<S>>>;
+
+/// A `Program<S, A>` can only produce an `A`, since `pure` is given one,
+/// `effect` runs an action of type `A`, and `bind` is given a function which
+/// makes a `Program<S, A>`. Each function is only given the result of the
+/// program it was bound to, so the value always has the type it expects, and
+/// this can't fail.
+fn downcast<A>(value: &Value) -> &A
+where
+    A: 'static,
+{
+    value
+        .downcast_ref()
+        .expect("a step of a program produced a value of the wrong type")
+}
+
+/// An action turned into data, so that `run_trampolined` can run it with a
+/// loop instead of nesting a call to `run` for every bind. It produces a value
+/// of type `A`.
+pub struct Program<S, A>(Rc<Instr<S>>, PhantomData<fn() -> A>);
+/// The instructions of a program, which don't know the types of their results,
+/// so that the loop can run them one after another.
+enum Instr<S> {
+    Pure(Value),
+    /// An action with no binds inside it, which is run with `run`.
+    Effect(Box<dyn Effect<S>>),
+    Bind(Rc<Instr<S>>, Continue<S>),
+}
+impl<S, A> Clone for Program<S, A> {
+    fn clone(&self) -> Self {
+        Program(self.0.clone(), PhantomData)
+    }
+}
+impl<S, A> Program<S, A>
+where
+    A: 'static,
+{
+    fn pure(value: A) -> Self {
+        Program(Rc::new(Instr::Pure(Rc::new(value))), PhantomData)
+    }
+    fn effect<Act>(act: Shared<Act>) -> Self
+    where
+        Act: 'static,
+        Leaf<A, Act>: Effect<S>,
+    {
+        Program(
+            Rc::new(Instr::Effect(Box::new(Leaf {
+                act,
+                _value: PhantomData,
+            }))),
+            PhantomData,
+        )
+    }
+    fn bind<B, F>(self, func: F) -> Program<S, B>
+    where
+        F: Fn(&A) -> Program<S, B> + 'static,
+    {
+        Program(
+            Rc::new(Instr::Bind(
+                self.0,
+                Rc::new(move |value: &Value| func(downcast(value)).0),
+            )),
+            PhantomData,
+        )
+    }
+}
+
+/// The functions still to be applied once the current program finishes, most
+/// recent first. This is the stack which `run` would have used.
+struct Stack<S>(Option<Rc<Frame<S>>>);
+struct Frame<S> {
+    func: Continue<S>,
+    rest: Stack<S>,
+}
+impl<S> Stack<S> {
+    fn push(&self, func: Continue<S>) -> Self {
+        Stack(Some(Rc::new(Frame {
+            func,
+            rest: self.clone(),
+        })))
+    }
+    fn pop(&self) -> Option<(Continue<S>, Self)> {
+        self.0
+            .as_ref()
+            .map(|frame| (frame.func.clone(), frame.rest.clone()))
+    }
+}
+impl<S> Clone for Stack<S> {
+    fn clone(&self) -> Self {
+        Stack(self.0.clone())
+    }
+}
+/// Dropping the frames one at a time, like `Collected`.
+impl<S> Drop for Stack<S> {
+    fn drop(&mut self) {
+        let mut next = self.0.take();
+        while let Some(frame) = next {
+            next = match Rc::try_unwrap(frame) {
+                Ok(mut frame) => frame.rest.0.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+/// The state of `run_trampolined` between two effects: the program to run
+/// next, and the stack of functions to apply to its result.
+pub struct Machine<S> {
+    program: Rc<Instr<S>>,
+    stack: Stack<S>,
+}
+impl<S> Clone for Machine<S> {
+    fn clone(&self) -> Self {
+        Machine {
+            program: self.program.clone(),
+            stack: self.stack.clone(),
+        }
+    }
+}
+
+/// Run the pure steps of a program until it reaches an effect, or finishes.
+/// The effect is then run, and its result becomes the next iteration of the
+/// scaffold's `TailRec` loop.
+fn step<S>(machine: Machine<S>) -> <S as Ap<Step<Machine<S>, Value>>>::R
+where
+    S: Ap<Step<Machine<S>, Value>>,
+{
+    let Machine {
+        mut program,
+        mut stack,
+    } = machine;
+    loop {
+        let instr = program.clone();
+        match &*instr {
+            Instr::Pure(value) => match stack.pop() {
+                Some((func, rest)) => {
+                    program = func(value);
+                    stack = rest;
+                }
+                None => return S::build(Step::Done(value.clone())),
+            },
+            Instr::Bind(inner, func) => {
+                stack = stack.push(func.clone());
+                program = inner.clone();
+            }
+            Instr::Effect(effect) => return effect.resume(stack),
+        }
+    }
+}
+
+trait Effect<S> {
+    fn resume(&self, stack: Stack<S>) -> <S as Ap<Step<Machine<S>, Value>>>::R
+    where
+        S: Ap<Step<Machine<S>, Value>>;
+}
+struct Leaf<A, Act> {
+    act: Shared<Act>,
+    _value: PhantomData<fn() -> A>,
+}
+impl<S, A, Act> Effect<S> for Leaf<A, Act>
+where
+    S: Ap<Step<Machine<S>, Value>>,
+    Map<A, Shared<Act>, Resume<S>>: Run<S, Step<Machine<S>, Value>>,
+{
+    fn resume(&self, stack: Stack<S>) -> <S as Ap<Step<Machine<S>, Value>>>::R
+    where
+        S: Ap<Step<Machine<S>, Value>>,
+    {
+        then_step(self.act.clone(), Resume { stack }).run()
+    }
+}
+
+/// Continues the program with the result of an effect.
+pub struct Resume<S> {
+    stack: Stack<S>,
+}
+impl<S> Clone for Resume<S> {
+    fn clone(&self) -> Self {
+        Resume {
+            stack: self.stack.clone(),
+        }
+    }
+}
+impl<S, A> MapFn<A, Step<Machine<S>, Value>> for Resume<S>
+where
+    A: Clone + 'static,
+{
+    fn call(&self, value: &A) -> Step<Machine<S>, Value> {
+        Step::Loop(Machine {
+            program: Program::<S, A>::pure(value.clone()).0,
+            stack: self.stack.clone(),
+        })
+    }
+}
+
+/// The result of a program, which `run_trampolined` turns back into the type
+/// of the action. It can only be run once, but only `run_trampolined` makes
+/// one, and it runs it straight away through a `Map`, which runs the action it
+/// maps once under every scaffold, so the result is never taken twice.
+pub struct Finished<S>(RefCell<Option<<S as Ap<Value>>::R>>)
+where
+    S: Ap<Value>;
+impl<S> Mappable<Value> for Finished<S> where S: Ap<Value> {}
+impl<S> Sequential<Value> for Finished<S> where S: Ap<Value> {}
+impl<S> Action<Value> for Finished<S> where S: Ap<Value> {}
+impl<S> Run<S, Value> for Finished<S>
+where
+    S: Ap<Value>,
+{
+    fn run(&self) -> S::R {
+        self.0
+            .borrow_mut()
+            .take()
+            .expect("the result of a program was run twice")
+    }
+}
+
+/// Gets the result of a program back from its `Value`.
+pub struct Downcast<A>(PhantomData<fn() -> A>);
+impl<A> Clone for Downcast<A> {
+    fn clone(&self) -> Self {
+        Downcast(PhantomData)
+    }
+}
+impl<A> MapFn<Value, A> for Downcast<A>
+where
+    A: Clone + 'static,
+{
+    fn call(&self, value: &Value) -> A {
+        downcast::<A>(value).clone()
+    }
+}
+
+/// Actions which can be run by a trampoline, so that deep chains of
+/// `and_then`, such as an action which binds to itself recursively, run in
+/// constant stack.
+///
+/// `run` nests a call to `Bind::bind` for every `and_then` and `map`.
+/// `run_trampolined` instead turns the action into a `Program`, and runs its
+/// binds one at a time in a loop, with the functions still to be applied kept
+/// on the heap. Each action with no binds inside it is run with `run`, and the
+/// loop is the scaffold's `TailRec`.
+///
+/// The results of the steps are passed along as `Rc<dyn Any>`, so they must be
+/// `Clone + 'static`.
+pub trait Trampolined<S, A>: Action<A> {
+    fn program(self) -> Program<S, A>;
+    fn run_trampolined(self) -> <S as Ap<A>>::R
+    where
+        S: TailRec<Machine<S>, Value> + Ap<A> + 'static,
+        A: Clone + 'static,
+        Map<Value, Finished<S>, Downcast<A>>: Run<S, A>,
+    {
+        let machine = Machine {
+            program: self.program().0,
+            stack: Stack(None),
+        };
+        let result = S::tail_rec(machine, step::<S>);
+        then_step(Finished(RefCell::new(Some(result))), Downcast(PhantomData)).run()
+    }
+    fn run_trampolined_qualified(self, _scaffold: &S) -> <S as Ap<A>>::R
+    where
+        S: TailRec<Machine<S>, Value> + Ap<A> + 'static,
+        A: Clone + 'static,
+        Map<Value, Finished<S>, Downcast<A>>: Run<S, A>,
+    {
+        self.run_trampolined()
+    }
+    /// Box the action like `Boxed::boxed`, but so that `run_trampolined` can
+    /// still take it apart, rather than running it as a single effect.
+    fn boxed_trampolined(self) -> BoxAction<S, A>
+    where
+        S: Ap<A>,
+        Self: Run<S, A> + 'static,
+    {
+        BoxAction {
+            action: Box::new(Parts(self)),
+        }
+    }
+    /// Share the action like `Boxed::shared`, but so that `run_trampolined`
+    /// can still take it apart once it is no longer shared.
+    fn shared_trampolined(self) -> RcAction<S, A>
+    where
+        S: Ap<A>,
+        Self: Run<S, A> + 'static,
+    {
+        RcAction {
+            action: Rc::new(Parts(self)),
+        }
+    }
+}
+
+impl<S, A> Trampolined<S, A> for Pure<A>
+where
+    A: 'static,
+{
+    fn program(self) -> Program<S, A> {
+        Program::pure(self.value)
+    }
+}
+
+impl<S, A, B, ActA, F> Trampolined<S, B> for Map<A, ActA, F>
+where
+    A: 'static,
+    B: 'static,
+    ActA: Trampolined<S, A>,
+    F: MapFn<A, B> + 'static,
+{
+    fn program(self) -> Program<S, B> {
+        let func = self.func;
+        self.act_a
+            .program()
+            .bind(move |result_a| Program::pure(func.call(result_a)))
+    }
+}
+
+impl<S, A, B, ActA, ActB, F> Trampolined<S, B> for AndThen<A, ActA, ActB, F>
+where
+    A: 'static,
+    ActA: Trampolined<S, A>,
+    ActB: Trampolined<S, B>,
+    F: Fn(&A) -> ActB + 'static,
+{
+    fn program(self) -> Program<S, B> {
+        let func = self.func;
+        self.act_a
+            .program()
+            .bind(move |result_a| func(result_a).program())
+    }
+}
+
+impl<S, A, L, R> Trampolined<S, A> for EitherAction<L, R>
+where
+    L: Trampolined<S, A>,
+    R: Trampolined<S, A>,
+{
+    fn program(self) -> Program<S, A> {
+        match self {
+            EitherAction::Left(act_l) => act_l.program(),
+            EitherAction::Right(act_r) => act_r.program(),
+        }
+    }
+}
+
+/// An action boxed by `Boxed::boxed` can't be taken apart, so it is run as a
+/// single effect.
+impl<S, A> Trampolined<S, A> for BoxAction<S, A>
+where
+    S: Ap<A> + Ap<Step<Machine<S>, Value>> + 'static,
+    A: Clone + 'static,
+    Map<A, Shared<Self>, Resume<S>>: Run<S, Step<Machine<S>, Value>>,
+{
+    fn program(self) -> Program<S, A> {
+        match self.action.dyn_program() {
+            Ok(program) => program,
+            Err(action) => Program::effect(Shared(Rc::new(BoxAction { action }))),
+        }
+    }
+}
+
+/// If the action is still shared, or was shared by `Boxed::shared`, it can't
+/// be taken apart, so it is run as a single effect.
+impl<S, A> Trampolined<S, A> for RcAction<S, A>
+where
+    S: Ap<A> + Ap<Step<Machine<S>, Value>> + 'static,
+    A: Clone + 'static,
+    Map<A, Shared<Self>, Resume<S>>: Run<S, Step<Machine<S>, Value>>,
+{
+    fn program(self) -> Program<S, A> {
+        match self.action.dyn_program_unique() {
+            Ok(program) => program,
+            Err(action) => Program::effect(Shared(Rc::new(RcAction { action }))),
+        }
+    }
+}
+
+/// Actions with no binds inside them are run as a single effect.
+macro_rules! trampoline_effect {
+    ($(impl<$($param:ident),*> for $action:ty $(where $bound:ident: $trait:path)?;)*) => {
+        $(
+            impl<S, Out, $($param),*> Trampolined<S, Out> for $action
+            where
+                $($bound: $trait,)?
+                S: Ap<Step<Machine<S>, Value>>,
+                Self: Action<Out> + 'static,
+                Out: Clone + 'static,
+                Map<Out, Shared<Self>, Resume<S>>: Run<S, Step<Machine<S>, Value>>,
+            {
+                fn program(self) -> Program<S, Out> {
+                    Program::effect(Shared(Rc::new(self)))
+                }
+            }
+        )*
+    };
+}
+
+trampoline_effect! {
+    impl<A> for Literal<S, A> where S: Ap<A>;
+    impl<ActA, ActB> for Sequence<ActA, ActB>;
+    impl<A, B, ActA, ActB, F> for Combine<A, B, ActA, ActB, F>;
+    impl<B, ActA, ActB> for First<B, ActA, ActB>;
+    impl<A, ActA, ActB> for Second<A, ActA, ActB>;
+    impl<A, W> for Writer<A, W>;
+    impl<W> for Tell<W>;
+    impl<A, W, ActA> for Listen<A, W, ActA>;
+    impl<F, ActA> for Pass<F, ActA>;
+    impl<ActA, F> for Censor<ActA, F>;
+    impl<A, E> for ThrowError<A, E>;
+    impl<E, ActA, ActB, F> for CatchError<E, ActA, ActB, F>;
+    impl<A> for Nothing<A>;
+    impl<A> for FromOption<A>;
+    impl<ActA, ActB, F> for OrElse<ActA, ActB, F>;
+    impl<A> for Choose<A>;
+    impl<> for Guard;
+    impl<St> for Get<St>;
+    impl<St> for Put<St>;
+    impl<St, F> for Modify<St, F>;
+    impl<St, F> for Gets<St, F>;
+    impl<Env> for Ask<Env>;
+    impl<Env, F> for Asks<Env, F>;
+    impl<Env, ActA, F> for Local<Env, ActA, F>;
+    impl<Res, A, B> for Jump<Res, A, B>;
+    impl<Res, A, ActA, F> for CallCc<Res, A, ActA, F>;
+    impl<A, ActStep, F> for TailRecM<A, ActStep, F>;
+    impl<T, ActB, F> for Traverse<T, ActB, F>;
+    impl<ActA> for SequenceVec<ActA>;
+    impl<ActA> for ReplicateM<ActA>;
+    impl<T, B, ActB, F> for ForEach<T, B, ActB, F>;
+    impl<T, Acc, ActAcc, F> for FoldM<T, Acc, ActAcc, F>;
+    impl<ActCond, ActA> for WhileM<ActCond, ActA>;
+    impl<F> for FromFuture<F>;
+    impl<K, V, ActA, C> for Memo<K, V, ActA, C>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::monoid::Sum;
+    use super::super::{
+        block_on, choose, from_future, from_option, get, modify, pure, throw_error, writer,
+        BoxFuture, BuildCont, BuildFuture, BuildReader, BuildState, Pure,
+    };
+    use super::super::{BoxAction, Boxed};
+    use super::*;
+
+    const STEPS: u32 = 1_000_000;
+
+    fn count(n: &u32) -> Pure<Step<u32, u32>> {
+        pure(if *n < STEPS {
+            Step::Loop(n + 1)
+        } else {
+            Step::Done(*n)
+        })
+    }
+
+    #[test]
+    fn million_steps() {
+        let writer_type: PhantomData<fn() -> Sum<u64>> = PhantomData;
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+
+        let action = tail_rec_m(0, count);
+        let action2 = tail_rec_m(0, |&n| {
+            writer(
+                if n < STEPS {
+                    Step::Loop(n + 1)
+                } else {
+                    Step::Done(n)
+                },
+                Sum(u64::from(n)),
+            )
+        });
+
+        assert_eq!(action.run_qualified(&()), STEPS);
+        assert_eq!(action.run_qualified(&BuildId(())), Identity(STEPS));
+        assert_eq!(
+            action2.run_qualified(&BuildWriter(writer_type, ())),
+            (STEPS, Sum(500_000_500_000))
+        );
+        assert_eq!(
+            action.run_qualified(&BuildError(err_type, BuildOption(()))),
+            Some(Ok(STEPS))
+        );
+        assert_eq!(action.run_qualified(&BuildList()), vec![STEPS]);
+    }
+
+    #[test]
+    fn loop_stops_without_value() {
+        let scaffold = BuildOption(());
+
+        let action = tail_rec_m(0, |&n| {
+            from_option(if n < 10 {
+                Some(Step::Loop(n + 1))
+            } else {
+                None::<Step<u32, u32>>
+            })
+        });
+
+        assert_eq!(action.run_qualified(&scaffold), None);
+    }
+
+    #[test]
+    fn list_loops_in_order() {
+        let action = tail_rec_m(1, |&n| {
+            choose(if n < 4 {
+                vec![Step::Loop(n * 2), Step::Loop(n * 2 + 1)]
+            } else {
+                vec![Step::Done(n)]
+            })
+        });
+
+        assert_eq!(action.run_qualified(&BuildList()), vec![4, 5, 6, 7]);
+    }
+
+    fn countdown(n: u32) -> BoxAction<BuildWriter<Sum<u64>>, u32> {
+        if n == 0 {
+            pure(0).boxed_trampolined()
+        } else {
+            writer(n - 1, Sum(1))
+                .and_then(|&n| countdown(n))
+                .boxed_trampolined()
+        }
+    }
+
+    fn count_up(n: u32) -> BoxAction<BuildState<'static, u64>, u64> {
+        modify(|total: &u64| total + 1)
+            .and_then(move |_| {
+                if n == 0 {
+                    EitherAction::Left(get())
+                } else {
+                    EitherAction::Right(count_up(n - 1))
+                }
+            })
+            .boxed_trampolined()
+    }
+
+    fn sum_to(n: u64) -> BoxAction<BuildFuture, u64> {
+        if n == 0 {
+            pure(0).boxed_trampolined()
+        } else {
+            from_future(move || async move { n })
+                .and_then(move |&x| sum_to(n - 1).map(move |total| total + x))
+                .boxed_trampolined()
+        }
+    }
+
+    #[test]
+    fn million_recursive_binds() {
+        let writer_type: PhantomData<fn() -> Sum<u64>> = PhantomData;
+        let state_scaffold: BuildState<u64> = BuildState(PhantomData);
+
+        let future: BoxFuture<u64> = sum_to(u64::from(STEPS)).run_trampolined();
+
+        assert_eq!(
+            countdown(STEPS).run_trampolined_qualified(&BuildWriter(writer_type, ())),
+            (0, Sum(u64::from(STEPS)))
+        );
+        assert_eq!(
+            count_up(STEPS)
+                .run_trampolined_qualified(&state_scaffold)
+                .run_state(0),
+            (u64::from(STEPS) + 1, u64::from(STEPS) + 1)
+        );
+        assert_eq!(block_on(future), 500_000_500_000);
+    }
+
+    /// The result of a program would be taken twice if `Finished` were run
+    /// twice, which would panic, so a program is run with each kind of
+    /// scaffold.
+    #[test]
+    fn programs_finish_under_every_scaffold() {
+        let err_type: PhantomData<fn() -> Vec<&'static str>> = PhantomData;
+        let reader_scaffold: BuildReader<()> = BuildReader(PhantomData);
+        let res_type: PhantomData<fn() -> u32> = PhantomData;
+
+        let action = || pure(3).and_then(|&x| pure(x + 1)).map(|x| x * 2);
+
+        assert_eq!(action().run_trampolined_qualified(&()), 8);
+        assert_eq!(
+            action().run_trampolined_qualified(&BuildId(())),
+            Identity(8)
+        );
+        assert_eq!(
+            action().run_trampolined_qualified(&BuildOption(())),
+            Some(8)
+        );
+        assert_eq!(action().run_trampolined_qualified(&BuildList()), vec![8]);
+        assert_eq!(
+            action().run_trampolined_qualified(&BuildValidation(err_type, ())),
+            Ok(8)
+        );
+        assert_eq!(
+            action()
+                .run_trampolined_qualified(&reader_scaffold)
+                .run_reader(&()),
+            8
+        );
+        assert_eq!(
+            action()
+                .run_trampolined_qualified(&BuildCont(res_type))
+                .eval_cont(),
+            8
+        );
+    }
+
+    #[test]
+    fn trampolined_errors_stop() {
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, ());
+
+        let action = pure(3)
+            .and_then(|&x| throw_error::<u32, _>("failed").map(move |y| x + y))
+            .and_then(|&x| pure(x * 2));
+        let action2 = pure(3).and_then(|&x| pure(x + 1)).map(|x| x * 2);
+
+        assert_eq!(action.run_trampolined_qualified(&scaffold), Err("failed"));
+        assert_eq!(action2.run_trampolined_qualified(&scaffold), Ok(8));
+    }
+
+    /// Not `Clone`, so it can't be passed between the steps of a program.
+    struct Opaque(u32);
+
+    #[test]
+    fn boxed_actions_are_effects() {
+        let writer_type: PhantomData<fn() -> Sum<u64>> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let step: BoxAction<BuildWriter<Sum<u64>>, u32> = writer(2, Sum(5))
+            .map(|&x| Opaque(x))
+            .map(|opaque| opaque.0 + 1)
+            .boxed();
+        let action = step.and_then(|&x| countdown(x));
+
+        assert_eq!(action.run_trampolined_qualified(&scaffold), (0, Sum(8)));
+    }
+
+    #[test]
+    fn shared_actions_are_effects() {
+        let writer_type: PhantomData<fn() -> Sum<u64>> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let step = writer(2, Sum(5)).shared();
+        let step2 = step.clone();
+        let action = step.and_then(move |&x| step2.clone().map(move |y| x + y));
+        let action2 = countdown(3).shared();
+
+        assert_eq!(action.run_trampolined_qualified(&scaffold), (4, Sum(10)));
+        assert_eq!(action2.run_trampolined_qualified(&scaffold), (0, Sum(3)));
+    }
+}
@@ -1,308 +1,270 @@
 use std::marker::PhantomData;
 
 use super::{
-    Action, AndThen, BaseAction, Combine, First, Map, Mappable, Second, Sequence, Sequential,
+    Action, Ap, BaseWriter, Bind, BuildId, BuildWriter, Identity, Mappable, Run, Sequential,
 };
 
-struct WriterTrans<'r, A, Act, W> {
-    action: Act,
-    _result_type: PhantomData<&'r (A, W)>,
+/// `Tell` is an action which appends to the log without producing a value.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tell<W> {
+    log: W,
 }
-
-pub trait Monoid: Default {
-    fn op(&self, other: &Self) -> Self;
+pub fn tell<W>(log: W) -> Tell<W> {
+    Tell { log }
+}
+impl<W> Mappable<()> for Tell<W> {}
+impl<W> Sequential<()> for Tell<W> {}
+impl<W> Action<()> for Tell<W> {}
+impl<S, W> Run<S, ()> for Tell<W>
+where
+    S: BaseWriter<(), W>,
+    W: Clone,
+{
+    fn run(&self) -> S::R {
+        S::writer((), self.log.clone())
+    }
 }
 
-trait Writer<A, W>: Action<A> {
-    fn unwrap_writer(&self) -> (A, W);
+/// Scaffolds which can read and rewrite the log written while completing a
+/// value. `listen`, `pass` and `censor` all run through this, so like `tell`
+/// they work with any scaffold which contains a `BuildWriter`.
+pub trait BaseListen<A, B>: Ap<A> + Ap<B> {
+    /// The type of the log, which is the `W` of the `BuildWriter` inside.
+    type Log;
+    /// Complete `value`, then pass its result and the log it wrote to `func`,
+    /// which produces the result and the log to write in their place. If
+    /// `value` completed without a result, such as an error, its log is kept
+    /// as it is.
+    fn listen<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
+    where
+        F: Fn(A, Self::Log) -> (B, Self::Log);
 }
-trait BaseWriter<A, W>: BaseAction<A>
+impl<A, B, S, W> BaseListen<A, B> for BuildWriter<W, S>
 where
-    Self: Sized,
+    S: Bind<(A, W), (B, W)>,
+    W: Default,
 {
-    fn writer(value: A, log: W) -> Self;
-    fn pass<ActA, F>(action: ActA) -> Self
+    type Log = W;
+    fn listen<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
     where
-        ActA: Writer<(A, F), W>,
-        F: Fn(&W) -> W,
+        F: Fn(A, Self::Log) -> (B, Self::Log),
     {
-        let ((value, func), log) = action.unwrap_writer();
-        Self::writer(value, func(&log))
-    }
-}
-trait BaseWriterTell<W>: BaseWriter<(), W> {
-    fn tell(log: W) -> Self {
-        Self::writer((), log)
+        S::bind(value, |(result, log)| {
+            <S as Ap<(B, W)>>::build(func(result, log))
+        })
     }
 }
-trait BaseWriterListen<A, W>: BaseWriter<(A, W), W>
+impl<A, B, S> BaseListen<A, B> for BuildId<S>
 where
-    W: Clone,
+    S: BaseListen<Identity<A>, Identity<B>>,
 {
-    fn listen<ActA>(action: ActA) -> Self
+    type Log = S::Log;
+    fn listen<F>(value: <Self as Ap<A>>::R, func: F) -> <Self as Ap<B>>::R
     where
-        ActA: Writer<A, W>,
+        F: Fn(A, Self::Log) -> (B, Self::Log),
     {
-        let (value, log) = action.unwrap_writer();
-        Self::writer((value, log.clone()), log)
+        S::listen(value, |Identity(result), log| {
+            let (result, log) = func(result, log);
+            (Identity(result), log)
+        })
     }
 }
 
-trait WriterT<A, Act, W>: Action<A>
-where
-    Act: Action<(A, W)>,
-{
-    fn unwrap_writer_t(&self) -> Act;
+/// `Listen` runs an action, and produces the log it wrote alongside its
+/// result. The log is still written as usual.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Listen<A, W, ActA> {
+    act_a: ActA,
+    _act_a_type: PhantomData<fn() -> (A, W)>,
 }
-trait BaseWriterT<A, Act, W>: BaseAction<A>
+pub fn listen<A, W, ActA>(act_a: ActA) -> Listen<A, W, ActA>
 where
-    Act: BaseAction<(A, W)>,
+    ActA: Action<A>,
 {
-    fn writer_t(action: Act) -> Self;
-}
-trait BaseWriterTellT<Act, W>: BaseWriterT<(), Act, W>
-where
-    Act: BaseAction<((), W)>,
-{
-    fn tell(log: W) -> Self {
-        Self::writer_t(Act::pure(((), log)))
-    }
-}
-
-impl Monoid for () {
-    fn op (&self, other: &Self) -> () {
-        ()
+    Listen {
+        act_a,
+        _act_a_type: PhantomData,
     }
 }
-
-impl<A, W> Mappable<A> for (A, W) {}
-impl<A, W> Sequential<A> for (A, W) where W: Monoid {}
-impl<A, W> Action<A> for (A, W) where W: Monoid {}
-
-impl<A, W> BaseAction<A> for (A, W)
+impl<A, ActA, W> Mappable<(A, W)> for Listen<A, W, ActA> where ActA: Mappable<A> {}
+impl<A, ActA, W> Sequential<(A, W)> for Listen<A, W, ActA> where ActA: Sequential<A> {}
+impl<A, ActA, W> Action<(A, W)> for Listen<A, W, ActA> where ActA: Action<A> {}
+impl<A, ActA, S, W> Run<S, (A, W)> for Listen<A, W, ActA>
 where
-    W: Monoid,
+    S: BaseListen<A, (A, W), Log = W>,
+    W: Clone,
+    ActA: Run<S, A>,
 {
-    fn pure(value: A) -> Self {
-        (value, W::default())
+    fn run(&self) -> <S as Ap<(A, W)>>::R {
+        S::listen(self.act_a.run(), |result_a, log| {
+            ((result_a, log.clone()), log)
+        })
     }
 }
 
-impl<A, W> Writer<A, W> for (A, W)
-where
-    W: Monoid,
-    Self: Clone,
-{
-    fn unwrap_writer(&self) -> (A, W) {
-        self.clone()
-    }
+/// `Pass` runs an action which produces a value together with a function, and
+/// uses the function to rewrite the log the action wrote.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pass<F, ActA> {
+    act_a: ActA,
+    _func_type: PhantomData<fn() -> F>,
 }
-impl<A, W> BaseWriter<A, W> for (A, W)
+pub fn pass<A, F, ActA>(act_a: ActA) -> Pass<F, ActA>
 where
-    W: Monoid,
+    ActA: Action<(A, F)>,
 {
-    fn writer(value: A, log: W) -> Self {
-        (value, log)
+    Pass {
+        act_a,
+        _func_type: PhantomData,
     }
 }
-impl<W> BaseWriterTell<W> for ((), W) where W: Monoid {}
-impl<A, W> BaseWriterListen<A, W> for ((A, W), W) where W: Monoid + Clone {}
-
-impl<'a, A, B, ActA, W, F> Writer<B, W> for Map<'a, A, ActA, F>
+impl<A, F, ActA> Mappable<A> for Pass<F, ActA> where ActA: Mappable<(A, F)> {}
+impl<A, F, ActA> Sequential<A> for Pass<F, ActA> where ActA: Sequential<(A, F)> {}
+impl<A, F, ActA> Action<A> for Pass<F, ActA> where ActA: Action<(A, F)> {}
+impl<A, F, ActA, S, W> Run<S, A> for Pass<F, ActA>
 where
-    ActA: Writer<A, W>,
-    F: Fn(&A) -> B,
+    S: BaseListen<(A, F), A, Log = W>,
+    ActA: Run<S, (A, F)>,
+    F: Fn(&W) -> W,
 {
-    fn unwrap_writer(&self) -> (B, W) {
-        let (result, log) = self.act_a.unwrap_writer();
-        ((self.func)(&result), log)
+    fn run(&self) -> <S as Ap<A>>::R {
+        S::listen(self.act_a.run(), |(result_a, func), log| {
+            (result_a, func(&log))
+        })
     }
 }
-impl<'b, A, B, ActA, ActB, W> Writer<A, W> for First<'b, B, ActA, ActB>
-where
-    W: Monoid,
-    ActA: Writer<A, W>,
-    ActB: Writer<B, W>,
-{
-    fn unwrap_writer(&self) -> (A, W) {
-        let (result, log_a) = self.act_a.unwrap_writer();
-        let (_, log_b) = self.act_b.unwrap_writer();
-        (result, log_a.op(&log_b))
-    }
+
+/// `Censor` runs an action, and rewrites the log it wrote using a function.
+/// Only the log of the wrapped action is rewritten, so `censor` can be used to
+/// scope changes to part of a computation.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Censor<ActA, F> {
+    act_a: ActA,
+    func: F,
 }
-impl<'a, A, B, ActA, ActB, W> Writer<B, W> for Second<'a, A, ActA, ActB>
+pub fn censor<A, W, ActA, F>(func: F, act_a: ActA) -> Censor<ActA, F>
 where
-    W: Monoid,
-    ActA: Writer<A, W>,
-    ActB: Writer<B, W>,
+    ActA: Action<A>,
+    F: Fn(&W) -> W,
 {
-    fn unwrap_writer(&self) -> (B, W) {
-        let (_, log_a) = self.act_a.unwrap_writer();
-        let (result, log_b) = self.act_b.unwrap_writer();
-        (result, log_a.op(&log_b))
-    }
+    Censor { act_a, func }
 }
-impl<A, B, ActA, ActB, W> Writer<(A, B), W> for Sequence<ActA, ActB>
+impl<A, ActA, F> Mappable<A> for Censor<ActA, F> where ActA: Mappable<A> {}
+impl<A, ActA, F> Sequential<A> for Censor<ActA, F> where ActA: Sequential<A> {}
+impl<A, ActA, F> Action<A> for Censor<ActA, F> where ActA: Action<A> {}
+impl<A, ActA, F, S, W> Run<S, A> for Censor<ActA, F>
 where
-    W: Monoid,
-    ActA: Writer<A, W>,
-    ActB: Writer<B, W>,
+    S: BaseListen<A, A, Log = W>,
+    ActA: Run<S, A>,
+    F: Fn(&W) -> W,
 {
-    fn unwrap_writer(&self) -> ((A, B), W) {
-        let (result_a, log_a) = self.0.unwrap_writer();
-        let (result_b, log_b) = self.1.unwrap_writer();
-        ((result_a, result_b), log_a.op(&log_b))
+    fn run(&self) -> <S as Ap<A>>::R {
+        S::listen(self.act_a.run(), |result_a, log| {
+            (result_a, (self.func)(&log))
+        })
     }
 }
-impl<'a, 'b, A, B, C, ActA, ActB, F, W> Writer<C, W> for Combine<'a, 'b, A, B, ActA, ActB, F>
-where
-    W: Monoid,
-    ActA: Writer<A, W>,
-    ActB: Writer<B, W>,
-    F: Fn(&A, &B) -> C,
-{
-    fn unwrap_writer(&self) -> (C, W) {
-        let (result_a, log_a) = self.act_a.unwrap_writer();
-        let (result_b, log_b) = self.act_b.unwrap_writer();
-        ((self.func)(&result_a, &result_b), log_a.op(&log_b))
+
+#[cfg(test)]
+mod test {
+    use super::super::monoid::Sum;
+    use super::super::{none, pure, throw_error, writer, BuildError, BuildOption};
+    use super::*;
+
+    #[test]
+    fn tell_appends() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = tell(3).and_then(|_| writer(5, 4)).and_then(|&x| tell(x));
+
+        assert_eq!(action.run_qualified(&scaffold), ((), 12));
     }
-}
 
-impl<'a, 'ab, A, B, ActA, ActB, F, W> Writer<B, W> for AndThen<'a, 'ab, A, ActA, ActB, F>
-where
-    W: Monoid,
-    ActA: Writer<A, W>,
-    ActB: Writer<B, W>,
-    F: Fn(&A) -> ActB,
-{
-    fn unwrap_writer(&self) -> (B, W) {
-        let (result_a, log_a) = self.act_a.unwrap_writer();
-        let (result_b, log_b) = (self.func)(&result_a).unwrap_writer();
-        (result_b, log_a.op(&log_b))
+    #[test]
+    fn listen_sees_inner_log() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = tell(1).and_then(|_| listen(writer(5, 4).and_then(|_| tell(2))));
+
+        assert_eq!(action.run_qualified(&scaffold), (((), 6), 7));
     }
-}
 
-// WriterT Implementations
-impl<'r, A, Act, W> Mappable<A> for WriterTrans<'r, A, Act, W> {}
-impl<'r, A, Act, W> Sequential<A> for WriterTrans<'r, A, Act, W>
-where
-    W: Monoid,
-    Act: Action<(A, W)>,
-{
-}
-impl<'r, A, Act, W> Action<A> for WriterTrans<'r, A, Act, W>
-where
-    W: Monoid,
-    Act: Action<(A, W)>,
-{
-}
-impl<'r, A, Act, W> BaseAction<A> for WriterTrans<'r, A, Act, W>
-where
-    W: Monoid,
-    Act: BaseAction<(A, W)>,
-{
-    fn pure(value: A) -> Self {
-        WriterTrans {
-            action: Act::pure((value, W::default())),
-            _result_type: PhantomData,
-        }
+    #[test]
+    fn pass_rewrites_log() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let double = |log: &u32| log * 2;
+        let action = tell(1).and_then(move |_| pass(writer((5, double), 4)));
+
+        assert_eq!(action.run_qualified(&scaffold), (5, 9));
     }
-}
-impl<'r, A, Act, W> WriterT<A, Act, W> for WriterTrans<'r, A, Act, W>
-where
-    W: Monoid,
-    Act: Action<(A, W)> + Clone,
-{
-    fn unwrap_writer_t(&self) -> Act {
-        self.action.clone()
+
+    #[test]
+    fn censor_is_scoped() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
+
+        let action = tell(1)
+            .and_then(|_| censor(|_: &u32| 0, tell(10).and_then(|_| pure(3))))
+            .and_then(|&x| writer(x, 2));
+
+        assert_eq!(action.run_qualified(&scaffold), (3, 3));
     }
-}
-impl<'r, A, Act, W> BaseWriterT<A, Act, W> for WriterTrans<'r, A, Act, W>
-where
-    W: Monoid,
-    Act: BaseAction<(A, W)>,
-{
-    fn writer_t(action: Act) -> Self {
-        WriterTrans {
-            action: action,
-            _result_type: PhantomData,
-        }
+
+    #[test]
+    fn writer_over_error() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildWriter(writer_type, BuildError(err_type, ()));
+
+        let action = listen(tell(2).and_then(|_| pure(3)));
+        let action2 = censor(
+            |log: &u32| log + 1,
+            tell(2).and_then(|_| throw_error::<i32, _>("failed")),
+        );
+
+        assert_eq!(action.run_qualified(&scaffold), Ok(((3, 2), 2)));
+        assert_eq!(action2.run_qualified(&scaffold), Err("failed"));
     }
-}
 
+    #[test]
+    fn multi_channel_log() {
+        type Log = (Sum<u32>, Vec<&'static str>);
+        let writer_type: PhantomData<fn() -> Log> = PhantomData;
+        let scaffold = BuildWriter(writer_type, ());
 
-// impl<'a, A, B, Act, ActA, W, F> WriterT<B, Act, W> for Map<'a, A, ActA, F>
-// where
-//     ActA: WriterT<A, Act, W>,
-//     F: Fn(&A) -> B,
-// {
-//     fn unwrap_writer_t(&self) -> (B, W) {
-//         let (result, log) = self.act_a.unwrap_writer_t();
-//         ((self.func)(&result), log)
-//     }
-// }
-// impl<'b, A, B, ActA, ActB, W> WriterT<A, Act, W> for First<'b, B, ActA, ActB>
-// where
-//     W: Monoid,
-//     ActA: WriterT<A, Act, W>,
-//     ActB: WriterT<B, Act, W>,
-// {
-//     fn unwrap_writer(&self) -> (A, W) {
-//         let (result, log_a) = self.act_a.unwrap_writer();
-//         let (_, log_b) = self.act_b.unwrap_writer();
-//         (result, log_a.op(&log_b))
-//     }
-// }
-// impl<'a, A, B, ActA, ActB, W> WriterT<B, Act, W> for Second<'a, A, ActA, ActB>
-// where
-//     W: Monoid,
-//     ActA: WriterT<A, Act, W>,
-//     ActB: WriterT<B, Act, W>,
-// {
-//     fn unwrap_writer(&self) -> (B, W) {
-//         let (_, log_a) = self.act_a.unwrap_writer();
-//         let (result, log_b) = self.act_b.unwrap_writer();
-//         (result, log_a.op(&log_b))
-//     }
-// }
-// impl<A, B, ActA, ActB, W> Writer<(A, B), W> for Sequence<ActA, ActB>
-// where
-//     W: Monoid,
-//     ActA: WriterT<A, Act, W>,
-//     ActB: WriterT<B, Act, W>,
-// {
-//     fn unwrap_writer(&self) -> ((A, B), W) {
-//         let (result_a, log_a) = self.0.unwrap_writer();
-//         let (result_b, log_b) = self.1.unwrap_writer();
-//         ((result_a, result_b), log_a.op(&log_b))
-//     }
-// }
-// impl<'a, 'b, A, B, C, ActA, ActB, F, W> Writer<C, W> for Combine<'a, 'b, A, B, ActA, ActB, F>
-// where
-//     W: Monoid,
-//     ActA: WriterT<A, Act, W>,
-//     ActB: WriterT<B, Act, W>,
-//     F: Fn(&A, &B) -> C,
-// {
-//     fn unwrap_writer(&self) -> (C, W) {
-//         let (result_a, log_a) = self.act_a.unwrap_writer();
-//         let (result_b, log_b) = self.act_b.unwrap_writer();
-//         ((self.func)(&result_a, &result_b), log_a.op(&log_b))
-//     }
-// }
+        let action = tell((Sum(1), vec!["start"]))
+            .and_then(|_| writer(3, (Sum(2), vec![])))
+            .and_then(|&x| tell((Sum(x), vec!["finish"])));
 
-// impl<'a, 'ab, A, B, ActA, ActB, F, W> WriterT<B, Act, W> for AndThen<'a, 'ab, A, ActA, ActB, F>
-// where
-//     W: Monoid,
-//     ActA: WriterT<A, Act, W>,
-//     ActB: Writer<B, W>,
-//     F: Fn(&A) -> ActB,
-// {
-//     fn unwrap_writer(&self) -> (B, W) {
-//         let (result_a, log_a) = self.act_a.unwrap_writer();
-//         let (result_b, log_b) = (self.func)(&result_a).unwrap_writer();
-//         (result_b, log_a.op(&log_b))
-//     }
-// }
+        assert_eq!(
+            action.run_qualified(&scaffold),
+            ((), (Sum(6), vec!["start", "finish"]))
+        );
+    }
+
+    #[test]
+    fn error_over_writer() {
+        let writer_type: PhantomData<fn() -> u32> = PhantomData;
+        let err_type: PhantomData<fn() -> &'static str> = PhantomData;
+        let scaffold = BuildError(err_type, BuildWriter(writer_type, ()));
+        let scaffold2 = BuildOption(BuildId(BuildWriter(writer_type, ())));
 
+        let double = |log: &u32| log * 2;
+        let action = listen(tell(2).and_then(|_| pure(3)));
+        let action2 = censor(
+            |log: &u32| log + 1,
+            tell(2).and_then(|_| throw_error::<i32, _>("failed")),
+        );
+        let action3 = tell(1).and_then(move |_| pass(writer((5, double), 4)));
+        let action4 = censor(|log: &u32| log + 1, tell(2).and_then(|_| none::<i32>()));
 
+        assert_eq!(action.run_qualified(&scaffold), (Ok((3, 2)), 2));
+        assert_eq!(action2.run_qualified(&scaffold), (Err("failed"), 2));
+        assert_eq!(action3.run_qualified(&scaffold), (Ok(5), 9));
+        assert_eq!(action4.run_qualified(&scaffold2), (Identity(None), 2));
+    }
+}